@@ -1,4 +1,7 @@
-//! Private blockstores for use in the FVM.
+//! Blockstore wrappers used by the FVM to buffer and fork state.
 
 mod buffered;
 pub use buffered::BufferedBlockstore;
+
+mod overlay;
+pub use overlay::OverlayBlockstore;
@@ -0,0 +1,438 @@
+// Copyright 2019-2022 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::io::{Cursor, Read, Seek};
+
+use anyhow::{anyhow, Result};
+use byteorder::{BigEndian, ByteOrder, ReadBytesExt};
+use cid::Cid;
+#[cfg(feature = "car-export")]
+use futures::{stream, AsyncWrite};
+use fvm_ipld_blockstore::{Blockstore, Buffered};
+#[cfg(feature = "car-export")]
+use fvm_ipld_car::CarHeader;
+use fvm_ipld_encoding::DAG_CBOR;
+use fvm_shared::commcid::{FIL_COMMITMENT_SEALED, FIL_COMMITMENT_UNSEALED};
+
+/// A copy-on-write `Blockstore` layered over a base store.
+///
+/// Reads fall through the stack of write layers (newest first) and finally to the base store.
+/// Writes always land in the topmost layer and are never written to the base store unless
+/// explicitly flushed. Layers can be nested with [`OverlayBlockstore::fork`], and the topmost
+/// layer can then either be merged into its parent with [`OverlayBlockstore::commit`] or thrown
+/// away with [`OverlayBlockstore::discard`].
+///
+/// This type is not threadsafe and can only be used in synchronous contexts.
+#[derive(Debug)]
+pub struct OverlayBlockstore<BS> {
+    base: BS,
+    layers: RefCell<Vec<HashMap<Cid, Vec<u8>>>>,
+}
+
+impl<BS> OverlayBlockstore<BS>
+where
+    BS: Blockstore,
+{
+    pub fn new(base: BS) -> Self {
+        Self {
+            base,
+            layers: RefCell::new(vec![HashMap::new()]),
+        }
+    }
+
+    pub fn into_inner(self) -> BS {
+        self.base
+    }
+
+    /// Returns a reference to the underlying base store.
+    pub fn base(&self) -> &BS {
+        &self.base
+    }
+
+    /// Returns the number of nested forks on top of the bottom write layer.
+    pub fn depth(&self) -> usize {
+        self.layers.borrow().len() - 1
+    }
+
+    /// Pushes a new write layer. All subsequent writes go to this layer until it's either
+    /// committed or discarded.
+    pub fn fork(&self) {
+        self.layers.borrow_mut().push(HashMap::new())
+    }
+
+    /// Merges the topmost write layer into its parent.
+    pub fn commit(&self) -> Result<()> {
+        let mut layers = self.layers.borrow_mut();
+        if layers.len() < 2 {
+            return Err(anyhow!("commit failed: no fork to commit"));
+        }
+        let top = layers.pop().expect("checked above");
+        layers.last_mut().expect("checked above").extend(top);
+        Ok(())
+    }
+
+    /// Drops the topmost write layer and all blocks written to it.
+    pub fn discard(&self) -> Result<()> {
+        let mut layers = self.layers.borrow_mut();
+        if layers.len() < 2 {
+            return Err(anyhow!("discard failed: no fork to discard"));
+        }
+        layers.pop();
+        Ok(())
+    }
+
+    /// Returns every block written to the overlay (across all layers) that hasn't been discarded.
+    ///
+    /// The order of the returned blocks is unspecified.
+    pub fn delta(&self) -> Vec<(Cid, Vec<u8>)> {
+        let layers = self.layers.borrow();
+        let mut seen = HashSet::new();
+        layers
+            .iter()
+            .rev()
+            .flat_map(|l| l.iter())
+            .filter(|(k, _)| seen.insert(**k))
+            .map(|(k, v)| (*k, v.clone()))
+            .collect()
+    }
+
+    /// Returns the blocks written to the overlay that are reachable from `root`, children before
+    /// parents. Blocks that are only present in the base store are not included.
+    pub fn reachable(&self, root: &Cid) -> Result<Vec<(Cid, Vec<u8>)>> {
+        let layers = self.layers.borrow();
+        let lookup = |k: &Cid| layers.iter().rev().find_map(|l| l.get(k)).map(Vec::as_slice);
+        let mut buffer = Vec::new();
+        copy_rec(&lookup, *root, &mut buffer)?;
+
+        let mut seen = HashSet::new();
+        Ok(buffer
+            .into_iter()
+            .filter(|(k, _)| seen.insert(*k))
+            .map(|(k, v)| (k, v.to_vec()))
+            .collect())
+    }
+
+    /// Writes the written delta (see [`OverlayBlockstore::delta`]) to `writer` as a CAR file with
+    /// the given roots. Requires the `car-export` feature.
+    #[cfg(feature = "car-export")]
+    pub async fn export_car<W>(&self, roots: Vec<Cid>, writer: &mut W) -> Result<()>
+    where
+        W: AsyncWrite + Send + Unpin,
+    {
+        let mut blocks = stream::iter(self.delta());
+        CarHeader::from(roots)
+            .write_stream_async(writer, &mut blocks)
+            .await
+            .map_err(|e| anyhow!("failed to export overlay as CAR: {}", e))
+    }
+}
+
+impl<BS> Buffered for OverlayBlockstore<BS>
+where
+    BS: Blockstore,
+{
+    /// Flushes the blocks reachable from the root node to the base store.
+    /// This will recursively traverse all write layers and write all data connected by links to
+    /// this root Cid. Calling flush will not reset the write layers.
+    fn flush(&self, root: &Cid) -> Result<()> {
+        let layers = self.layers.borrow();
+        let lookup = |k: &Cid| layers.iter().rev().find_map(|l| l.get(k)).map(Vec::as_slice);
+        let mut buffer = Vec::new();
+        copy_rec(&lookup, *root, &mut buffer)?;
+
+        self.base.put_many_keyed(buffer)?;
+
+        Ok(())
+    }
+}
+
+/// Given a CBOR encoded Buffer, returns a tuple of:
+/// the type of the CBOR object along with extra
+/// elements we expect to read. More info on this can be found in
+/// Appendix C. of RFC 7049 which defines the CBOR specification.
+/// This was implemented because the CBOR library we use does not expose low
+/// methods like this, requiring us to deserialize the whole CBOR payload, which
+/// is unnecessary and quite inefficient for our usecase here.
+fn cbor_read_header_buf<B: Read>(br: &mut B, scratch: &mut [u8]) -> anyhow::Result<(u8, usize)> {
+    let first = br.read_u8()?;
+    let maj = (first & 0xe0) >> 5;
+    let low = first & 0x1f;
+
+    if low < 24 {
+        Ok((maj, low as usize))
+    } else if low == 24 {
+        let val = br.read_u8()?;
+        if val < 24 {
+            return Err(anyhow!(
+                "cbor input was not canonical (lval 24 with value < 24)"
+            ));
+        }
+        Ok((maj, val as usize))
+    } else if low == 25 {
+        br.read_exact(&mut scratch[..2])?;
+        let val = BigEndian::read_u16(&scratch[..2]);
+        if val <= u8::MAX as u16 {
+            return Err(anyhow!(
+                "cbor input was not canonical (lval 25 with value <= MaxUint8)"
+            ));
+        }
+        Ok((maj, val as usize))
+    } else if low == 26 {
+        br.read_exact(&mut scratch[..4])?;
+        let val = BigEndian::read_u32(&scratch[..4]);
+        if val <= u16::MAX as u32 {
+            return Err(anyhow!(
+                "cbor input was not canonical (lval 26 with value <= MaxUint16)"
+            ));
+        }
+        Ok((maj, val as usize))
+    } else if low == 27 {
+        br.read_exact(&mut scratch[..8])?;
+        let val = BigEndian::read_u64(&scratch[..8]);
+        if val <= u32::MAX as u64 {
+            return Err(anyhow!(
+                "cbor input was not canonical (lval 27 with value <= MaxUint32)"
+            ));
+        }
+        Ok((maj, val as usize))
+    } else {
+        Err(anyhow!("invalid header cbor_read_header_buf"))
+    }
+}
+
+/// Given a CBOR serialized IPLD buffer, read through all of it and return all the Links.
+/// This function is useful because it is quite a bit more fast than doing this recursively on a
+/// deserialized IPLD object.
+fn scan_for_links<B: Read + Seek, F>(buf: &mut B, mut callback: F) -> Result<()>
+where
+    F: FnMut(Cid) -> anyhow::Result<()>,
+{
+    let mut scratch: [u8; 100] = [0; 100];
+    let mut remaining = 1;
+    while remaining > 0 {
+        let (maj, extra) = cbor_read_header_buf(buf, &mut scratch)?;
+        match maj {
+            // MajUnsignedInt, MajNegativeInt, MajOther
+            0 | 1 | 7 => {}
+            // MajByteString, MajTextString
+            2 | 3 => {
+                buf.seek(std::io::SeekFrom::Current(extra as i64))?;
+            }
+            // MajTag
+            6 => {
+                // Check if the tag refers to a CID
+                if extra == 42 {
+                    let (maj, extra) = cbor_read_header_buf(buf, &mut scratch)?;
+                    // The actual CID is expected to be a byte string
+                    if maj != 2 {
+                        return Err(anyhow!("expected cbor type byte string in input"));
+                    }
+                    if extra > 100 {
+                        return Err(anyhow!("string in cbor input too long"));
+                    }
+                    buf.read_exact(&mut scratch[..extra])?;
+                    let c = Cid::try_from(&scratch[1..extra])?;
+                    callback(c)?;
+                } else {
+                    remaining += 1;
+                }
+            }
+            // MajArray
+            4 => {
+                remaining += extra;
+            }
+            // MajMap
+            5 => {
+                remaining += extra * 2;
+            }
+            _ => {
+                return Err(anyhow!("unhandled cbor type: {}", maj));
+            }
+        }
+        remaining -= 1;
+    }
+    Ok(())
+}
+
+/// Collects the IPLD DAG under `root` that's present in the cache, children before parents.
+fn copy_rec<'a, F>(cache: &F, root: Cid, buffer: &mut Vec<(Cid, &'a [u8])>) -> Result<()>
+where
+    F: Fn(&Cid) -> Option<&'a [u8]>,
+{
+    const DAG_RAW: u64 = 0x55;
+    const BLAKE2B_256: u64 = 0xb220;
+    const BLAKE2B_LEN: u8 = 32;
+    const IDENTITY: u64 = 0x0;
+
+    // Differences from lotus (vm.Copy):
+    // 1. We assume that if we don't have a block in our buffer, it must already be in the client
+    //    and don't check. This should only happen if the client is missing state.
+    // 2. We always write-back new blocks, even if the client already has them. We haven't noticed a
+    //    perf impact.
+
+    // TODO(M2): Make this not cbor specific.
+    match (root.codec(), root.hash().code(), root.hash().size()) {
+        // Allow non-truncated blake2b-256 raw/cbor (code/state)
+        (DAG_RAW | DAG_CBOR, BLAKE2B_256, BLAKE2B_LEN) => (),
+        // Ignore raw identity cids (fake code cids)
+        (DAG_RAW, IDENTITY, _) => return Ok(()),
+        // Copy links from cbor identity cids.
+        // We shouldn't be creating these at the moment, but lotus' vm.Copy supports them.
+        (DAG_CBOR, IDENTITY, _) => {
+            return scan_for_links(&mut Cursor::new(root.hash().digest()), |link| {
+                copy_rec(cache, link, buffer)
+            })
+        }
+        // Ignore commitments (not even going to check the hash function.
+        (FIL_COMMITMENT_UNSEALED | FIL_COMMITMENT_SEALED, _, _) => return Ok(()),
+        // Fail on anything else. We usually want to continue on error, but there's really no going
+        // back from here.
+        (codec, hash, length) => {
+            return Err(anyhow!(
+                "cid {root} has unexpected codec ({codec}), hash ({hash}), or length ({length})"
+            ))
+        }
+    }
+
+    // If we don't have the block, we assume it's already in the datastore.
+    //
+    // The alternative would be to check if it's in the datastore, but that's likely even more
+    // expensive. And there wouldn't be much we could do at that point but abort the block.
+    let block = match cache(&root) {
+        Some(blk) => blk,
+        None => return Ok(()),
+    };
+
+    // At the moment, we only expect dag-cbor and raw.
+    // In M2, we'll need to copy explicitly.
+    if root.codec() == DAG_CBOR {
+        // TODO(M2): Make this non-recursive.
+        scan_for_links(&mut Cursor::new(block), |link| {
+            copy_rec(cache, link, buffer)
+        })?;
+    }
+
+    // Finally, push the block. We do this _last_ so that we always include write before parents.
+    buffer.push((root, block));
+
+    Ok(())
+}
+
+impl<BS> Blockstore for OverlayBlockstore<BS>
+where
+    BS: Blockstore,
+{
+    fn get(&self, cid: &Cid) -> Result<Option<Vec<u8>>> {
+        let layers = self.layers.borrow();
+        Ok(match layers.iter().rev().find_map(|l| l.get(cid)) {
+            Some(data) => Some(data.clone()),
+            None => self.base.get(cid)?,
+        })
+    }
+
+    fn put_keyed(&self, cid: &Cid, buf: &[u8]) -> Result<()> {
+        self.layers
+            .borrow_mut()
+            .last_mut()
+            .expect("overlay always has at least one layer")
+            .insert(*cid, Vec::from(buf));
+        Ok(())
+    }
+
+    fn has(&self, k: &Cid) -> Result<bool> {
+        if self.layers.borrow().iter().any(|l| l.contains_key(k)) {
+            Ok(true)
+        } else {
+            Ok(self.base.has(k)?)
+        }
+    }
+
+    fn put_many_keyed<D, I>(&self, blocks: I) -> Result<()>
+    where
+        Self: Sized,
+        D: AsRef<[u8]>,
+        I: IntoIterator<Item = (Cid, D)>,
+    {
+        self.layers
+            .borrow_mut()
+            .last_mut()
+            .expect("overlay always has at least one layer")
+            .extend(blocks.into_iter().map(|(k, v)| (k, v.as_ref().into())));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cid::multihash::Code;
+    use fvm_ipld_blockstore::{Blockstore, MemoryBlockstore};
+    use fvm_ipld_encoding::CborStore;
+
+    use super::*;
+
+    #[test]
+    fn nested_fork_commit_discard() {
+        let mem = MemoryBlockstore::default();
+        let overlay = OverlayBlockstore::new(&mem);
+
+        let a = overlay.put_cbor(&1u8, Code::Blake2b256).unwrap();
+        overlay.fork();
+        let b = overlay.put_cbor(&2u8, Code::Blake2b256).unwrap();
+        overlay.fork();
+        let c = overlay.put_cbor(&3u8, Code::Blake2b256).unwrap();
+        assert_eq!(overlay.depth(), 2);
+        assert_eq!(overlay.get_cbor::<u8>(&a).unwrap(), Some(1));
+        assert_eq!(overlay.get_cbor::<u8>(&c).unwrap(), Some(3));
+
+        // Throw away the innermost fork.
+        overlay.discard().unwrap();
+        assert_eq!(overlay.get_cbor::<u8>(&c).unwrap(), None);
+        assert_eq!(overlay.get_cbor::<u8>(&b).unwrap(), Some(2));
+
+        // Keep the remaining one.
+        overlay.commit().unwrap();
+        assert_eq!(overlay.depth(), 0);
+        assert_eq!(overlay.get_cbor::<u8>(&b).unwrap(), Some(2));
+        assert!(overlay.commit().is_err());
+        assert!(overlay.discard().is_err());
+
+        // Nothing reached the base store.
+        assert_eq!(mem.get_cbor::<u8>(&a).unwrap(), None);
+        assert_eq!(mem.get_cbor::<u8>(&b).unwrap(), None);
+
+        let mut delta: Vec<_> = overlay.delta().into_iter().map(|(k, _)| k).collect();
+        delta.sort();
+        let mut expected = vec![a, b];
+        expected.sort();
+        assert_eq!(delta, expected);
+    }
+
+    #[test]
+    fn flush_reachable_from_forks() {
+        let mem = MemoryBlockstore::default();
+        let overlay = OverlayBlockstore::new(&mem);
+
+        let leaf = overlay.put_cbor(&"leaf", Code::Blake2b256).unwrap();
+        overlay.fork();
+        let root = overlay.put_cbor(&(leaf, 1u8), Code::Blake2b256).unwrap();
+        let unconnected = overlay.put_cbor(&27u8, Code::Blake2b256).unwrap();
+
+        let reachable: Vec<_> = overlay
+            .reachable(&root)
+            .unwrap()
+            .into_iter()
+            .map(|(k, _)| k)
+            .collect();
+        assert_eq!(reachable, vec![leaf, root]);
+
+        overlay.flush(&root).unwrap();
+        assert_eq!(
+            mem.get_cbor::<(Cid, u8)>(&root).unwrap(),
+            Some((leaf, 1u8))
+        );
+        assert_eq!(mem.get_cbor::<String>(&leaf).unwrap(), Some("leaf".into()));
+        assert_eq!(mem.get_cbor::<u8>(&unconnected).unwrap(), None);
+    }
+}
@@ -109,6 +109,8 @@ pub enum Cause {
         /// The informational syscall message.
         message: String,
     },
+    /// The original cause was exceeding a resource limit.
+    ResourceLimitExceeded(ResourceLimitExceeded),
     /// The original cause was a fatal error.
     Fatal {
         /// The alternate-formatted message from the anyhow error.
@@ -130,6 +132,11 @@ impl Cause {
         }
     }
 
+    /// Records an exceeded resource limit as the cause of a backtrace.
+    pub fn from_limit(limit: ResourceLimitExceeded) -> Self {
+        Self::ResourceLimitExceeded(limit)
+    }
+
     /// Records a fatal error as the cause of a backtrace.
     pub fn from_fatal(err: anyhow::Error) -> Self {
         Self::Fatal {
@@ -154,6 +161,7 @@ impl Display for Cause {
                     module, function, &message, *error as u32, error,
                 )
             }
+            Cause::ResourceLimitExceeded(limit) => write!(f, "{}", limit),
             Cause::Fatal {
                 error_msg,
                 backtrace,
@@ -163,3 +171,88 @@ impl Display for Cause {
         }
     }
 }
+
+/// A resource limit enforced while executing a message.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ResourceLimit {
+    /// The maximum call depth ([`NetworkConfig::max_call_depth`]).
+    ///
+    /// [`NetworkConfig::max_call_depth`]: crate::machine::NetworkConfig::max_call_depth
+    CallDepth,
+    /// The maximum Wasm stack height of an instance ([`NetworkConfig::max_wasm_stack`]).
+    ///
+    /// [`NetworkConfig::max_wasm_stack`]: crate::machine::NetworkConfig::max_wasm_stack
+    WasmStack,
+    /// The maximum memory of a Wasm instance ([`NetworkConfig::max_inst_memory_bytes`]).
+    ///
+    /// [`NetworkConfig::max_inst_memory_bytes`]: crate::machine::NetworkConfig::max_inst_memory_bytes
+    InstanceMemory,
+    /// The maximum memory of all Wasm instances on the call stack
+    /// ([`NetworkConfig::max_exec_memory_bytes`]).
+    ///
+    /// [`NetworkConfig::max_exec_memory_bytes`]: crate::machine::NetworkConfig::max_exec_memory_bytes
+    ExecMemory,
+    /// The maximum number of IPLD blocks an actor may hold.
+    Blocks,
+}
+
+impl ResourceLimit {
+    /// The exit code an actor fails with when it's known to have failed because it exceeded this
+    /// limit, if any. These exit codes are only used when
+    /// [`NetworkConfig::limit_exit_codes`](crate::machine::NetworkConfig::limit_exit_codes) is
+    /// enabled, and only for the Wasm stack limit and for memory limits exceeded while
+    /// instantiating the actor:
+    ///
+    /// | Limit                                     | Exit code                               |
+    /// |-------------------------------------------|-----------------------------------------|
+    /// | [`CallDepth`](Self::CallDepth)            | None                                    |
+    /// | [`WasmStack`](Self::WasmStack)            | [`ExitCode::SYS_STACK_LIMIT_EXCEEDED`]  |
+    /// | [`InstanceMemory`](Self::InstanceMemory)  | [`ExitCode::SYS_MEMORY_LIMIT_EXCEEDED`] |
+    /// | [`ExecMemory`](Self::ExecMemory)          | [`ExitCode::SYS_MEMORY_LIMIT_EXCEEDED`] |
+    /// | [`Blocks`](Self::Blocks)                  | None                                    |
+    ///
+    /// Exceeding the call depth or block limits is only reported through
+    /// [`Cause::ResourceLimitExceeded`].
+    pub fn exit_code(self) -> Option<ExitCode> {
+        match self {
+            ResourceLimit::WasmStack => Some(ExitCode::SYS_STACK_LIMIT_EXCEEDED),
+            ResourceLimit::InstanceMemory | ResourceLimit::ExecMemory => {
+                Some(ExitCode::SYS_MEMORY_LIMIT_EXCEEDED)
+            }
+            ResourceLimit::CallDepth | ResourceLimit::Blocks => None,
+        }
+    }
+}
+
+impl Display for ResourceLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ResourceLimit::CallDepth => "call depth",
+            ResourceLimit::WasmStack => "wasm stack height",
+            ResourceLimit::InstanceMemory => "instance memory bytes",
+            ResourceLimit::ExecMemory => "execution memory bytes",
+            ResourceLimit::Blocks => "ipld blocks",
+        })
+    }
+}
+
+/// An attempt to exceed a resource limit.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ResourceLimitExceeded {
+    /// The limit that was exceeded.
+    pub kind: ResourceLimit,
+    /// The value of the limit.
+    pub limit: u64,
+    /// The value that was attempted.
+    pub attempted: u64,
+}
+
+impl Display for ResourceLimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "resource limit exceeded: {} (limit {}, attempted {})",
+            self.kind, self.limit, self.attempted
+        )
+    }
+}
@@ -9,13 +9,16 @@ use fvm_shared::sys::BlockId;
 use fvm_shared::{ActorID, MethodNum, METHOD_SEND};
 use num_traits::Zero;
 
-use super::{Backtrace, CallManager, InvocationResult, NO_DATA_BLOCK_ID};
-use crate::call_manager::backtrace::Frame;
+use super::{
+    Backtrace, CallManager, InvocationResources, InvocationResult, PeakResources, ResourceLimit,
+    ResourceLimitExceeded, ResourceUsage, NO_DATA_BLOCK_ID,
+};
+use crate::call_manager::backtrace::{Cause, Frame};
 use crate::call_manager::FinishRet;
 use crate::gas::{Gas, GasTracker};
 use crate::kernel::{Block, BlockRegistry, ExecutionError, Kernel, Result, SyscallError};
 use crate::machine::limiter::ExecMemory;
-use crate::machine::{Engine, Machine};
+use crate::machine::{Engine, Machine, STACK_HEIGHT_MAX_NAME, STACK_HEIGHT_NAME};
 use crate::state_tree::ActorState;
 use crate::syscalls::error::Abort;
 use crate::syscalls::{charge_for_exec, update_gas_available};
@@ -54,6 +57,8 @@ pub struct InnerDefaultCallManager<M: Machine> {
     limits: M::Limiter,
     /// Accumulator for events emitted in this call stack.
     events: EventsAccumulator,
+    /// The peak resources used by each invocation, in call order.
+    invocation_resources: Vec<InvocationResources>,
 }
 
 #[doc(hidden)]
@@ -104,6 +109,7 @@ where
             invocation_count: 0,
             limits,
             events: Default::default(),
+            invocation_resources: Vec::new(),
         })))
     }
 
@@ -118,6 +124,8 @@ where
         method: MethodNum,
         params: Option<Block>,
         value: &TokenAmount,
+        gas_limit: Option<Gas>,
+        read_only: bool,
     ) -> Result<InvocationResult>
     where
         K: Kernel<CallManager = Self>,
@@ -135,8 +143,12 @@ where
             });
         }
 
-        let result =
-            self.with_stack_frame(|s| s.send_unchecked::<K>(from, to, method, params, value));
+        let result = self.with_stack_frame(|s| match gas_limit {
+            Some(limit) => s.with_gas_limit(limit, |s| {
+                s.send_unchecked::<K>(from, to, method, params, value, read_only)
+            }),
+            None => s.send_unchecked::<K>(from, to, method, params, value, read_only),
+        });
 
         if self.machine.context().tracing {
             self.trace(match &result {
@@ -190,6 +202,8 @@ where
             mut gas_tracker,
             mut exec_trace,
             events,
+            limits,
+            invocation_resources,
             ..
         } = *self.0.take().expect("call manager is poisoned");
 
@@ -203,12 +217,25 @@ where
 
         let events = events.finish();
 
+        // The limiter is back at the top-level stack frame, so its peak covers the whole message.
+        let peak = invocation_resources.iter().fold(
+            PeakResources {
+                exec_memory_bytes: limits.peak_exec_memory_bytes(),
+                ..Default::default()
+            },
+            |peak, inv| peak.max(inv.peak),
+        );
+
         (
             FinishRet {
                 gas_used,
                 backtrace,
                 exec_trace,
                 events,
+                resource_usage: ResourceUsage {
+                    peak,
+                    invocations: invocation_resources,
+                },
             },
             machine,
         )
@@ -311,6 +338,7 @@ where
             fvm_shared::METHOD_CONSTRUCTOR,
             Some(Block::new(DAG_CBOR, params)),
             &TokenAmount::zero(),
+            false,
         )?;
 
         Ok(id)
@@ -337,6 +365,7 @@ where
         method: MethodNum,
         params: Option<Block>,
         value: &TokenAmount,
+        read_only: bool,
     ) -> Result<InvocationResult>
     where
         K: Kernel<CallManager = Self>,
@@ -344,6 +373,12 @@ where
         // Get the receiver; this will resolve the address.
         let to = match self.state_tree().lookup_id(&to)? {
             Some(addr) => addr,
+            // Sending to a new address creates an actor, which read-only calls may not do.
+            None if read_only => {
+                return Err(
+                    syscall_error!(ReadOnly; "cannot create actor {} in read-only mode", to).into(),
+                )
+            }
             None => match to.payload() {
                 Payload::BLS(_) | Payload::Secp256k1(_) => {
                     // Try to create an account actor if the receiver is a key address.
@@ -362,7 +397,7 @@ where
 
         // Do the actual send.
 
-        self.send_resolved::<K>(from, to, method, params, value)
+        self.send_resolved::<K>(from, to, method, params, value, read_only)
     }
 
     /// Send with resolved addresses.
@@ -373,6 +408,7 @@ where
         method: MethodNum,
         params: Option<Block>,
         value: &TokenAmount,
+        read_only: bool,
     ) -> Result<InvocationResult>
     where
         K: Kernel<CallManager = Self>,
@@ -388,6 +424,11 @@ where
 
         // Transfer, if necessary.
         if !value.is_zero() {
+            if read_only {
+                return Err(
+                    syscall_error!(ReadOnly; "cannot transfer value in read-only mode").into(),
+                );
+            }
             self.machine.transfer(from, to, value)?;
         }
 
@@ -408,6 +449,15 @@ where
         // Increment invocation count
         self.invocation_count += 1;
 
+        // Reserve a slot for the invocation's resource usage, so invocations are listed in call
+        // order.
+        let resources_idx = self.invocation_resources.len();
+        self.invocation_resources.push(InvocationResources {
+            actor_id: to,
+            method,
+            peak: Default::default(),
+        });
+
         // This is a cheap operation as it doesn't actually clone the struct,
         // it returns a referenced copy.
         let engine: Engine = self.engine().clone();
@@ -422,13 +472,33 @@ where
                 |_| syscall_error!(NotFound; "actor code cid does not exist {}", &state.code),
             )?;
 
+        // Look up the syscalls the actor may call.
+        let capabilities = self
+            .context()
+            .capabilities
+            .groups(&state.code, self.builtin_actors());
+
+        let max_wasm_stack = self.context().max_wasm_stack;
+        let limit_exit_codes = self.context().limit_exit_codes;
+
         log::trace!("calling {} -> {}::{}", from, to, method);
         self.map_mut(|cm| {
             // Make the kernel.
-            let kernel = K::new(cm, block_registry, from, to, method, value.clone());
+            let kernel = K::new(
+                cm,
+                block_registry,
+                from,
+                to,
+                method,
+                value.clone(),
+                read_only,
+            );
 
             // Make a store.
-            let mut store = engine.new_store(kernel);
+            let mut store = engine.new_store(kernel, capabilities);
+
+            // The instance and its memory, once instantiated.
+            let mut exports = None;
 
             // From this point on, there are no more syscall errors, only aborts.
             let result: std::result::Result<BlockId, Abort> = (|| {
@@ -436,7 +506,19 @@ where
                 let instance = engine
                     .get_instance(&mut store, &state.code)
                     .and_then(|i| i.context("actor code not found"))
-                    .map_err(Abort::Fatal)?;
+                    .map_err(|e| {
+                        // Instantiation fails if the initial memory exceeds the memory limits.
+                        let limiter = store.data_mut().kernel.limiter_mut();
+                        match limiter
+                            .memory_limit_exceeded()
+                            .and_then(|limit| limit.kind.exit_code())
+                        {
+                            Some(code) if limit_exit_codes => {
+                                Abort::Exit(code, format!("failed to instantiate actor: {:#}", e))
+                            }
+                            _ => Abort::Fatal(e),
+                        }
+                    })?;
 
                 // Resolve and store a reference to the exported memory.
                 let memory = instance
@@ -445,6 +527,7 @@ where
                     .map_err(Abort::Fatal)?;
 
                 store.data_mut().memory = memory;
+                exports = Some((instance, memory));
 
                 // Lookup the invoke method.
                 let invoke: wasmtime::TypedFunc<(u32,), u32> = instance
@@ -470,10 +553,59 @@ where
                 Ok(res?)
             })();
 
+            // Measure the instance's peak memory and stack height. The memory can only grow, so its
+            // final size is its peak.
+            let mut get_stack_height = |instance: wasmtime::Instance, name| {
+                instance
+                    .get_global(&mut store, name)
+                    .and_then(|g| g.get(&mut store).i32())
+                    .unwrap_or_default() as u32
+            };
+            let (wasm_stack, stack_height) = match exports {
+                Some((instance, _)) => (
+                    get_stack_height(instance, STACK_HEIGHT_MAX_NAME),
+                    get_stack_height(instance, STACK_HEIGHT_NAME),
+                ),
+                None => (0, 0),
+            };
+            let instance_memory_bytes = exports.map_or(0, |(_, memory)| memory.data_size(&store));
+
             let invocation_data = store.into_data();
             let last_error = invocation_data.last_error;
             let (mut cm, block_registry) = invocation_data.kernel.into_inner();
 
+            // We're still in the invocation's stack frame, so the limiter's peak covers this
+            // invocation and the calls it made.
+            cm.invocation_resources[resources_idx].peak = PeakResources {
+                instance_memory_bytes,
+                exec_memory_bytes: cm.limits.peak_exec_memory_bytes(),
+                wasm_stack,
+            };
+
+            // Attribute traps to the resource limit that caused them, but only when that's certain:
+            // the stack limiter leaves the stack height above the limit when it traps (the height
+            // reads as zero without stack height reporting). A trap following a failed syscall or
+            // memory growth may well be unrelated to the failure.
+            let limit_exceeded = match &result {
+                Err(Abort::Exit(ExitCode::SYS_ILLEGAL_INSTRUCTION, _))
+                    if stack_height > max_wasm_stack =>
+                {
+                    Some(ResourceLimitExceeded {
+                        kind: ResourceLimit::WasmStack,
+                        limit: max_wasm_stack as u64,
+                        attempted: stack_height as u64,
+                    })
+                }
+                _ => None,
+            };
+            let limit_exit_code = limit_exceeded.and_then(|limit| limit.kind.exit_code());
+            let result = match (result, limit_exit_code) {
+                (Err(Abort::Exit(_, message)), Some(code)) if limit_exit_codes => {
+                    Err(Abort::Exit(code, message))
+                }
+                (result, _) => result,
+            };
+
             // Resolve the return block's ID into an actual block, converting to an abort if it
             // doesn't exist.
             let result = result.and_then(|ret_id| {
@@ -493,7 +625,9 @@ where
             let ret = match result {
                 Ok(ret) => Ok(InvocationResult::Return(ret.cloned())),
                 Err(abort) => {
-                    if let Some(err) = last_error {
+                    if let Some(limit) = limit_exceeded {
+                        cm.backtrace.begin(Cause::from_limit(limit));
+                    } else if let Some(err) = last_error {
                         cm.backtrace.begin(err);
                     }
 
@@ -552,6 +686,24 @@ where
         replace_with::replace_with_and_return(self, || DefaultCallManager(None), f)
     }
 
+    /// Runs a send with a nested gas budget of at most `limit`. If the callee exhausts that budget
+    /// while the caller still has gas left, the send fails with [`ExitCode::SYS_OUT_OF_GAS`]
+    /// instead of aborting the whole message.
+    fn with_gas_limit<F>(&mut self, limit: Gas, f: F) -> Result<InvocationResult>
+    where
+        F: FnOnce(&mut Self) -> Result<InvocationResult>,
+    {
+        self.gas_tracker.push_limit(limit);
+        let res = f(self);
+        self.gas_tracker.pop_limit();
+        match res {
+            Err(ExecutionError::OutOfGas) if self.gas_tracker.gas_available() > Gas::zero() => {
+                Ok(InvocationResult::Failure(ExitCode::SYS_OUT_OF_GAS))
+            }
+            res => res,
+        }
+    }
+
     /// Check that we're not violating the call stack depth, then envelope a call
     /// with an increase/decrease of the depth to make sure none of them are missed.
     fn with_stack_frame<F, V>(&mut self, f: F) -> Result<V>
@@ -571,7 +723,15 @@ where
         //
         // NOTE: Unlike the FVM, Lotus adds _then_ checks. It does this because the
         // `call_stack_depth` in lotus is 0 for the top-level call, unlike in the FVM where it's 1.
-        if self.call_stack_depth > self.machine.context().max_call_depth {
+        let max_call_depth = self.machine.context().max_call_depth;
+        if self.call_stack_depth > max_call_depth {
+            // Record the exceeded limit, so it's reported as the cause of the failing send.
+            self.limits
+                .set_syscall_limit_exceeded(ResourceLimitExceeded {
+                    kind: ResourceLimit::CallDepth,
+                    limit: max_call_depth as u64,
+                    attempted: self.call_stack_depth as u64,
+                });
             let sys_err = syscall_error!(LimitExceeded, "message execution exceeds call depth");
             if self.machine.context().tracing {
                 self.trace(ExecutionEvent::CallError(sys_err.clone()));
@@ -3,14 +3,14 @@ use fvm_shared::econ::TokenAmount;
 use fvm_shared::error::ExitCode;
 use fvm_shared::{ActorID, MethodNum};
 
-use crate::gas::{GasCharge, GasTracker, PriceList};
+use crate::gas::{Gas, GasCharge, GasTracker, PriceList};
 use crate::kernel::{self, Result};
 use crate::machine::{Machine, MachineContext};
 use crate::state_tree::StateTree;
 use crate::Kernel;
 
 pub mod backtrace;
-pub use backtrace::Backtrace;
+pub use backtrace::{Backtrace, ResourceLimit, ResourceLimitExceeded};
 
 mod default;
 
@@ -52,6 +52,15 @@ pub trait CallManager: 'static {
 
     /// Send a message. The type parameter `K` specifies the the _kernel_ on top of which the target
     /// actor should execute.
+    ///
+    /// If a `gas_limit` is specified, the callee may use at most that much gas. Running out of it
+    /// fails the send with [`ExitCode::SYS_OUT_OF_GAS`] and leaves the caller's remaining gas
+    /// untouched.
+    ///
+    /// In `read_only` mode, the callee and everything it calls may not mutate state: value
+    /// transfers, actor creation and state changes fail with
+    /// [`ErrorNumber::ReadOnly`](fvm_shared::error::ErrorNumber::ReadOnly).
+    #[allow(clippy::too_many_arguments)]
     fn send<K: Kernel<CallManager = Self>>(
         &mut self,
         from: ActorID,
@@ -59,6 +68,8 @@ pub trait CallManager: 'static {
         method: MethodNum,
         params: Option<kernel::Block>,
         value: &TokenAmount,
+        gas_limit: Option<Gas>,
+        read_only: bool,
     ) -> Result<InvocationResult>;
 
     /// Execute some operation (usually a send) within a transaction.
@@ -94,7 +105,7 @@ pub trait CallManager: 'static {
 
     /// Returns the current price list.
     fn price_list(&self) -> &PriceList {
-        self.machine().context().price_list
+        &self.machine().context().price_list
     }
 
     /// Returns the machine context.
@@ -167,4 +178,48 @@ pub struct FinishRet {
     pub backtrace: Backtrace,
     pub exec_trace: ExecutionTrace,
     pub events: Vec<StampedEvent>,
+    pub resource_usage: ResourceUsage,
+}
+
+/// The peak Wasm resources used by an execution. These are measured deterministically, in the same
+/// units as the corresponding limits in [`NetworkConfig`](crate::machine::NetworkConfig).
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct PeakResources {
+    /// The peak memory used by a single Wasm instance, in bytes.
+    pub instance_memory_bytes: usize,
+    /// The peak memory used by all Wasm instances on the call stack at once, in bytes.
+    pub exec_memory_bytes: usize,
+    /// The peak Wasm stack height reached by a single Wasm instance. Only measured with
+    /// [`NetworkConfig::wasm_stack_reporting`](crate::machine::NetworkConfig::wasm_stack_reporting)
+    /// enabled, zero otherwise.
+    pub wasm_stack: u32,
+}
+
+impl PeakResources {
+    /// Returns the peaks of both `self` and `other`.
+    pub fn max(self, other: Self) -> Self {
+        Self {
+            instance_memory_bytes: self.instance_memory_bytes.max(other.instance_memory_bytes),
+            exec_memory_bytes: self.exec_memory_bytes.max(other.exec_memory_bytes),
+            wasm_stack: self.wasm_stack.max(other.wasm_stack),
+        }
+    }
+}
+
+/// The peak resources used by a single actor invocation. The instance memory and stack height are
+/// those of the invoked actor's instance, while the exec memory includes the calls it made.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvocationResources {
+    pub actor_id: ActorID,
+    pub method: MethodNum,
+    pub peak: PeakResources,
+}
+
+/// The Wasm resources used while executing a message.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ResourceUsage {
+    /// The peak resources used across the whole message.
+    pub peak: PeakResources,
+    /// The peak resources used by each invocation, in call order.
+    pub invocations: Vec<InvocationResources>,
 }
@@ -0,0 +1,178 @@
+//! Builders and verifiers for the on-chain commitments to message execution results: the receipts
+//! AMT referenced by block headers, and the per-message events AMT referenced by each receipt.
+//!
+//! Light clients and indexers can use the verifiers to check receipts and events reported by a
+//! node against the roots committed on-chain.
+
+use anyhow::{anyhow, Context};
+use cid::Cid;
+use fvm_ipld_amt::{Amt, Amtv0};
+use fvm_ipld_blockstore::Blockstore;
+use fvm_shared::event::StampedEvent;
+use fvm_shared::receipt::Receipt;
+
+/// The bit width of the events AMT.
+pub const EVENTS_AMT_BITWIDTH: u32 = 5;
+
+/// Builds the receipts AMT (a legacy v0 AMT) from the receipts of a tipset's messages, in
+/// execution order, and returns its root.
+pub fn receipts_root<BS: Blockstore>(store: &BS, receipts: &[Receipt]) -> anyhow::Result<Cid> {
+    Amtv0::new_from_iter(store, receipts.iter().cloned()).context("failed to build receipts AMT")
+}
+
+/// Loads and decodes all receipts from the receipts AMT with the given root.
+pub fn load_receipts<BS: Blockstore>(store: &BS, root: &Cid) -> anyhow::Result<Vec<Receipt>> {
+    let amt = Amtv0::load(root, store).context("failed to load receipts AMT")?;
+    let mut receipts = Vec::with_capacity(amt.count() as usize);
+    amt.for_each(|i, receipt: &Receipt| {
+        check_index(i, receipts.len())?;
+        receipts.push(receipt.clone());
+        Ok(())
+    })
+    .context("failed to decode receipts AMT")?;
+    Ok(receipts)
+}
+
+/// Checks that the receipts AMT with the given root holds exactly the given receipts, in order.
+pub fn verify_receipts<BS: Blockstore>(
+    store: &BS,
+    root: &Cid,
+    receipts: &[Receipt],
+) -> anyhow::Result<()> {
+    verify_all("receipt", &load_receipts(store, root)?, receipts)
+}
+
+/// Builds the AMT of the events emitted by a single message and returns its root, or `None` if
+/// there are no events. This is the value of [`Receipt::events_root`].
+pub fn events_root<BS: Blockstore>(
+    store: &BS,
+    events: &[StampedEvent],
+) -> anyhow::Result<Option<Cid>> {
+    if events.is_empty() {
+        return Ok(None);
+    }
+    let vals = (0u64..).zip(events.iter().cloned());
+    let mut amt = Amt::new_from_sorted_with_bit_width(store, EVENTS_AMT_BITWIDTH, vals)
+        .context("failed to add events to AMT")?;
+    let root = amt.flush().context("failed to flush events AMT")?;
+    Ok(Some(root))
+}
+
+/// Loads and decodes all events from the events AMT with the given root.
+pub fn load_events<BS: Blockstore>(store: &BS, root: &Cid) -> anyhow::Result<Vec<StampedEvent>> {
+    let amt = Amt::load(root, store).context("failed to load events AMT")?;
+    let mut events = Vec::with_capacity(amt.count() as usize);
+    amt.for_each(|i, event: &StampedEvent| {
+        check_index(i, events.len())?;
+        events.push(event.clone());
+        Ok(())
+    })
+    .context("failed to decode events AMT")?;
+    Ok(events)
+}
+
+/// Checks that the events root of a receipt commits to exactly the given events, in order. A
+/// missing root commits to no events.
+pub fn verify_events<BS: Blockstore>(
+    store: &BS,
+    root: Option<&Cid>,
+    events: &[StampedEvent],
+) -> anyhow::Result<()> {
+    match root {
+        Some(root) => verify_all("event", &load_events(store, root)?, events),
+        None if events.is_empty() => Ok(()),
+        None => Err(anyhow!(
+            "expected {} events, but the receipt has no events root",
+            events.len()
+        )),
+    }
+}
+
+fn check_index(index: u64, expected: usize) -> anyhow::Result<()> {
+    if index != expected as u64 {
+        return Err(anyhow!(
+            "expected entry at index {}, found {}",
+            expected,
+            index
+        ));
+    }
+    Ok(())
+}
+
+fn verify_all<T: PartialEq>(kind: &str, committed: &[T], reported: &[T]) -> anyhow::Result<()> {
+    if committed.len() != reported.len() {
+        return Err(anyhow!(
+            "expected {} {}s, but {} were committed",
+            reported.len(),
+            kind,
+            committed.len()
+        ));
+    }
+    if let Some(i) = (0..committed.len()).find(|&i| committed[i] != reported[i]) {
+        return Err(anyhow!("{} {} does not match the committed value", kind, i));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use fvm_ipld_blockstore::MemoryBlockstore;
+    use fvm_ipld_encoding::RawBytes;
+    use fvm_shared::error::ExitCode;
+    use fvm_shared::event::{ActorEvent, Entry, Flags};
+
+    use super::*;
+
+    fn event(i: u64) -> StampedEvent {
+        StampedEvent::new(
+            i,
+            ActorEvent::from(vec![Entry {
+                flags: Flags::FLAG_INDEXED_ALL,
+                key: "i".to_owned(),
+                value: RawBytes::serialize(i).unwrap(),
+            }]),
+        )
+    }
+
+    #[test]
+    fn events() {
+        let store = MemoryBlockstore::default();
+        let events: Vec<_> = (0..100).map(event).collect();
+
+        assert_eq!(events_root(&store, &[]).unwrap(), None);
+        verify_events(&store, None, &[]).unwrap();
+        verify_events(&store, None, &events).unwrap_err();
+
+        let root = events_root(&store, &events).unwrap().unwrap();
+        assert_eq!(load_events(&store, &root).unwrap(), events);
+        verify_events(&store, Some(&root), &events).unwrap();
+        verify_events(&store, Some(&root), &events[1..]).unwrap_err();
+
+        let mut tampered = events.clone();
+        tampered[42] = event(43);
+        verify_events(&store, Some(&root), &tampered).unwrap_err();
+    }
+
+    #[test]
+    fn receipts() {
+        let store = MemoryBlockstore::default();
+        let events = events_root(&store, &[event(0)]).unwrap();
+        let receipts: Vec<_> = (0..20)
+            .map(|i| Receipt {
+                exit_code: ExitCode::new(i % 3),
+                return_data: RawBytes::serialize(i).unwrap(),
+                gas_used: 1000 + i as i64,
+                events_root: events.filter(|_| i % 2 == 0),
+            })
+            .collect();
+
+        let root = receipts_root(&store, &receipts).unwrap();
+        assert_eq!(load_receipts(&store, &root).unwrap(), receipts);
+        verify_receipts(&store, &root, &receipts).unwrap();
+
+        let mut tampered = receipts.clone();
+        tampered[7].gas_used += 1;
+        verify_receipts(&store, &root, &tampered).unwrap_err();
+        verify_receipts(&store, &root, &receipts[..19]).unwrap_err();
+    }
+}
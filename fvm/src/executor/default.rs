@@ -10,13 +10,13 @@ use fvm_shared::address::Payload;
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::error::{ErrorNumber, ExitCode};
 use fvm_shared::event::StampedEvent;
-use fvm_shared::message::Message;
+use fvm_shared::message::{Message, MessageError, MessageLimits};
 use fvm_shared::receipt::Receipt;
-use fvm_shared::ActorID;
+use fvm_shared::{ActorID, MethodNum};
 use num_traits::Zero;
 
-use super::{ApplyFailure, ApplyKind, ApplyRet, Executor};
-use crate::call_manager::{backtrace, Backtrace, CallManager, InvocationResult};
+use super::{ApplyFailure, ApplyKind, ApplyRet, CallRet, Executor};
+use crate::call_manager::{backtrace, Backtrace, CallManager, InvocationResult, ResourceUsage};
 use crate::gas::{Gas, GasCharge, GasOutputs};
 use crate::kernel::{Block, ClassifyResult, Context as _, ExecutionError, Kernel};
 use crate::machine::{Machine, BURNT_FUNDS_ACTOR_ADDR, REWARD_ACTOR_ADDR};
@@ -74,6 +74,7 @@ where
             exec_trace: ExecutionTrace,
             events_root: Option<Cid>,
             events: Vec<StampedEvent>, // TODO consider removing if nothing in the client ends up using it.
+            resource_usage: ResourceUsage,
         }
 
         // Apply the message.
@@ -100,7 +101,15 @@ where
 
             let result = cm.with_transaction(|cm| {
                 // Invoke the message.
-                let ret = cm.send::<K>(sender_id, msg.to, msg.method_num, params, &msg.value)?;
+                let ret = cm.send::<K>(
+                    sender_id,
+                    msg.to,
+                    msg.method_num,
+                    params,
+                    &msg.value,
+                    None,
+                    false,
+                )?;
 
                 // Charge for including the result (before we end the transaction).
                 if let InvocationResult::Return(value) = &ret {
@@ -127,6 +136,7 @@ where
                     exec_trace: res.exec_trace,
                     events_root,
                     events: res.events,
+                    resource_usage: res.resource_usage,
                 }),
                 machine,
             )
@@ -139,6 +149,7 @@ where
             exec_trace,
             events_root,
             events,
+            resource_usage,
         } = ret;
 
         // Extract the exit code and build the result of the message application.
@@ -179,11 +190,7 @@ where
                 // Errors indicate the message couldn't be dispatched at all
                 // (as opposed to failing during execution of the receiving actor).
                 // These errors are mapped to exit codes that persist on chain.
-                let exit_code = match err.1 {
-                    ErrorNumber::InsufficientFunds => ExitCode::SYS_INSUFFICIENT_FUNDS,
-                    ErrorNumber::NotFound => ExitCode::SYS_INVALID_RECEIVER,
-                    _ => ExitCode::SYS_ASSERTION_FAILED,
-                };
+                let exit_code = send_error_exit_code(err.1);
 
                 backtrace.begin(backtrace::Cause::from_syscall("send", "send", err));
                 Receipt {
@@ -220,16 +227,15 @@ where
             }
         };
 
-        let failure_info = if backtrace.is_empty() || receipt.exit_code.is_success() {
-            None
-        } else {
-            Some(ApplyFailure::MessageBacktrace(backtrace))
-        };
+        let failure_info = ApplyFailure::from_backtrace(backtrace, receipt.exit_code);
 
         match apply_kind {
-            ApplyKind::Explicit => {
-                self.finish_message(msg, receipt, failure_info, gas_cost, exec_trace, events)
-            }
+            ApplyKind::Explicit => self
+                .finish_message(msg, receipt, failure_info, gas_cost, exec_trace, events)
+                .map(|ret| ApplyRet {
+                    resource_usage,
+                    ..ret
+                }),
             ApplyKind::Implicit => Ok(ApplyRet {
                 msg_receipt: receipt,
                 penalty: TokenAmount::zero(),
@@ -242,6 +248,7 @@ where
                 failure_info,
                 exec_trace,
                 events,
+                resource_usage,
             }),
         }
     }
@@ -268,6 +275,93 @@ where
         self.0
     }
 
+    /// Invokes a method on an actor against the current state without a chain message, e.g. to
+    /// query an actor's getter methods. The call is made from `from`, which must exist, with no
+    /// value. Unlike [`Executor::execute_message`], it doesn't check or bump the sender's nonce,
+    /// doesn't charge the sender for gas, and reverts all state changes made by the call.
+    pub fn call_actor(
+        &mut self,
+        from: &Address,
+        to: Address,
+        method: MethodNum,
+        params: RawBytes,
+        gas_limit: i64,
+    ) -> Result<CallRet> {
+        let sender_id = self
+            .state_tree()
+            .lookup_id(from)
+            .with_context(|| format!("failed to lookup actor {}", from))?
+            .ok_or_else(|| anyhow!("sender {} does not exist", from))?;
+        // The nonce is only used to derive the addresses of actors created during the call.
+        let nonce = self
+            .state_tree()
+            .get_actor_id(sender_id)
+            .with_context(|| format!("failed to lookup actor {}", from))?
+            .ok_or_else(|| anyhow!("sender {} does not exist", from))?
+            .sequence;
+
+        let params = if params.is_empty() {
+            None
+        } else {
+            Some(Block::new(DAG_CBOR, params.bytes()))
+        };
+
+        let (result, gas_used, mut backtrace) = self.map_machine(|machine| {
+            let mut cm =
+                K::CallManager::new(machine, gas_limit, sender_id, nonce, TokenAmount::zero());
+
+            cm.state_tree_mut().begin_transaction();
+            let result = cm.send::<K>(
+                sender_id,
+                to,
+                method,
+                params,
+                &TokenAmount::zero(),
+                None,
+                false,
+            );
+            // Always revert, the call must not change the state.
+            if let Err(e) = cm.state_tree_mut().end_transaction(true) {
+                return (Err(e.into()), cm.finish().1);
+            }
+
+            let (res, machine) = cm.finish();
+            (Ok((result, res.gas_used, res.backtrace)), machine)
+        })?;
+
+        let (exit_code, return_data) = match result {
+            Ok(InvocationResult::Return(return_data)) => {
+                backtrace.clear();
+                (ExitCode::OK, return_data)
+            }
+            Ok(InvocationResult::Failure(exit_code)) => (exit_code, None),
+            Err(ExecutionError::OutOfGas) => (ExitCode::SYS_OUT_OF_GAS, None),
+            Err(ExecutionError::Syscall(err)) => {
+                let exit_code = send_error_exit_code(err.1);
+                backtrace.begin(backtrace::Cause::from_syscall("send", "send", err));
+                (exit_code, None)
+            }
+            Err(ExecutionError::Fatal(err)) => {
+                return Err(err.context(format!(
+                    "[from={}, to={}, m={}, h={}]",
+                    from,
+                    to,
+                    method,
+                    self.context().network_context.epoch,
+                )))
+            }
+        };
+
+        let failure_info = ApplyFailure::from_backtrace(backtrace, exit_code);
+
+        Ok(CallRet {
+            exit_code,
+            return_data,
+            gas_used,
+            failure_info,
+        })
+    }
+
     // TODO: The return type here is very strange because we have three cases:
     //  1. Continue (return actor ID & gas).
     //  2. Short-circuit (return ApplyRet).
@@ -304,6 +398,21 @@ where
                 }
 
                 let miner_penalty_amount = &self.context().network_context.base_fee * msg.gas_limit;
+
+                // The maximum message size is a mempool policy rather than a consensus rule, so
+                // it isn't enforced here.
+                let limits = MessageLimits {
+                    max_message_size: usize::MAX,
+                    ..MessageLimits::default()
+                };
+                if let Err(e) = msg.validate(&limits) {
+                    return Ok(Err(ApplyRet::prevalidation_fail(
+                        validation_exit_code(&e),
+                        format!("Message invalid: {}", e),
+                        miner_penalty_amount,
+                    )));
+                }
+
                 (inclusion_cost, miner_penalty_amount)
             }
         };
@@ -466,6 +575,7 @@ where
             failure_info,
             exec_trace,
             events,
+            resource_usage: Default::default(),
         })
     }
 
@@ -485,3 +595,25 @@ where
         )
     }
 }
+
+/// Maps a syscall error returned when sending a top-level message or call to the exit code of the
+/// call. Such errors mean the call couldn't be dispatched at all.
+fn send_error_exit_code(err: ErrorNumber) -> ExitCode {
+    match err {
+        ErrorNumber::InsufficientFunds => ExitCode::SYS_INSUFFICIENT_FUNDS,
+        ErrorNumber::NotFound => ExitCode::SYS_INVALID_RECEIVER,
+        _ => ExitCode::SYS_ASSERTION_FAILED,
+    }
+}
+
+/// Maps a message that failed [`Message::validate`] to the exit code of its receipt.
+fn validation_exit_code(err: &MessageError) -> ExitCode {
+    match err {
+        MessageError::InvalidFromProtocol(_) => ExitCode::SYS_SENDER_INVALID,
+        MessageError::InvalidTo => ExitCode::SYS_INVALID_RECEIVER,
+        MessageError::NegativeValue | MessageError::ValueExceedsSupply => {
+            ExitCode::SYS_INSUFFICIENT_FUNDS
+        }
+        _ => ExitCode::SYS_SENDER_STATE_INVALID,
+    }
+}
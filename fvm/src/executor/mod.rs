@@ -1,3 +1,4 @@
+pub mod commitments;
 mod default;
 mod threaded;
 
@@ -14,7 +15,9 @@ use fvm_shared::receipt::Receipt;
 use num_traits::Zero;
 pub use threaded::ThreadedExecutor;
 
-use crate::call_manager::Backtrace;
+use crate::call_manager::backtrace::Cause;
+use crate::call_manager::{Backtrace, ResourceLimitExceeded, ResourceUsage};
+use crate::kernel::Block;
 use crate::trace::ExecutionTrace;
 use crate::Kernel;
 
@@ -49,10 +52,34 @@ pub trait Executor {
 pub enum ApplyFailure {
     /// The backtrace from a message failure.
     MessageBacktrace(Backtrace),
+    /// The backtrace from a message failure caused by exceeding a resource limit.
+    ResourceLimitExceeded {
+        /// The exceeded limit.
+        exceeded: ResourceLimitExceeded,
+        /// The backtrace, whose cause is the exceeded limit.
+        backtrace: Backtrace,
+    },
     /// A message describing a pre-validation failure.
     PreValidation(String),
 }
 
+impl ApplyFailure {
+    /// Returns the failure information for a message that exited with `exit_code` and the given
+    /// backtrace, if there is any.
+    pub(crate) fn from_backtrace(backtrace: Backtrace, exit_code: ExitCode) -> Option<Self> {
+        if backtrace.is_empty() || exit_code.is_success() {
+            return None;
+        }
+        Some(match &backtrace.cause {
+            Some(Cause::ResourceLimitExceeded(exceeded)) => ApplyFailure::ResourceLimitExceeded {
+                exceeded: *exceeded,
+                backtrace,
+            },
+            _ => ApplyFailure::MessageBacktrace(backtrace),
+        })
+    }
+}
+
 impl Display for ApplyFailure {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -60,6 +87,13 @@ impl Display for ApplyFailure {
                 writeln!(f, "message failed with backtrace:")?;
                 write!(f, "{}", bt)?;
             }
+            ApplyFailure::ResourceLimitExceeded {
+                exceeded,
+                backtrace,
+            } => {
+                writeln!(f, "message failed with {}, backtrace:", exceeded)?;
+                write!(f, "{}", backtrace)?;
+            }
             ApplyFailure::PreValidation(msg) => {
                 writeln!(f, "pre-validation failed: {}", msg)?;
             }
@@ -91,6 +125,8 @@ pub struct ApplyRet {
     pub exec_trace: ExecutionTrace,
     /// Events generated while applying the message.
     pub events: Vec<StampedEvent>,
+    /// The peak Wasm resources used while applying the message, overall and per invocation.
+    pub resource_usage: ResourceUsage,
 }
 
 impl ApplyRet {
@@ -117,10 +153,24 @@ impl ApplyRet {
             failure_info: Some(ApplyFailure::PreValidation(message.into())),
             exec_trace: vec![],
             events: vec![],
+            resource_usage: Default::default(),
         }
     }
 }
 
+/// The result of calling an actor off-chain with [`DefaultExecutor::call_actor`].
+#[derive(Clone, Debug)]
+pub struct CallRet {
+    /// The exit code of the call.
+    pub exit_code: ExitCode,
+    /// The block returned by the actor, if the call succeeded and returned a value.
+    pub return_data: Option<Block>,
+    /// The gas used by the call.
+    pub gas_used: i64,
+    /// Additional failure information for debugging, if any.
+    pub failure_info: Option<ApplyFailure>,
+}
+
 /// The kind of message being applied:
 ///
 /// 1. Explicit messages may only come from account actors and charge the sending account for gas
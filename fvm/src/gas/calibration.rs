@@ -0,0 +1,659 @@
+//! A harness for calibrating the gas schedule against the hardware it runs on.
+//!
+//! Each workload runs one priced operation over a range of input sizes, timing every run, and
+//! fits a linear model (`base + per_unit * size`) to the timings. Timings are converted to gas at
+//! a fixed rate ([`CalibrationConfig::gas_per_ns`]), and reported next to a model fitted to the
+//! charges of the current [`PriceList`], along with the standard errors of the fitted
+//! parameters.
+//!
+//! ```no_run
+//! use fvm::gas::calibration::{calibrate, CalibrationConfig};
+//! use fvm::gas::price_list_by_network_version;
+//! use fvm_shared::version::NetworkVersion;
+//!
+//! let price_list = price_list_by_network_version(NetworkVersion::V18);
+//! let report = calibrate(price_list, &CalibrationConfig::default()).unwrap();
+//! println!("{report}");
+//! ```
+
+use std::fmt;
+use std::time::Instant;
+
+use anyhow::{anyhow, Context};
+use cid::multihash::Code;
+use fvm_ipld_blockstore::{Block, Blockstore, MemoryBlockstore};
+use fvm_ipld_encoding::{to_vec, BytesSer, RawBytes, DAG_CBOR};
+use fvm_shared::address::Address;
+use fvm_shared::crypto::signature::{self, SignatureType};
+use fvm_shared::event::{ActorEvent, Entry, Flags, StampedEvent};
+use fvm_wasm_instrument::gas_metering::Rules;
+use fvm_wasm_instrument::parity_wasm::elements::{
+    BlockType, BulkInstruction, Instruction, Instructions, Local, ValueType,
+};
+use fvm_wasm_instrument::parity_wasm::{builder, serialize};
+use multihash::MultihashDigest;
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+use wasmtime::{Engine, Instance, Memory, MemoryType, Module, Store};
+
+use super::{Gas, PriceList};
+use crate::kernel::SupportedHashes;
+
+/// Settings for a calibration run.
+#[derive(Clone, Debug)]
+pub struct CalibrationConfig {
+    /// The conversion rate from execution time to gas.
+    ///
+    /// DEFAULT: 10 gas per nanosecond
+    pub gas_per_ns: f64,
+    /// The number of timed runs for each input size.
+    ///
+    /// DEFAULT: 20
+    pub iterations: usize,
+    /// The seed for generating workload inputs.
+    ///
+    /// DEFAULT: 0
+    pub seed: u64,
+}
+
+impl Default for CalibrationConfig {
+    fn default() -> Self {
+        Self {
+            gas_per_ns: 10.0,
+            iterations: 20,
+            seed: 0,
+        }
+    }
+}
+
+/// A single timed run of a workload.
+#[derive(Clone, Copy, Debug)]
+pub struct Observation {
+    /// The input size, in the unit of the workload.
+    pub size: f64,
+    /// The wall-clock time of the run.
+    pub elapsed_ns: f64,
+    /// The gas charged for the run by the current price list.
+    pub charged: Gas,
+}
+
+/// A linear model `base + per_unit * size` fitted with ordinary least squares.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LinearFit {
+    pub base: f64,
+    pub per_unit: f64,
+    /// The standard error of `base`.
+    pub base_error: f64,
+    /// The standard error of `per_unit`.
+    pub per_unit_error: f64,
+    /// The coefficient of determination.
+    pub r_squared: f64,
+}
+
+impl LinearFit {
+    /// Fits a model to `(x, y)` points. Returns `None` with fewer than three points, or if all
+    /// points have the same `x`.
+    pub fn fit(points: &[(f64, f64)]) -> Option<Self> {
+        let n = points.len() as f64;
+        if points.len() < 3 {
+            return None;
+        }
+        let mean_x = points.iter().map(|p| p.0).sum::<f64>() / n;
+        let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+        let sxx: f64 = points.iter().map(|p| (p.0 - mean_x).powi(2)).sum();
+        let sxy: f64 = points.iter().map(|p| (p.0 - mean_x) * (p.1 - mean_y)).sum();
+        let syy: f64 = points.iter().map(|p| (p.1 - mean_y).powi(2)).sum();
+        if sxx == 0.0 {
+            return None;
+        }
+
+        let per_unit = sxy / sxx;
+        let base = mean_y - per_unit * mean_x;
+        let residuals: f64 = points
+            .iter()
+            .map(|p| (p.1 - base - per_unit * p.0).powi(2))
+            .sum();
+        let variance = residuals / (n - 2.0);
+
+        Some(Self {
+            base,
+            per_unit,
+            base_error: (variance * (1.0 / n + mean_x.powi(2) / sxx)).sqrt(),
+            per_unit_error: (variance / sxx).sqrt(),
+            r_squared: if syy == 0.0 {
+                1.0
+            } else {
+                1.0 - residuals / syy
+            },
+        })
+    }
+}
+
+/// The calibration result for a single priced operation.
+#[derive(Clone, Debug)]
+pub struct CalibrationResult {
+    /// The name of the operation, matching the name of its [`GasCharge`](super::GasCharge).
+    pub name: String,
+    /// The unit of the input size.
+    pub unit: &'static str,
+    /// The model of the charges of the current price list, in gas.
+    pub current: LinearFit,
+    /// The model of the measured timings, converted to gas.
+    pub suggested: LinearFit,
+}
+
+impl CalibrationResult {
+    /// Fits the current and suggested models to the observations of a workload.
+    pub fn from_observations(
+        name: impl Into<String>,
+        unit: &'static str,
+        observations: &[Observation],
+        gas_per_ns: f64,
+    ) -> anyhow::Result<Self> {
+        let name = name.into();
+        let fit = |y: &dyn Fn(&Observation) -> f64| {
+            let points: Vec<_> = observations.iter().map(|o| (o.size, y(o))).collect();
+            LinearFit::fit(&points).ok_or_else(|| anyhow!("too few observations for {}", name))
+        };
+        Ok(Self {
+            current: fit(&|o| o.charged.as_milligas() as f64 / super::MILLIGAS_PRECISION as f64)?,
+            suggested: fit(&|o| o.elapsed_ns * gas_per_ns)?,
+            name,
+            unit,
+        })
+    }
+}
+
+/// The results of a calibration run.
+#[derive(Clone, Debug, Default)]
+pub struct CalibrationReport {
+    pub results: Vec<CalibrationResult>,
+}
+
+impl fmt::Display for CalibrationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<32} {:>12} {:>12} {:>26} {:>24} {:>6}",
+            "operation", "base", "per unit", "suggested base", "suggested per unit", "r²"
+        )?;
+        for r in &self.results {
+            writeln!(
+                f,
+                "{:<32} {:>12.0} {:>12.3} {:>26} {:>24} {:>6.3}",
+                format!("{} [{}]", r.name, r.unit),
+                r.current.base,
+                r.current.per_unit,
+                format!("{:.0} ± {:.0}", r.suggested.base, r.suggested.base_error),
+                format!(
+                    "{:.3} ± {:.3}",
+                    r.suggested.per_unit, r.suggested.per_unit_error
+                ),
+                r.suggested.r_squared,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs all workloads against the given price list.
+pub fn calibrate(
+    price_list: &PriceList,
+    config: &CalibrationConfig,
+) -> anyhow::Result<CalibrationReport> {
+    let mut harness = Harness {
+        price_list,
+        config,
+        rng: StdRng::seed_from_u64(config.seed),
+        report: CalibrationReport::default(),
+    };
+    harness.block_open()?;
+    harness.hashing()?;
+    harness.verify_signature()?;
+    harness.actor_event()?;
+    harness.memory_grow()?;
+    harness.wasm_instructions()?;
+    Ok(harness.report)
+}
+
+/// The input sizes for workloads measured in bytes.
+const BYTE_SIZES: &[usize] = &[0, 256, 1 << 10, 4 << 10, 16 << 10, 64 << 10, 256 << 10];
+
+struct Harness<'a> {
+    price_list: &'a PriceList,
+    config: &'a CalibrationConfig,
+    rng: StdRng,
+    report: CalibrationReport,
+}
+
+impl Harness<'_> {
+    /// Runs a workload for every input size and adds the fitted result to the report. `setup`
+    /// prepares the input for a given size, `run` is the timed operation, and `charge` returns the
+    /// gas the current price list charges for the input.
+    fn measure<T, R>(
+        &mut self,
+        name: impl Into<String>,
+        unit: &'static str,
+        sizes: &[usize],
+        mut setup: impl FnMut(&mut StdRng, usize) -> anyhow::Result<T>,
+        mut run: impl FnMut(&mut T) -> anyhow::Result<R>,
+        charge: impl Fn(&T) -> Gas,
+    ) -> anyhow::Result<()> {
+        let mut observations = Vec::with_capacity(sizes.len() * self.config.iterations);
+        for &size in sizes {
+            let mut input = setup(&mut self.rng, size)?;
+            let charged = charge(&input);
+            // warm up caches and lazily initialized state
+            run(&mut input)?;
+            for _ in 0..self.config.iterations {
+                let start = Instant::now();
+                let output = run(&mut input)?;
+                let elapsed = start.elapsed();
+                drop(output);
+                observations.push(Observation {
+                    size: size as f64,
+                    elapsed_ns: elapsed.as_nanos() as f64,
+                    charged,
+                });
+            }
+        }
+        let result = CalibrationResult::from_observations(
+            name,
+            unit,
+            &observations,
+            self.config.gas_per_ns,
+        )?;
+        self.report.results.push(result);
+        Ok(())
+    }
+
+    fn block_open(&mut self) -> anyhow::Result<()> {
+        let pl = self.price_list;
+        let store = MemoryBlockstore::default();
+        self.measure(
+            "OnBlockOpen",
+            "byte",
+            BYTE_SIZES,
+            |rng, size| {
+                let data = random_bytes(rng, size);
+                let cid = store.put(Code::Blake2b256, &Block::new(DAG_CBOR, &data))?;
+                Ok((cid, size))
+            },
+            |(cid, _)| store.get(cid)?.context("block not found"),
+            |&(_, size)| pl.on_block_open_base().total() + pl.on_block_open_per_byte(size).total(),
+        )
+    }
+
+    fn hashing(&mut self) -> anyhow::Result<()> {
+        let pl = self.price_list;
+        for hasher in [
+            SupportedHashes::Sha2_256,
+            SupportedHashes::Blake2b256,
+            SupportedHashes::Blake2b512,
+            SupportedHashes::Keccak256,
+            SupportedHashes::Ripemd160,
+        ] {
+            self.measure(
+                format!("OnHashing({:?})", hasher),
+                "byte",
+                BYTE_SIZES,
+                |rng, size| Ok(random_bytes(rng, size)),
+                |data| Ok(hasher.digest(data)),
+                |data| pl.on_hashing(data.len()).total(),
+            )?;
+        }
+        Ok(())
+    }
+
+    fn verify_signature(&mut self) -> anyhow::Result<()> {
+        let pl = self.price_list;
+        for sig_type in [SignatureType::Secp256k1, SignatureType::BLS] {
+            self.measure(
+                format!("OnVerifySignature({:?})", sig_type),
+                "byte",
+                BYTE_SIZES,
+                |rng, size| {
+                    let data = random_bytes(rng, size);
+                    let (sig, addr) = sign(rng, sig_type, &data)?;
+                    Ok((data, sig, addr))
+                },
+                |(data, sig, addr)| {
+                    signature::verify(sig_type, sig, data, addr).map_err(|e| anyhow!(e))
+                },
+                |_| pl.on_verify_signature(sig_type).total(),
+            )?;
+        }
+        Ok(())
+    }
+
+    fn actor_event(&mut self) -> anyhow::Result<()> {
+        let pl = self.price_list;
+        // The syscall decodes the event from actor memory, then the kernel stamps and records it.
+        let emit = |(_, encoded): &mut (ActorEvent, Vec<u8>)| -> anyhow::Result<_> {
+            let evt: ActorEvent = fvm_ipld_encoding::from_slice(encoded)?;
+            Ok(StampedEvent::new(0, evt))
+        };
+        let charge = |(evt, _): &(ActorEvent, Vec<u8>)| pl.on_actor_event(evt).total();
+
+        self.measure(
+            "OnActorEvent",
+            "entry",
+            &[0, 1, 2, 4, 8, 16, 32, 64, 128, 255],
+            |rng, count| encoded_event(rng, count, 8),
+            emit,
+            charge,
+        )?;
+        self.measure(
+            "OnActorEvent",
+            "byte",
+            BYTE_SIZES,
+            |rng, size| encoded_event(rng, 1, size),
+            emit,
+            charge,
+        )
+    }
+
+    fn memory_grow(&mut self) -> anyhow::Result<()> {
+        let pl = self.price_list;
+        let engine = Engine::default();
+        let page_size = wasmtime_environ::WASM_PAGE_SIZE as usize;
+        self.measure(
+            "OnMemoryGrow",
+            "page",
+            &[1, 2, 4, 8, 16, 32, 64, 128, 256],
+            |_, pages| Ok(pages),
+            |&mut pages| {
+                let mut store = Store::new(&engine, ());
+                let memory = Memory::new(&mut store, MemoryType::new(0, None))?;
+                memory.grow(&mut store, pages as u64)?;
+                // touch every page, as growing may only reserve address space
+                let data = memory.data_mut(&mut store);
+                for page in 0..pages {
+                    data[page * page_size] = 1;
+                }
+                Ok(())
+            },
+            |&pages| pl.grow_memory_gas(pages * page_size),
+        )
+    }
+
+    fn wasm_instructions(&mut self) -> anyhow::Result<()> {
+        let pl = self.price_list;
+        let engine = Engine::default();
+        for class in InstructionClass::ALL {
+            let body = class.snippet().repeat(SNIPPETS_PER_ITERATION);
+            let module = Module::new(&engine, loop_module(&body)?)?;
+
+            // Everything executed in one loop iteration, including the loop itself and the
+            // bodies of called functions.
+            let executed: Vec<_> = LOOP_HEAD
+                .iter()
+                .chain(&body)
+                .chain(LOOP_TAIL)
+                .chain(class.callee().repeat(SNIPPETS_PER_ITERATION).iter())
+                .cloned()
+                .collect();
+            let milligas_per_iteration: u64 = executed
+                .iter()
+                .map(|i| pl.wasm_rules.instruction_cost(i).unwrap_or_default())
+                .sum();
+
+            let sizes: Vec<_> = [1, 10, 100, 1000, 10_000]
+                .iter()
+                .map(|iterations| iterations * executed.len())
+                .collect();
+            self.measure(
+                format!("OnWasmInstruction({:?})", class),
+                "instruction",
+                &sizes,
+                |_, size| {
+                    let mut store = Store::new(&engine, ());
+                    let instance = Instance::new(&mut store, &module, &[])?;
+                    let run = instance.get_typed_func::<i64, (), _>(&mut store, "run")?;
+                    Ok((store, run, (size / executed.len()) as u64))
+                },
+                |(store, run, iterations)| Ok(run.call(&mut *store, *iterations as i64)?),
+                |&(_, _, iterations)| {
+                    Gas::from_milligas((milligas_per_iteration * iterations) as i64)
+                },
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Classes of WASM instructions with (potentially) different execution costs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InstructionClass {
+    /// Constants and local variable access.
+    Local,
+    /// Integer addition.
+    Add,
+    /// Integer multiplication.
+    Mul,
+    /// Integer division.
+    Div,
+    /// Memory loads and stores.
+    Memory,
+    /// Function calls.
+    Call,
+    /// Indirect function calls, through the table.
+    CallIndirect,
+    /// Blocks and branches.
+    ControlFlow,
+    /// Bulk memory copies and fills, of 1KiB each.
+    BulkMemory,
+}
+
+impl InstructionClass {
+    pub const ALL: [Self; 9] = [
+        Self::Local,
+        Self::Add,
+        Self::Mul,
+        Self::Div,
+        Self::Memory,
+        Self::Call,
+        Self::CallIndirect,
+        Self::ControlFlow,
+        Self::BulkMemory,
+    ];
+
+    /// A snippet of instructions of this class, with no net effect on the stack.
+    fn snippet(self) -> Vec<Instruction> {
+        use Instruction::*;
+        match self {
+            Self::Local => vec![I64Const(1), SetLocal(1)],
+            Self::Add => vec![GetLocal(1), I64Const(3), I64Add, SetLocal(1)],
+            Self::Mul => vec![GetLocal(1), I64Const(3), I64Mul, SetLocal(1)],
+            Self::Div => vec![GetLocal(1), I64Const(3), I64DivU, SetLocal(1)],
+            Self::Memory => vec![I32Const(0), I32Const(8), I64Load(3, 0), I64Store(3, 0)],
+            Self::Call => vec![Call(0)],
+            Self::CallIndirect => vec![I32Const(0), CallIndirect(0, 0)],
+            Self::ControlFlow => vec![Block(BlockType::NoResult), I32Const(1), BrIf(0), End],
+            Self::BulkMemory => vec![
+                I32Const(0),
+                I32Const(1024),
+                I32Const(1024),
+                Bulk(BulkInstruction::MemoryCopy),
+                I32Const(0),
+                I32Const(0),
+                I32Const(1024),
+                Bulk(BulkInstruction::MemoryFill),
+            ],
+        }
+    }
+
+    /// The instructions executed by the callee for each snippet.
+    fn callee(self) -> Vec<Instruction> {
+        match self {
+            Self::Call | Self::CallIndirect => vec![Instruction::End],
+            _ => vec![],
+        }
+    }
+}
+
+/// The number of snippets executed per loop iteration, amortizing the cost of the loop itself.
+const SNIPPETS_PER_ITERATION: usize = 100;
+
+/// The loop head: exit once the counter in local 0 reaches zero.
+const LOOP_HEAD: &[Instruction] = &[
+    Instruction::GetLocal(0),
+    Instruction::I64Eqz,
+    Instruction::BrIf(1),
+];
+
+/// The loop tail: decrement the counter in local 0 and continue.
+const LOOP_TAIL: &[Instruction] = &[
+    Instruction::GetLocal(0),
+    Instruction::I64Const(1),
+    Instruction::I64Sub,
+    Instruction::SetLocal(0),
+    Instruction::Br(0),
+];
+
+/// Builds a module exporting `run(n: i64)`, which executes `body` `n` times. Function 0 is an
+/// empty function for [`InstructionClass::Call`] to call, and the first entry of the table for
+/// [`InstructionClass::CallIndirect`].
+fn loop_module(body: &[Instruction]) -> anyhow::Result<Vec<u8>> {
+    let mut code = vec![
+        Instruction::Block(BlockType::NoResult),
+        Instruction::Loop(BlockType::NoResult),
+    ];
+    code.extend_from_slice(LOOP_HEAD);
+    code.extend_from_slice(body);
+    code.extend_from_slice(LOOP_TAIL);
+    code.extend([Instruction::End, Instruction::End, Instruction::End]);
+
+    let module = builder::module()
+        .function()
+        .signature()
+        .build()
+        .body()
+        .with_instructions(Instructions::new(vec![Instruction::End]))
+        .build()
+        .build()
+        .function()
+        .signature()
+        .with_param(ValueType::I64)
+        .build()
+        .body()
+        .with_locals(vec![Local::new(1, ValueType::I64)])
+        .with_instructions(Instructions::new(code))
+        .build()
+        .build()
+        .memory()
+        .with_min(1)
+        .build()
+        .table()
+        .with_min(1)
+        .with_element(0, vec![0])
+        .build()
+        .export()
+        .field("run")
+        .internal()
+        .func(1)
+        .build()
+        .build();
+    serialize(module).map_err(|e| anyhow!("failed to serialize module: {}", e))
+}
+
+fn random_bytes(rng: &mut StdRng, size: usize) -> Vec<u8> {
+    let mut data = vec![0u8; size];
+    rng.fill_bytes(&mut data);
+    data
+}
+
+/// Builds an event with `count` indexed entries, each with a `size` byte value, along with its
+/// encoding.
+fn encoded_event(
+    rng: &mut StdRng,
+    count: usize,
+    size: usize,
+) -> anyhow::Result<(ActorEvent, Vec<u8>)> {
+    let entries = (0..count)
+        .map(|i| {
+            Ok(Entry {
+                flags: Flags::FLAG_INDEXED_ALL,
+                key: format!("k{}", i),
+                value: RawBytes::serialize(BytesSer(&random_bytes(rng, size)))?,
+            })
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let evt = ActorEvent::from(entries);
+    let encoded = to_vec(&evt)?;
+    Ok((evt, encoded))
+}
+
+/// Signs `data` with a freshly generated key, returning the signature and the signer's address.
+fn sign(
+    rng: &mut StdRng,
+    sig_type: SignatureType,
+    data: &[u8],
+) -> anyhow::Result<(Vec<u8>, Address)> {
+    match sig_type {
+        SignatureType::Secp256k1 => {
+            let key = libsecp256k1::SecretKey::random(rng);
+            let public = libsecp256k1::PublicKey::from_secret_key(&key);
+            let addr = Address::new_secp256k1(&public.serialize())?;
+            Ok((signature::ops::sign_secp256k1(&key, data).to_vec(), addr))
+        }
+        SignatureType::BLS => {
+            use bls_signatures::Serialize;
+            let key = bls_signatures::PrivateKey::generate(rng);
+            let addr = Address::new_bls(&key.public_key().as_bytes())?;
+            Ok((signature::ops::sign_bls(&key, data).to_vec(), addr))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn linear_fit() {
+        let points: Vec<_> = (0..10).map(|x| (x as f64, 3.0 + 2.0 * x as f64)).collect();
+        let fit = LinearFit::fit(&points).unwrap();
+        assert!((fit.base - 3.0).abs() < 1e-9);
+        assert!((fit.per_unit - 2.0).abs() < 1e-9);
+        assert!(fit.base_error < 1e-9 && fit.per_unit_error < 1e-9);
+        assert!((fit.r_squared - 1.0).abs() < 1e-9);
+
+        // Noise shows up in the error bars.
+        let noisy: Vec<_> = points
+            .iter()
+            .enumerate()
+            .map(|(i, &(x, y))| (x, y + if i % 2 == 0 { 1.0 } else { -1.0 }))
+            .collect();
+        let fit = LinearFit::fit(&noisy).unwrap();
+        assert!((fit.per_unit - 2.0).abs() < 0.5);
+        assert!(fit.per_unit_error > 0.0 && fit.r_squared < 1.0);
+
+        assert_eq!(LinearFit::fit(&points[..2]), None);
+        assert_eq!(LinearFit::fit(&[(1.0, 1.0), (1.0, 2.0), (1.0, 3.0)]), None);
+    }
+
+    #[test]
+    fn loop_module_runs() {
+        let engine = Engine::default();
+        for class in InstructionClass::ALL {
+            let body = class.snippet().repeat(2);
+            let module = Module::new(&engine, loop_module(&body).unwrap()).unwrap();
+            let mut store = Store::new(&engine, ());
+            let instance = Instance::new(&mut store, &module, &[]).unwrap();
+            let run = instance
+                .get_typed_func::<i64, (), _>(&mut store, "run")
+                .unwrap();
+            run.call(&mut store, 10).unwrap();
+        }
+    }
+
+    #[test]
+    #[ignore = "takes several minutes; run explicitly to calibrate"]
+    fn calibrate_current_schedule() {
+        let price_list =
+            crate::gas::price_list_by_network_version(fvm_shared::version::NetworkVersion::V18);
+        let report = calibrate(price_list, &CalibrationConfig::default()).unwrap();
+        println!("{}", report);
+    }
+}
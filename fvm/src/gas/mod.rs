@@ -6,12 +6,17 @@ use std::ops::{Add, AddAssign, Mul, Sub, SubAssign};
 
 use fvm_shared::econ::TokenAmount;
 use num_traits::Zero;
+use serde::{Deserialize, Serialize};
 
 pub use self::charge::GasCharge;
 pub(crate) use self::outputs::GasOutputs;
-pub use self::price_list::{price_list_by_network_version, PriceList, WasmGasPrices};
+pub use self::price_list::{
+    price_list_by_network_version, PriceList, PriceListBuilder, WasmGasPrices,
+};
 use crate::kernel::{ExecutionError, Result};
 
+#[cfg(feature = "testing")]
+pub mod calibration;
 mod charge;
 mod outputs;
 mod price_list;
@@ -24,7 +29,8 @@ pub const MILLIGAS_PRECISION: i64 = 1000;
 /// - Enforces correct units by making it impossible to, e.g., get gas squared (by multiplying gas
 ///   by gas).
 /// - Makes it harder to confuse gas and milligas.
-#[derive(Hash, Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Default)]
+#[derive(Hash, Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct Gas(i64 /* milligas */);
 
 impl Debug for Gas {
@@ -155,6 +161,9 @@ pub struct GasTracker {
     gas_limit: Gas,
     gas_used: Gas,
     gas_premium: TokenAmount,
+    /// Caps on `gas_used` imposed by sends with a gas limit, innermost last. Each cap is at most
+    /// the one before it.
+    nested_limits: Vec<Gas>,
     trace: Option<Vec<GasCharge>>,
 }
 
@@ -166,6 +175,7 @@ impl GasTracker {
             gas_limit,
             gas_used,
             gas_premium,
+            nested_limits: Vec::new(),
             trace: None,
         }
     }
@@ -178,9 +188,10 @@ impl GasTracker {
         log::trace!("charging gas: {} {}", name, to_use);
         // The gas type uses saturating math.
         self.gas_used += to_use;
-        if self.gas_used > self.gas_limit {
+        let limit = self.effective_limit();
+        if self.gas_used > limit {
             log::trace!("gas limit reached");
-            self.gas_used = self.gas_limit;
+            self.gas_used = limit;
             Err(ExecutionError::OutOfGas)
         } else {
             Ok(())
@@ -206,6 +217,25 @@ impl GasTracker {
         res
     }
 
+    /// Limits the gas available until the matching [`GasTracker::pop_limit`] to at most `limit`.
+    /// Running out of gas inside the nested budget leaves the rest of the outer budget untouched.
+    pub fn push_limit(&mut self, limit: Gas) {
+        let cap = (self.gas_used + limit).min(self.effective_limit());
+        self.nested_limits.push(cap);
+    }
+
+    /// Removes the innermost nested gas limit pushed with [`GasTracker::push_limit`].
+    pub fn pop_limit(&mut self) {
+        self.nested_limits
+            .pop()
+            .expect("popped a gas limit that was never pushed");
+    }
+
+    /// The cap on gas used imposed by the innermost nested limit, or the message gas limit.
+    fn effective_limit(&self) -> Gas {
+        self.nested_limits.last().copied().unwrap_or(self.gas_limit)
+    }
+
     /// Getter for the maximum gas usable by this message.
     pub fn gas_limit(&self) -> Gas {
         self.gas_limit
@@ -216,9 +246,9 @@ impl GasTracker {
         self.gas_used
     }
 
-    /// Getter for gas available.
+    /// Getter for gas available, taking nested limits into account.
     pub fn gas_available(&self) -> Gas {
-        self.gas_limit - self.gas_used
+        self.effective_limit() - self.gas_used
     }
 
     /// Gettr for gas premium
@@ -267,6 +297,31 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn nested_gas_limits() -> Result<()> {
+        let mut t = GasTracker::new(Gas::new(100), Gas::new(10), Zero::zero());
+
+        t.push_limit(Gas::new(30));
+        assert_eq!(t.gas_available(), Gas::new(30));
+        t.charge_gas("", Gas::new(20))?;
+
+        // An inner limit can't exceed the outer one.
+        t.push_limit(Gas::new(50));
+        assert_eq!(t.gas_available(), Gas::new(10));
+        assert!(t.charge_gas("", Gas::new(11)).is_err());
+        assert_eq!(t.gas_used(), Gas::new(40));
+        t.pop_limit();
+        assert_eq!(t.gas_available(), Gas::zero());
+        t.pop_limit();
+
+        // Exhausting a nested budget leaves the rest of the gas.
+        assert_eq!(t.gas_available(), Gas::new(60));
+        assert_eq!(t.gas_limit(), Gas::new(100));
+        t.charge_gas("", Gas::new(60))?;
+        assert!(t.charge_gas("", Gas::new(1)).is_err());
+        Ok(())
+    }
+
     #[test]
     fn milligas_to_gas_round() {
         assert_eq!(milligas_to_gas(100, false), 0);
@@ -3,7 +3,9 @@
 
 use std::collections::HashMap;
 use std::num::NonZeroU64;
+use std::path::Path;
 
+use anyhow::Context;
 use fvm_shared::crypto::signature::SignatureType;
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::event::{ActorEvent, Flags};
@@ -18,6 +20,7 @@ use fvm_wasm_instrument::gas_metering::{MemoryGrowCost, Rules};
 use fvm_wasm_instrument::parity_wasm::elements::Instruction;
 use lazy_static::lazy_static;
 use num_traits::Zero;
+use serde::{Deserialize, Serialize};
 
 use super::GasCharge;
 use crate::gas::Gas;
@@ -146,6 +149,13 @@ lazy_static! {
 
         wasm_rules: WasmGasPrices{
             exec_instruction_cost: Zero::zero(),
+            arithmetic_cost: Zero::zero(),
+            division_cost: Zero::zero(),
+            memory_access_cost: Zero::zero(),
+            control_flow_cost: Zero::zero(),
+            call_cost: Zero::zero(),
+            call_indirect_cost: Zero::zero(),
+            bulk_memory_cost: Zero::zero(),
             memory_expansion_per_byte_cost: Zero::zero(),
         },
 
@@ -278,6 +288,13 @@ lazy_static! {
 
         wasm_rules: WasmGasPrices{
             exec_instruction_cost: Gas::new(4),
+            arithmetic_cost: Gas::new(4),
+            division_cost: Gas::new(4),
+            memory_access_cost: Gas::new(4),
+            control_flow_cost: Gas::new(4),
+            call_cost: Gas::new(4),
+            call_indirect_cost: Gas::new(4),
+            bulk_memory_cost: Gas::new(4),
             memory_expansion_per_byte_cost: Zero::zero(),
         },
 
@@ -412,6 +429,13 @@ lazy_static! {
 
         wasm_rules: WasmGasPrices{
             exec_instruction_cost: Gas::new(4),
+            arithmetic_cost: Gas::new(4),
+            division_cost: Gas::new(4),
+            memory_access_cost: Gas::new(4),
+            control_flow_cost: Gas::new(4),
+            call_cost: Gas::new(4),
+            call_indirect_cost: Gas::new(4),
+            bulk_memory_cost: Gas::new(4),
             memory_expansion_per_byte_cost: Zero::zero(),
         },
 
@@ -428,16 +452,16 @@ lazy_static! {
     };
 }
 
-#[derive(Clone, Debug, Copy, PartialEq, Eq)]
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub(crate) struct ScalingCost {
     flat: Gas,
     scale: Gas,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub(crate) struct StepCost(Vec<Step>);
 
-#[derive(Clone, Debug, Copy, PartialEq, Eq)]
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub(crate) struct Step {
     start: i64,
     cost: Gas,
@@ -462,7 +486,12 @@ impl StepCost {
 
 /// Provides prices for operations in the VM.
 /// All costs are in milligas.
-#[derive(Clone, Debug, PartialEq, Eq)]
+///
+/// Besides the built-in price lists returned by [`price_list_by_network_version`], price lists
+/// can be loaded from JSON files with [`PriceList::load`], or derived from an existing price list
+/// with [`PriceList::builder`]. The easiest way to write a new price list file is to start from
+/// the output of [`PriceList::to_json`] for a built-in price list.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PriceList {
     /// Storage gas charge multiplier
     pub(crate) storage_gas_multiplier: i64,
@@ -576,14 +605,55 @@ pub struct PriceList {
     pub(crate) event_per_byte_cost: Gas,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub struct WasmGasPrices {
+    /// Gas cost for executing any instruction not covered by one of the classes below, such as
+    /// constants and local or global variable access.
     pub(crate) exec_instruction_cost: Gas,
+    /// Gas cost for integer arithmetic, bitwise operations, comparisons and conversions.
+    pub(crate) arithmetic_cost: Gas,
+    /// Gas cost for division and remainder.
+    pub(crate) division_cost: Gas,
+    /// Gas cost for memory loads and stores.
+    pub(crate) memory_access_cost: Gas,
+    /// Gas cost for branches, `if` and `select`.
+    pub(crate) control_flow_cost: Gas,
+    /// Gas cost for direct calls.
+    pub(crate) call_cost: Gas,
+    /// Gas cost for indirect calls, which also check the callee's signature.
+    pub(crate) call_indirect_cost: Gas,
+    /// Gas cost for bulk memory and table instructions. This is a flat cost, independent of the
+    /// number of bytes copied or filled.
+    pub(crate) bulk_memory_cost: Gas,
     /// Gas cost for every byte made writeable in Wasm memory.
     pub(crate) memory_expansion_per_byte_cost: Gas,
 }
 
 impl PriceList {
+    /// Loads a price list from a JSON file.
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read price list from {}", path.display()))?;
+        Self::from_json(&json)
+            .with_context(|| format!("failed to parse price list from {}", path.display()))
+    }
+
+    /// Parses a price list from JSON.
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Serializes the price list as (pretty-printed) JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("price lists are always serializable")
+    }
+
+    /// Returns a builder for a price list that starts out identical to this one.
+    pub fn builder(&self) -> PriceListBuilder {
+        PriceListBuilder(self.clone())
+    }
+
     /// Returns the gas required for storing a message of a given size in the chain.
     #[inline]
     pub fn on_chain_message(&self, msg_size: usize) -> GasCharge {
@@ -881,6 +951,155 @@ impl PriceList {
     }
 }
 
+/// Builds a [`PriceList`] by overriding individual costs of an existing one. Created with
+/// [`PriceList::builder`].
+///
+/// ```
+/// use fvm::gas::{price_list_by_network_version, Gas};
+/// use fvm_shared::version::NetworkVersion;
+///
+/// let base = price_list_by_network_version(NetworkVersion::V18);
+/// let price_list = base
+///     .builder()
+///     .block_open_base(Gas::new(100_000))
+///     .exec_instruction_cost(Gas::from_milligas(2000))
+///     .build();
+/// assert_ne!(&price_list, base);
+/// assert_eq!(price_list.on_syscall().total(), base.on_syscall().total());
+/// ```
+#[derive(Clone, Debug)]
+pub struct PriceListBuilder(PriceList);
+
+macro_rules! price_list_setters {
+    ($($field:ident: $ty:ty),* $(,)?) => {
+        $(
+            #[doc = concat!("Overrides `", stringify!($field), "`.")]
+            pub fn $field(mut self, cost: $ty) -> Self {
+                self.0.$field = cost;
+                self
+            }
+        )*
+    };
+}
+
+macro_rules! wasm_price_setters {
+    ($($field:ident),* $(,)?) => {
+        $(
+            #[doc = concat!("Overrides the WASM `", stringify!($field), "`.")]
+            pub fn $field(mut self, cost: Gas) -> Self {
+                self.0.wasm_rules.$field = cost;
+                self
+            }
+        )*
+    };
+}
+
+impl PriceListBuilder {
+    price_list_setters! {
+        storage_gas_multiplier: i64,
+        on_chain_message_compute_base: Gas,
+        on_chain_message_storage_base: Gas,
+        on_chain_message_storage_per_byte: Gas,
+        on_chain_return_value_per_byte: Gas,
+        send_base: Gas,
+        send_transfer_funds: Gas,
+        send_transfer_only_premium: Gas,
+        send_invoke_method: Gas,
+        create_actor_compute: Gas,
+        create_actor_storage: Gas,
+        delete_actor: Gas,
+        bls_sig_cost: Gas,
+        secp256k1_sig_cost: Gas,
+        secp256k1_recover_cost: Gas,
+        hashing_base: Gas,
+        compute_unsealed_sector_cid_base: Gas,
+        verify_seal_base: Gas,
+        verify_aggregate_seal_base: Gas,
+        verify_consensus_fault: Gas,
+        verify_replica_update: Gas,
+        get_randomness_base: Gas,
+        get_randomness_per_byte: Gas,
+        block_memcpy_per_byte_cost: Gas,
+        block_open_base: Gas,
+        block_open_memret_per_byte_cost: Gas,
+        block_link_base: Gas,
+        block_link_storage_per_byte_cost: Gas,
+        block_create_base: Gas,
+        block_create_memret_per_byte_cost: Gas,
+        block_read_base: Gas,
+        block_stat_base: Gas,
+        syscall_cost: Gas,
+        extern_cost: Gas,
+        event_emit_base_cost: Gas,
+        event_per_entry_cost: Gas,
+        event_entry_index_cost: Gas,
+        event_per_byte_cost: Gas,
+    }
+
+    wasm_price_setters! {
+        exec_instruction_cost,
+        arithmetic_cost,
+        division_cost,
+        memory_access_cost,
+        control_flow_cost,
+        call_cost,
+        call_indirect_cost,
+        bulk_memory_cost,
+        memory_expansion_per_byte_cost,
+    }
+
+    /// Overrides the cost of executing any non-free WASM instruction, in all instruction classes.
+    pub fn flat_instruction_cost(mut self, cost: Gas) -> Self {
+        let rules = &mut self.0.wasm_rules;
+        rules.exec_instruction_cost = cost;
+        rules.arithmetic_cost = cost;
+        rules.division_cost = cost;
+        rules.memory_access_cost = cost;
+        rules.control_flow_cost = cost;
+        rules.call_cost = cost;
+        rules.call_indirect_cost = cost;
+        rules.bulk_memory_cost = cost;
+        self
+    }
+
+    /// Overrides the per-proof cost of verifying an aggregate seal proof of the given type.
+    pub fn verify_aggregate_seal_per(mut self, proof: RegisteredSealProof, cost: Gas) -> Self {
+        self.0.verify_aggregate_seal_per.insert(proof, cost);
+        self
+    }
+
+    /// Overrides the step costs of verifying an aggregate seal proof of the given type, as
+    /// `(number of proofs, cost)` pairs in ascending order of the number of proofs.
+    pub fn verify_aggregate_seal_steps(
+        mut self,
+        proof: RegisteredSealProof,
+        steps: impl IntoIterator<Item = (i64, Gas)>,
+    ) -> Self {
+        let steps = steps
+            .into_iter()
+            .map(|(start, cost)| Step { start, cost })
+            .collect();
+        self.0
+            .verify_aggregate_seal_steps
+            .insert(proof, StepCost(steps));
+        self
+    }
+
+    /// Overrides the cost of verifying a window PoSt of the given type, as a flat cost plus a cost
+    /// per challenged sector.
+    pub fn verify_post_cost(mut self, proof: RegisteredPoStProof, flat: Gas, scale: Gas) -> Self {
+        self.0
+            .verify_post_lookup
+            .insert(proof, ScalingCost { flat, scale });
+        self
+    }
+
+    /// Returns the price list.
+    pub fn build(self) -> PriceList {
+        self.0
+    }
+}
+
 /// Returns gas price list by NetworkVersion for gas consumption.
 pub fn price_list_by_network_version(network_version: NetworkVersion) -> &'static PriceList {
     match network_version {
@@ -891,29 +1110,47 @@ pub fn price_list_by_network_version(network_version: NetworkVersion) -> &'stati
     }
 }
 
-impl Rules for WasmGasPrices {
-    fn instruction_cost(&self, instruction: &Instruction) -> Option<u64> {
-        if self.exec_instruction_cost.is_zero() {
-            return Some(0);
-        }
-
-        // Rules valid for nv16. We will need to be generic over Rules (massive
-        // generics tax), use &dyn Rules (which breaks other things), or pass
-        // in the network version, or rules version, to vary these prices going
-        // forward.
+impl WasmGasPrices {
+    /// Returns the cost of executing an instruction, based on its class.
+    fn instruction_class_cost(&self, instruction: &Instruction) -> Gas {
+        use Instruction::*;
         match instruction {
             // FIP-0032: nop, drop, block, loop, unreachable, return, else, end are priced 0.
-            Instruction::Nop
-            | Instruction::Drop
-            | Instruction::Block(_)
-            | Instruction::Loop(_)
-            | Instruction::Unreachable
-            | Instruction::Return
-            | Instruction::Else
-            | Instruction::End => Some(0),
-            _ => Some(self.exec_instruction_cost.as_milligas() as u64),
+            Nop | Drop | Block(_) | Loop(_) | Unreachable | Return | Else | End => Gas::zero(),
+
+            Call(_) => self.call_cost,
+            CallIndirect(..) => self.call_indirect_cost,
+            Br(_) | BrIf(_) | BrTable(_) | If(_) | Select => self.control_flow_cost,
+
+            I32DivS | I32DivU | I32RemS | I32RemU | I64DivS | I64DivU | I64RemS | I64RemU
+            | F32Div | F64Div => self.division_cost,
+
+            I32Load(..) | I64Load(..) | F32Load(..) | F64Load(..) | I32Load8S(..)
+            | I32Load8U(..) | I32Load16S(..) | I32Load16U(..) | I64Load8S(..) | I64Load8U(..)
+            | I64Load16S(..) | I64Load16U(..) | I64Load32S(..) | I64Load32U(..) | I32Store(..)
+            | I64Store(..) | F32Store(..) | F64Store(..) | I32Store8(..) | I32Store16(..)
+            | I64Store8(..) | I64Store16(..) | I64Store32(..) => self.memory_access_cost,
+
+            Bulk(_) => self.bulk_memory_cost,
+
+            I32Eqz | I32Eq | I32Ne | I32LtS | I32LtU | I32GtS | I32GtU | I32LeS | I32LeU
+            | I32GeS | I32GeU | I64Eqz | I64Eq | I64Ne | I64LtS | I64LtU | I64GtS | I64GtU
+            | I64LeS | I64LeU | I64GeS | I64GeU | I32Clz | I32Ctz | I32Popcnt | I32Add | I32Sub
+            | I32Mul | I32And | I32Or | I32Xor | I32Shl | I32ShrS | I32ShrU | I32Rotl | I32Rotr
+            | I64Clz | I64Ctz | I64Popcnt | I64Add | I64Sub | I64Mul | I64And | I64Or | I64Xor
+            | I64Shl | I64ShrS | I64ShrU | I64Rotl | I64Rotr | I32WrapI64 | I64ExtendSI32
+            | I64ExtendUI32 | SignExt(_) => self.arithmetic_cost,
+
+            _ => self.exec_instruction_cost,
         }
     }
+}
+
+impl Rules for WasmGasPrices {
+    fn instruction_cost(&self, instruction: &Instruction) -> Option<u64> {
+        // Instruction costs vary by network version through the price list.
+        Some(self.instruction_class_cost(instruction).as_milligas() as u64)
+    }
 
     fn memory_grow_cost(&self) -> MemoryGrowCost {
         if self.memory_expansion_per_byte_cost.is_zero() {
@@ -928,3 +1165,111 @@ impl Rules for WasmGasPrices {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_round_trip() {
+        for nv in [
+            NetworkVersion::V15,
+            NetworkVersion::V16,
+            NetworkVersion::V18,
+        ] {
+            let price_list = price_list_by_network_version(nv);
+            let loaded = PriceList::from_json(&price_list.to_json()).unwrap();
+            assert_eq!(&loaded, price_list);
+        }
+        assert!(PriceList::from_json("{}").is_err());
+    }
+
+    #[test]
+    fn builder_overrides() {
+        let base = price_list_by_network_version(NetworkVersion::V18);
+        let price_list = base
+            .builder()
+            .send_base(Gas::new(1))
+            .exec_instruction_cost(Gas::new(2))
+            .verify_post_cost(
+                RegisteredPoStProof::StackedDRGWindow32GiBV1,
+                Gas::new(3),
+                Gas::new(4),
+            )
+            .build();
+
+        assert_eq!(price_list.send_base, Gas::new(1));
+        assert_eq!(price_list.wasm_rules.exec_instruction_cost, Gas::new(2));
+        assert_eq!(
+            price_list.verify_post_lookup[&RegisteredPoStProof::StackedDRGWindow32GiBV1],
+            ScalingCost {
+                flat: Gas::new(3),
+                scale: Gas::new(4),
+            }
+        );
+
+        // Everything else is left alone.
+        let post_cost = base.verify_post_lookup[&RegisteredPoStProof::StackedDRGWindow32GiBV1];
+        let reverted = price_list
+            .builder()
+            .send_base(base.send_base)
+            .exec_instruction_cost(base.wasm_rules.exec_instruction_cost)
+            .verify_post_cost(
+                RegisteredPoStProof::StackedDRGWindow32GiBV1,
+                post_cost.flat,
+                post_cost.scale,
+            )
+            .build();
+        assert_eq!(&reverted, base);
+    }
+
+    #[test]
+    fn unsupported_network_version() {
+        // Network versions without a built-in price list can still be configured.
+        let price_list = price_list_by_network_version(NetworkVersion::V18)
+            .builder()
+            .send_base(Gas::new(1))
+            .build();
+        let nc =
+            crate::machine::NetworkConfig::new_with_price_list(NetworkVersion::V14, price_list);
+        assert_eq!(nc.network_version, NetworkVersion::V14);
+        assert_eq!(nc.price_list.send_base, Gas::new(1));
+    }
+
+    #[test]
+    fn instruction_classes() {
+        use Instruction::*;
+
+        let base = price_list_by_network_version(NetworkVersion::V18);
+        let price_list = base
+            .builder()
+            .flat_instruction_cost(Gas::new(1))
+            .division_cost(Gas::new(10))
+            .memory_access_cost(Gas::new(3))
+            .call_indirect_cost(Gas::new(20))
+            .build();
+        let cost = |i| price_list.wasm_rules.instruction_cost(&i).unwrap();
+        assert_eq!(cost(I64DivU), 10_000);
+        assert_eq!(cost(I32RemS), 10_000);
+        assert_eq!(cost(I64Add), 1000);
+        assert_eq!(cost(I32Load8U(0, 0)), 3000);
+        assert_eq!(cost(I64Store(3, 0)), 3000);
+        assert_eq!(cost(CallIndirect(0, 0)), 20_000);
+        assert_eq!(cost(Call(0)), 1000);
+        assert_eq!(cost(GetLocal(0)), 1000);
+        assert_eq!(cost(Nop), 0);
+        assert_eq!(cost(End), 0);
+
+        // Existing network versions keep pricing all instructions the same.
+        for i in [
+            I64DivU,
+            I32Load8U(0, 0),
+            CallIndirect(0, 0),
+            Br(0),
+            I64Add,
+            GetLocal(0),
+        ] {
+            assert_eq!(base.wasm_rules.instruction_cost(&i), Some(4000));
+        }
+    }
+}
@@ -19,7 +19,7 @@ pub struct BlockRegistry {
 pub type BlockId = u32;
 
 const FIRST_ID: BlockId = 1;
-const MAX_BLOCKS: u32 = i32::MAX as u32; // TODO(M2): Limit
+pub(super) const MAX_BLOCKS: u32 = i32::MAX as u32; // TODO(M2): Limit
 
 /// Codecs allowed by the IPLD subsytem.
 const ALLOWED_CODECS: &[u64; 2] = &[DAG_CBOR, IPLD_RAW];
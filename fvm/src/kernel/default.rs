@@ -17,18 +17,22 @@ use fvm_shared::econ::TokenAmount;
 use fvm_shared::error::ErrorNumber;
 use fvm_shared::piece::{zero_piece_commitment, PaddedPieceSize};
 use fvm_shared::sector::SectorInfo;
+use fvm_shared::sys::SendFlags;
 use fvm_shared::version::NetworkVersion;
 use fvm_shared::{commcid, ActorID};
 use lazy_static::lazy_static;
 use multihash::MultihashDigest;
 use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
 
-use super::blocks::{Block, BlockRegistry};
+use super::blocks::{Block, BlockPutError, BlockRegistry, MAX_BLOCKS};
 use super::error::Result;
 use super::hash::SupportedHashes;
 use super::*;
-use crate::call_manager::{CallManager, InvocationResult, NO_DATA_BLOCK_ID};
+use crate::call_manager::{
+    CallManager, InvocationResult, ResourceLimit, ResourceLimitExceeded, NO_DATA_BLOCK_ID,
+};
 use crate::externs::{Consensus, Rand};
+use crate::machine::limiter::ExecMemory;
 use crate::state_tree::ActorState;
 use crate::syscall_error;
 
@@ -49,6 +53,7 @@ pub struct DefaultKernel<C> {
     actor_id: ActorID,
     method: MethodNum,
     value_received: TokenAmount,
+    read_only: bool,
 
     /// The call manager for this call stack. If this kernel calls another actor, it will
     /// temporarily "give" the call manager to the other kernel before re-attaching it.
@@ -82,6 +87,7 @@ where
         actor_id: ActorID,
         method: MethodNum,
         value_received: TokenAmount,
+        read_only: bool,
     ) -> Self {
         DefaultKernel {
             call_manager: mgr,
@@ -90,6 +96,7 @@ where
             actor_id,
             method,
             value_received,
+            read_only,
         }
     }
 
@@ -128,6 +135,35 @@ where
                 }
             })
     }
+
+    /// Returns a [`ReadOnly`](ErrorNumber::ReadOnly) error if this call may not mutate state.
+    fn check_writable(&self, op: &str) -> Result<()> {
+        if self.read_only {
+            return Err(syscall_error!(ReadOnly; "cannot {} in read-only mode", op).into());
+        }
+        Ok(())
+    }
+
+    /// Stores a block in the block registry, recording the exceeded limit if it's full.
+    fn put_block(&mut self, block: Block) -> Result<BlockId> {
+        let res = self.blocks.put(block);
+        if let Err(BlockPutError::TooManyBlocks) = res {
+            self.record_block_limit_exceeded();
+        }
+        Ok(res?)
+    }
+
+    /// Records that the block registry is full, so that it's reported as the cause of the
+    /// failing syscall.
+    fn record_block_limit_exceeded(&mut self) {
+        self.call_manager
+            .limiter_mut()
+            .set_syscall_limit_exceeded(ResourceLimitExceeded {
+                kind: ResourceLimit::Blocks,
+                limit: MAX_BLOCKS as u64,
+                attempted: MAX_BLOCKS as u64 + 1,
+            });
+    }
 }
 
 impl<C> SelfOps for DefaultKernel<C>
@@ -144,6 +180,7 @@ where
     }
 
     fn set_root(&mut self, new: Cid) -> Result<()> {
+        self.check_writable("set the state root")?;
         self.mutate_self(|actor_state| {
             actor_state.state = new;
             Ok(())
@@ -158,6 +195,7 @@ where
     fn self_destruct(&mut self, beneficiary: &Address) -> Result<()> {
         // Idempotentcy: If the actor doesn't exist, this won't actually do anything. The current
         // balance will be zero, and `delete_actor_id` will be a no-op.
+        self.check_writable("self destruct")?;
         self.call_manager
             .charge_gas(self.call_manager.price_list().on_delete_actor())?;
 
@@ -218,7 +256,7 @@ where
         )?;
 
         let stat = block.stat();
-        let id = self.blocks.put(block)?;
+        let id = self.put_block(block)?;
         Ok((id, stat))
     }
 
@@ -226,7 +264,7 @@ where
         self.call_manager
             .charge_gas(self.call_manager.price_list().on_block_create(data.len()))?;
 
-        Ok(self.blocks.put(Block::new(codec, data))?)
+        self.put_block(Block::new(codec, data))
     }
 
     fn block_link(&mut self, id: BlockId, hash_fun: u64, hash_len: u32) -> Result<Cid> {
@@ -331,6 +369,10 @@ where
     fn msg_gas_limit(&self) -> u64 {
         self.call_manager.gas_tracker().gas_limit().round_down() as u64
     }
+
+    fn read_only(&self) -> bool {
+        self.read_only
+    }
 }
 
 impl<C> SendOps for DefaultKernel<C>
@@ -343,8 +385,11 @@ where
         method: MethodNum,
         params_id: BlockId,
         value: &TokenAmount,
+        gas_limit: Option<Gas>,
+        flags: SendFlags,
     ) -> Result<SendResult> {
         let from = self.actor_id;
+        let read_only = self.read_only || flags.read_only();
 
         // Load parameters.
         let params = if params_id == NO_DATA_BLOCK_ID {
@@ -355,13 +400,16 @@ where
 
         // Make sure we can actually store the return block.
         if self.blocks.is_full() {
+            self.record_block_limit_exceeded();
             return Err(syscall_error!(LimitExceeded; "cannot store return block").into());
         }
 
         // Send.
-        let result = self
-            .call_manager
-            .with_transaction(|cm| cm.send::<Self>(from, *recipient, method, params, value))?;
+        let result = self.call_manager.with_transaction(|cm| {
+            cm.send::<Self>(
+                from, *recipient, method, params, value, gas_limit, read_only,
+            )
+        })?;
 
         // Store result and return.
         Ok(match result {
@@ -744,6 +792,8 @@ where
         actor_id: ActorID,
         predictable_address: Option<Address>,
     ) -> Result<()> {
+        self.check_writable("create actors")?;
+
         // TODO https://github.com/filecoin-project/builtin-actors/issues/492
         let singleton = self
             .call_manager
@@ -927,6 +977,7 @@ where
     C: CallManager,
 {
     fn emit_event(&mut self, evt: ActorEvent) -> Result<()> {
+        self.check_writable("emit events")?;
         self.call_manager
             .charge_gas(self.call_manager.price_list().on_actor_event(&evt))?;
 
@@ -14,11 +14,14 @@ use fvm_shared::sector::{
     AggregateSealVerifyProofAndInfos, RegisteredSealProof, ReplicaUpdateInfo, SealVerifyInfo,
     WindowPoStVerifyInfo,
 };
+use fvm_shared::sys::SendFlags;
 use fvm_shared::version::NetworkVersion;
 use fvm_shared::{ActorID, MethodNum};
 
 mod hash;
 
+pub(crate) use hash::SupportedHashes;
+
 mod blocks;
 pub mod default;
 
@@ -27,12 +30,13 @@ pub(crate) mod error;
 pub use error::{ClassifyResult, Context, ExecutionError, Result, SyscallError};
 use fvm_shared::event::{ActorEvent, StampedEvent};
 use multihash::MultihashGeneric;
-use wasmtime::ResourceLimiter;
+use wasmtime::{Linker, ResourceLimiter};
 
 use crate::call_manager::CallManager;
 use crate::gas::{Gas, PriceList};
 use crate::machine::limiter::ExecMemory;
 use crate::machine::Machine;
+use crate::syscalls::InvocationData;
 
 pub enum SendResult {
     Return(BlockId, BlockStat),
@@ -77,6 +81,7 @@ pub trait Kernel:
     /// - `method` is the method that has been invoked.
     /// - `value_received` is value received due to the current call.
     /// - `blocks` is the initial block registry (should already contain the parameters).
+    /// - `read_only` is true if this actor, or one of its callers, was called in read-only mode.
     #[allow(clippy::too_many_arguments)]
     fn new(
         mgr: Self::CallManager,
@@ -85,12 +90,37 @@ pub trait Kernel:
         actor_id: ActorID,
         method: MethodNum,
         value_received: TokenAmount,
+        read_only: bool,
     ) -> Self
     where
         Self: Sized;
 
     /// The kernel's underlying "machine".
     fn machine(&self) -> &<Self::CallManager as CallManager>::Machine;
+
+    /// Binds additional syscalls exposed by the embedder, on top of the ones bound by
+    /// [`bind_syscalls`][crate::syscalls::bind_syscalls]. This is called once per kernel type, when
+    /// the linker is first created.
+    ///
+    /// Custom syscalls should be bound with [`BindSyscall`][crate::syscalls::BindSyscall], which
+    /// takes care of gas accounting and error handling. They may not replace the built-in
+    /// syscalls, and are subject to the syscall capability table like any other syscall (see
+    /// [`SyscallGroups::of_syscall`][crate::machine::capabilities::SyscallGroups::of_syscall]).
+    ///
+    /// The linker belongs to the kernel `K` actors are executed with, which is `Self` unless this
+    /// kernel is wrapped by another kernel. Kernels wrapping another kernel should forward this
+    /// call to the wrapped kernel to keep its custom syscalls available. Custom syscalls should
+    /// therefore be generic over the kernel they're invoked on.
+    ///
+    /// By default, no custom syscalls are bound.
+    fn bind_custom_syscalls<K: Kernel>(
+        _linker: &mut Linker<InvocationData<K>>,
+    ) -> anyhow::Result<()>
+    where
+        Self: Sized,
+    {
+        Ok(())
+    }
 }
 
 /// Network-related operations.
@@ -133,6 +163,9 @@ pub trait MessageOps {
 
     /// The current message gas limit
     fn msg_gas_limit(&self) -> u64;
+
+    /// Whether this call is read-only, i.e. may not mutate state (constant).
+    fn read_only(&self) -> bool;
 }
 
 /// The IPLD subset of the kernel.
@@ -232,12 +265,16 @@ pub trait ActorOps {
 
 /// Operations to send messages to other actors.
 pub trait SendOps {
+    /// Sends a message to another actor. If `gas_limit` is specified, the callee may use at most
+    /// that much gas. With [`SendFlags::READ_ONLY`], the callee may not mutate state.
     fn send(
         &mut self,
         recipient: &Address,
         method: u64,
         params: BlockId,
         value: &TokenAmount,
+        gas_limit: Option<Gas>,
+        flags: SendFlags,
     ) -> Result<SendResult>;
 }
 
@@ -0,0 +1,204 @@
+//! The syscall capability table, which determines which syscalls each actor may call.
+//!
+//! Syscalls are organized in [`SyscallGroups`]. Calling a syscall outside of the groups granted to
+//! the calling actor fails with [`ErrorNumber::Forbidden`](fvm_shared::error::ErrorNumber::Forbidden).
+
+use std::collections::HashMap;
+
+use bitflags::bitflags;
+use cid::Cid;
+
+use super::Manifest;
+
+bitflags! {
+    /// Groups of syscalls that can be granted to actors. Syscalls in the `vm` and `gas` modules
+    /// don't belong to any group and may always be called.
+    pub struct SyscallGroups: u32 {
+        /// Everyday syscalls: IPLD, self state, actor lookups, sends, events, randomness, network
+        /// information, hashing, signatures and debugging.
+        const BASIC              = 0b00000001;
+        /// Proof verification syscalls: seals, PoSts, aggregate seals, replica updates, consensus
+        /// faults and unsealed sector CIDs.
+        const PROOFS             = 0b00000010;
+        /// Batch seal verification (`batch_verify_seals`).
+        const BATCH_VERIFY_SEALS = 0b00000100;
+        /// Actor creation (`new_actor_address` and `create_actor`).
+        const CREATE_ACTOR       = 0b00001000;
+        /// Actor code installation (`install_actor`).
+        const INSTALL_ACTOR      = 0b00010000;
+    }
+}
+
+impl SyscallGroups {
+    /// Returns the group the given syscall belongs to. The result is empty for syscalls that may
+    /// always be called.
+    pub fn of_syscall(module: &str, name: &str) -> Self {
+        match (module, name) {
+            ("vm" | "gas", _) => Self::empty(),
+            ("actor", "new_actor_address" | "create_actor") => Self::CREATE_ACTOR,
+            ("actor", "install_actor") => Self::INSTALL_ACTOR,
+            ("crypto", "batch_verify_seals") => Self::BATCH_VERIFY_SEALS,
+            (
+                "crypto",
+                "verify_seal"
+                | "verify_post"
+                | "compute_unsealed_sector_cid"
+                | "verify_consensus_fault"
+                | "verify_aggregate_seals"
+                | "verify_replica_update",
+            ) => Self::PROOFS,
+            _ => Self::BASIC,
+        }
+    }
+}
+
+/// Maps actor code to the [`SyscallGroups`] it may use. Built-in actors are referred to by their
+/// name in the builtin-actors manifest (e.g., `"init"`), so the table is independent of the actors
+/// version.
+///
+/// The groups for an actor are looked up by code CID first, then by built-in actor name, falling
+/// back to the default groups.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capabilities {
+    default: SyscallGroups,
+    by_builtin: HashMap<String, SyscallGroups>,
+    by_code: HashMap<Cid, SyscallGroups>,
+}
+
+impl Default for Capabilities {
+    /// Grants all syscalls to all actors, as no existing network version restricts syscalls. See
+    /// [`Capabilities::restricted`] for a table restricting the privileged syscalls.
+    fn default() -> Self {
+        Self::unrestricted()
+    }
+}
+
+impl Capabilities {
+    /// Creates a table granting `default` to all actors.
+    pub fn new(default: SyscallGroups) -> Self {
+        Self {
+            default,
+            by_builtin: HashMap::new(),
+            by_code: HashMap::new(),
+        }
+    }
+
+    /// Creates a table granting all syscalls to all actors.
+    pub fn unrestricted() -> Self {
+        Self::new(SyscallGroups::all())
+    }
+
+    /// Creates a table restricting the privileged syscalls to the built-in actors that need them:
+    /// actor creation and installation to the init actor, and batch seal verification to the
+    /// storage power actor.
+    pub fn restricted() -> Self {
+        let mut caps = Self::new(SyscallGroups::BASIC | SyscallGroups::PROOFS);
+        caps.set_builtin(
+            "init",
+            SyscallGroups::BASIC
+                | SyscallGroups::PROOFS
+                | SyscallGroups::CREATE_ACTOR
+                | SyscallGroups::INSTALL_ACTOR,
+        )
+        .set_builtin(
+            "storagepower",
+            SyscallGroups::BASIC | SyscallGroups::PROOFS | SyscallGroups::BATCH_VERIFY_SEALS,
+        );
+        caps
+    }
+
+    /// Sets the groups granted to actors without a more specific entry.
+    pub fn set_default(&mut self, groups: SyscallGroups) -> &mut Self {
+        self.default = groups;
+        self
+    }
+
+    /// Sets the groups granted to the built-in actor with the given manifest name.
+    pub fn set_builtin(&mut self, name: impl Into<String>, groups: SyscallGroups) -> &mut Self {
+        self.by_builtin.insert(name.into(), groups);
+        self
+    }
+
+    /// Sets the groups granted to actors with the given code CID.
+    pub fn set_code(&mut self, code: Cid, groups: SyscallGroups) -> &mut Self {
+        self.by_code.insert(code, groups);
+        self
+    }
+
+    /// Returns the groups granted to actors with the given code CID.
+    pub fn groups(&self, code: &Cid, manifest: &Manifest) -> SyscallGroups {
+        if let Some(groups) = self.by_code.get(code) {
+            return *groups;
+        }
+        self.by_builtin
+            .iter()
+            .find(|(name, _)| manifest.code_by_name(name) == Some(code))
+            .map(|(_, groups)| *groups)
+            .unwrap_or(self.default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use multihash::{Code, MultihashDigest};
+
+    use super::*;
+
+    #[test]
+    fn syscall_groups() {
+        assert_eq!(
+            SyscallGroups::of_syscall("vm", "abort"),
+            SyscallGroups::empty()
+        );
+        assert_eq!(
+            SyscallGroups::of_syscall("ipld", "block_open"),
+            SyscallGroups::BASIC
+        );
+        assert_eq!(
+            SyscallGroups::of_syscall("crypto", "hash"),
+            SyscallGroups::BASIC
+        );
+        assert_eq!(
+            SyscallGroups::of_syscall("crypto", "verify_post"),
+            SyscallGroups::PROOFS
+        );
+        assert_eq!(
+            SyscallGroups::of_syscall("crypto", "batch_verify_seals"),
+            SyscallGroups::BATCH_VERIFY_SEALS
+        );
+        assert_eq!(
+            SyscallGroups::of_syscall("actor", "create_actor"),
+            SyscallGroups::CREATE_ACTOR
+        );
+    }
+
+    #[test]
+    fn lookup() {
+        let manifest = Manifest::dummy();
+        let init = *manifest.get_init_code();
+        let account = *manifest.get_account_code();
+        let user = Cid::new_v1(fvm_shared::IPLD_RAW, Code::Blake2b256.digest(b"user"));
+
+        let mut caps = Capabilities::restricted();
+        assert!(caps
+            .groups(&init, &manifest)
+            .contains(SyscallGroups::CREATE_ACTOR));
+        assert!(!caps
+            .groups(&account, &manifest)
+            .contains(SyscallGroups::CREATE_ACTOR));
+        assert_eq!(
+            caps.groups(&user, &manifest),
+            SyscallGroups::BASIC | SyscallGroups::PROOFS
+        );
+
+        // Code CIDs take precedence over built-in names, which take precedence over the default.
+        caps.set_default(SyscallGroups::BASIC)
+            .set_code(init, SyscallGroups::empty());
+        assert_eq!(caps.groups(&init, &manifest), SyscallGroups::empty());
+        assert_eq!(caps.groups(&user, &manifest), SyscallGroups::BASIC);
+
+        let caps = Capabilities::unrestricted();
+        assert_eq!(caps.groups(&account, &manifest), SyscallGroups::all());
+        assert_eq!(Capabilities::default(), caps);
+    }
+}
@@ -2,7 +2,6 @@ use std::ops::RangeInclusive;
 
 use anyhow::{anyhow, Context as _};
 use cid::Cid;
-use fvm_ipld_amt::Amt;
 use fvm_ipld_blockstore::{Blockstore, Buffered};
 use fvm_ipld_encoding::CborStore;
 use fvm_shared::address::Address;
@@ -15,6 +14,7 @@ use log::debug;
 
 use super::{Engine, Machine, MachineContext};
 use crate::blockstore::BufferedBlockstore;
+use crate::executor::commitments::events_root;
 use crate::externs::Externs;
 #[cfg(feature = "m2-native")]
 use crate::init_actor::State as InitActorState;
@@ -25,8 +25,6 @@ use crate::state_tree::{ActorState, StateTree};
 use crate::syscall_error;
 use crate::system_actor::State as SystemActorState;
 
-pub const EVENTS_AMT_BITWIDTH: u32 = 5;
-
 pub struct DefaultMachine<B, E> {
     /// The initial execution context for this epoch.
     context: MachineContext,
@@ -100,7 +98,11 @@ where
         // Create a new state tree from the supplied root.
         let state_tree = {
             let bstore = BufferedBlockstore::new(blockstore);
-            StateTree::new_from_root(bstore, &context.initial_state_root)?
+            let state_tree = StateTree::new_from_root(bstore, &context.initial_state_root)?;
+            match &context.state_tree_node_cache {
+                Some(cache) => state_tree.with_node_cache(cache.clone()),
+                None => state_tree,
+            }
         };
 
         // Load the built-in actors manifest.
@@ -268,22 +270,11 @@ where
     }
 
     fn commit_events(&self, events: &[StampedEvent]) -> Result<Option<Cid>> {
-        if events.is_empty() {
-            return Ok(None);
-        }
-
         let blockstore = self.blockstore();
 
-        let amt_cid = {
-            let mut amt = Amt::new_with_bit_width(blockstore, EVENTS_AMT_BITWIDTH);
-            // TODO this can be zero-copy if the AMT supports a batch set operation that takes an
-            //  iterator of references and flushes the batch at the end.
-            amt.batch_set(events.iter().cloned())
-                .context("failed to add events to AMT")
-                .or_fatal()?;
-            amt.flush()
-                .context("failed to flush events AMT")
-                .or_fatal()?
+        let amt_cid = match events_root(blockstore, events).or_fatal()? {
+            Some(cid) => cid,
+            None => return Ok(None),
         };
 
         blockstore
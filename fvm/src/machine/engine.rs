@@ -18,6 +18,7 @@ use wasmtime::{
 use super::limiter::ExecMemory;
 use super::Machine;
 use crate::gas::WasmGasPrices;
+use crate::machine::capabilities::SyscallGroups;
 use crate::machine::NetworkConfig;
 use crate::syscalls::{bind_syscalls, charge_for_init, InvocationData};
 use crate::Kernel;
@@ -35,8 +36,9 @@ pub struct MultiEngine(Arc<Mutex<HashMap<EngineConfig, Engine>>>);
 pub struct EngineConfig {
     pub max_call_depth: u32,
     pub max_wasm_stack: u32,
+    pub wasm_stack_reporting: bool,
     pub max_inst_memory_bytes: u64,
-    pub wasm_prices: &'static WasmGasPrices,
+    pub wasm_prices: WasmGasPrices,
     pub actor_redirect: Vec<(Cid, Cid)>,
 }
 
@@ -45,8 +47,9 @@ impl From<&NetworkConfig> for EngineConfig {
         EngineConfig {
             max_call_depth: nc.max_call_depth,
             max_wasm_stack: nc.max_wasm_stack,
+            wasm_stack_reporting: nc.wasm_stack_reporting,
             max_inst_memory_bytes: nc.max_inst_memory_bytes,
-            wasm_prices: &nc.price_list.wasm_rules,
+            wasm_prices: nc.price_list.wasm_rules.clone(),
             actor_redirect: nc.actor_redirect.clone(),
         }
     }
@@ -342,12 +345,18 @@ impl Engine {
         //   making it charge gas based on memory requested
         // * divide code into metered blocks, and add a call to the gas counter
         //   function before entering each metered block
-        let mut m = inject(m, self.0.config.wasm_prices, "gas")
+        let mut m = inject(m, &self.0.config.wasm_prices, "gas")
             .map_err(|_| anyhow::Error::msg("injecting gas counter failed"))?;
 
         // Work around #602. Remove this once paritytech/parity-wasm#331 is merged and bubbled.
         fix_wasm_sections(&mut m);
 
+        // Export the stack height and record its peak, for reporting. This is done after injecting
+        // gas metering so that the extra instructions aren't charged for.
+        if self.0.config.wasm_stack_reporting {
+            inject_stack_height_exports(&mut m, self.0.config.max_wasm_stack)?;
+        }
+
         let wasm = m.to_bytes()?;
         let module = Module::from_binary(&self.0.engine, wasm.as_slice())?;
 
@@ -418,6 +427,12 @@ impl Engine {
                     linker.allow_shadowing(true);
 
                     bind_syscalls(&mut linker)?;
+
+                    // Custom syscalls may not replace the built-in ones.
+                    linker.allow_shadowing(false);
+                    K::bind_custom_syscalls(&mut linker)?;
+                    linker.allow_shadowing(true);
+
                     Box::new(Cache { linker })
                 })
                 .downcast_mut()
@@ -457,8 +472,13 @@ impl Engine {
         }
     }
 
-    /// Construct a new wasmtime "store" from the given kernel.
-    pub fn new_store<K: Kernel>(&self, mut kernel: K) -> wasmtime::Store<InvocationData<K>> {
+    /// Construct a new wasmtime "store" from the given kernel, for an actor that may call the
+    /// syscalls in `capabilities`.
+    pub fn new_store<K: Kernel>(
+        &self,
+        mut kernel: K,
+        capabilities: SyscallGroups,
+    ) -> wasmtime::Store<InvocationData<K>> {
         let memory_bytes = kernel.limiter_mut().curr_exec_memory_bytes();
 
         let id = InvocationData {
@@ -468,6 +488,7 @@ impl Engine {
             last_milligas_available: 0,
             last_memory_bytes: memory_bytes,
             memory: self.0.dummy_memory,
+            capabilities,
         };
 
         let mut store = wasmtime::Store::new(&self.0.engine, id);
@@ -482,6 +503,117 @@ impl Engine {
     }
 }
 
+/// The name of the exported global recording the peak stack height reached by an instance, as
+/// counted by the stack limiter.
+pub(crate) const STACK_HEIGHT_MAX_NAME: &str = "__fvm_stack_height_max";
+
+/// The name of the exported global holding the stack limiter's current stack height. If the stack
+/// limiter traps, this is left above the limit.
+pub(crate) const STACK_HEIGHT_NAME: &str = "__fvm_stack_height";
+
+/// Adds a global tracking the peak of the stack height maintained by the stack limiter, and exports
+/// it as [`STACK_HEIGHT_MAX_NAME`]. The stack height itself is exported as [`STACK_HEIGHT_NAME`].
+///
+/// The stack limiter increments the stack height and checks it against the limit before every
+/// call. We update the peak right after each of these checks. Fails if no such check is found,
+/// as the stack limiter's output must have changed.
+fn inject_stack_height_exports(
+    module: &mut elements::Module,
+    stack_limit: u32,
+) -> anyhow::Result<()> {
+    use elements::Instruction::*;
+    use elements::{
+        BlockType, ExportEntry, GlobalEntry, GlobalType, ImportCountType, InitExpr, Internal,
+        ValueType,
+    };
+
+    let num_globals = {
+        let globals = module
+            .global_section_mut()
+            .context("stack limiter didn't add a global")?
+            .entries_mut();
+        globals.push(GlobalEntry::new(
+            GlobalType::new(ValueType::I32, true),
+            InitExpr::new(vec![I32Const(0), End]),
+        ));
+        globals.len()
+    };
+    let max_idx = (module.import_count(ImportCountType::Global) + num_globals - 1) as u32;
+
+    let mut stack_height_idx = None;
+    let bodies = module
+        .code_section_mut()
+        .map(|code_section| code_section.bodies_mut().as_mut_slice())
+        .unwrap_or_default();
+    for body in bodies {
+        let code = body.code_mut().elements_mut();
+        let mut i = 0;
+        while i < code.len() {
+            // The stack limiter's preamble: increment the stack height, and trap if it exceeds the
+            // limit. The trap block may have been metered, so we look for its end.
+            let height_idx = match &code[i..] {
+                [GetGlobal(a), I32Const(_), I32Add, SetGlobal(b), GetGlobal(c), I32Const(limit), I32GtU, If(BlockType::NoResult), ..]
+                    if a == b && a == c && *limit == stack_limit as i32 =>
+                {
+                    *a
+                }
+                _ => {
+                    i += 1;
+                    continue;
+                }
+            };
+            stack_height_idx = Some(height_idx);
+            let at = block_end(code, i + 8).context("unterminated stack limiter check")?;
+            let update = [
+                GetGlobal(height_idx),
+                GetGlobal(max_idx),
+                I32GtU,
+                If(BlockType::NoResult),
+                GetGlobal(height_idx),
+                SetGlobal(max_idx),
+                End,
+            ];
+            i = at + update.len();
+            code.splice(at..at, update);
+        }
+    }
+
+    let height_idx = stack_height_idx.context("stack limiter preamble not found")?;
+    let exports = module
+        .export_section_mut()
+        .context("actor has no exports")?
+        .entries_mut();
+    exports.push(ExportEntry::new(
+        STACK_HEIGHT_MAX_NAME.to_owned(),
+        Internal::Global(max_idx),
+    ));
+    exports.push(ExportEntry::new(
+        STACK_HEIGHT_NAME.to_owned(),
+        Internal::Global(height_idx),
+    ));
+    Ok(())
+}
+
+/// Returns the index right after the `end` of the block whose body starts at `start`.
+fn block_end(code: &[elements::Instruction], start: usize) -> Option<usize> {
+    use elements::Instruction::*;
+
+    let mut depth = 1;
+    for (i, instr) in code.iter().enumerate().skip(start) {
+        match instr {
+            Block(_) | Loop(_) | If(_) => depth += 1,
+            End => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
 // Workaround for https://github.com/filecoin-project/ref-fvm/issues/602
 //
 // This removes the out-of-order data count section, if it exists, and re-inserts it (with the
@@ -1,5 +1,8 @@
+use std::mem;
+
 use wasmtime::ResourceLimiter;
 
+use crate::call_manager::{ResourceLimit, ResourceLimitExceeded};
 use crate::machine::NetworkConfig;
 
 /// Execution level memory tracking and adjustment.
@@ -10,6 +13,20 @@ where
     /// Get a snapshot of the total memory required by the modules on the call stack so far.
     fn curr_exec_memory_bytes(&self) -> usize;
 
+    /// Get the peak total memory required by the modules on the call stack during the current
+    /// stack frame, including nested frames.
+    fn peak_exec_memory_bytes(&self) -> usize;
+
+    /// Get the memory limit the current stack frame failed to grow its memory beyond, if any.
+    fn memory_limit_exceeded(&self) -> Option<ResourceLimitExceeded>;
+
+    /// Record a resource limit exceeded by the current syscall, to be reported as the cause of the
+    /// syscall's failure.
+    fn set_syscall_limit_exceeded(&mut self, limit: ResourceLimitExceeded);
+
+    /// Take the resource limit exceeded by the current syscall, if any.
+    fn take_syscall_limit_exceeded(&mut self) -> Option<ResourceLimitExceeded>;
+
     /// Push a new frame onto the call stack, and keep tallying up the current execution memory,
     /// then restore it to the current value when the frame is finished.
     fn with_stack_frame<T, G, F, R>(t: &mut T, g: G, f: F) -> R
@@ -27,6 +44,12 @@ pub struct ExecResourceLimiter {
     max_exec_memory_bytes: usize,
     /// Total bytes desired so far by all the instances including the currently executing instance on the call stack.
     curr_exec_memory_bytes: usize,
+    /// Peak of `curr_exec_memory_bytes` during the current stack frame.
+    peak_exec_memory_bytes: usize,
+    /// The last memory limit exceeded during the current stack frame.
+    memory_limit_exceeded: Option<ResourceLimitExceeded>,
+    /// The resource limit exceeded by the current syscall.
+    syscall_limit_exceeded: Option<ResourceLimitExceeded>,
 }
 
 impl ExecResourceLimiter {
@@ -35,6 +58,9 @@ impl ExecResourceLimiter {
             max_inst_memory_bytes,
             max_exec_memory_bytes,
             curr_exec_memory_bytes: 0,
+            peak_exec_memory_bytes: 0,
+            memory_limit_exceeded: None,
+            syscall_limit_exceeded: None,
         }
     }
 
@@ -49,6 +75,13 @@ impl ExecResourceLimiter {
 impl ResourceLimiter for ExecResourceLimiter {
     fn memory_growing(&mut self, current: usize, desired: usize, maximum: Option<usize>) -> bool {
         if desired > min(self.max_inst_memory_bytes, maximum) {
+            if desired > self.max_inst_memory_bytes {
+                self.memory_limit_exceeded = Some(ResourceLimitExceeded {
+                    kind: ResourceLimit::InstanceMemory,
+                    limit: self.max_inst_memory_bytes as u64,
+                    attempted: desired as u64,
+                });
+            }
             return false;
         }
 
@@ -56,10 +89,16 @@ impl ResourceLimiter for ExecResourceLimiter {
         let total_desired = self.curr_exec_memory_bytes.saturating_add(delta_desired);
 
         if total_desired > self.max_exec_memory_bytes {
+            self.memory_limit_exceeded = Some(ResourceLimitExceeded {
+                kind: ResourceLimit::ExecMemory,
+                limit: self.max_exec_memory_bytes as u64,
+                attempted: total_desired as u64,
+            });
             return false;
         }
 
         self.curr_exec_memory_bytes = total_desired;
+        self.peak_exec_memory_bytes = self.peak_exec_memory_bytes.max(total_desired);
         true
     }
 
@@ -74,16 +113,38 @@ impl ExecMemory for ExecResourceLimiter {
         self.curr_exec_memory_bytes
     }
 
+    fn peak_exec_memory_bytes(&self) -> usize {
+        self.peak_exec_memory_bytes
+    }
+
+    fn memory_limit_exceeded(&self) -> Option<ResourceLimitExceeded> {
+        self.memory_limit_exceeded
+    }
+
+    fn set_syscall_limit_exceeded(&mut self, limit: ResourceLimitExceeded) {
+        self.syscall_limit_exceeded = Some(limit);
+    }
+
+    fn take_syscall_limit_exceeded(&mut self) -> Option<ResourceLimitExceeded> {
+        self.syscall_limit_exceeded.take()
+    }
+
     fn with_stack_frame<T, G, F, R>(t: &mut T, g: G, f: F) -> R
     where
         G: Fn(&mut T) -> &mut Self,
         F: FnOnce(&mut T) -> R,
     {
-        let memory_bytes = g(t).curr_exec_memory_bytes;
+        let limiter = g(t);
+        let memory_bytes = limiter.curr_exec_memory_bytes;
+        let peak_memory_bytes = mem::replace(&mut limiter.peak_exec_memory_bytes, memory_bytes);
+        let memory_limit_exceeded = limiter.memory_limit_exceeded.take();
         let ret = f(t);
         // This method is part of the trait so that a setter like this
         // doesn't have to be made public.
-        g(t).curr_exec_memory_bytes = memory_bytes;
+        let limiter = g(t);
+        limiter.curr_exec_memory_bytes = memory_bytes;
+        limiter.peak_exec_memory_bytes = limiter.peak_exec_memory_bytes.max(peak_memory_bytes);
+        limiter.memory_limit_exceeded = memory_limit_exceeded;
         ret
     }
 }
@@ -97,6 +158,7 @@ mod tests {
     use wasmtime::ResourceLimiter;
 
     use super::ExecResourceLimiter;
+    use crate::call_manager::{ResourceLimit, ResourceLimitExceeded};
     use crate::machine::limiter::ExecMemory;
 
     #[test]
@@ -118,12 +180,63 @@ mod tests {
                         assert!(!limits.memory_growing(0, 3, None)); // Fail, 4+4+3 would be over the call stack limit of 10.
                         assert!(limits.memory_growing(0, 2, None)); // Ok, just at the call stack limit (although we should used a seen a push as well.)
                         assert_eq!(limits.curr_exec_memory_bytes(), 4 + 4 + 2);
+                        assert_eq!(limits.peak_exec_memory_bytes(), 4 + 4 + 2);
                     },
                 );
                 assert_eq!(limits.curr_exec_memory_bytes(), 4 + 4);
+                assert_eq!(limits.peak_exec_memory_bytes(), 4 + 4 + 2);
             },
         );
         assert_eq!(limits.curr_exec_memory_bytes(), 4);
+        assert_eq!(limits.peak_exec_memory_bytes(), 4 + 4 + 2);
+        ExecResourceLimiter::with_stack_frame(
+            &mut limits,
+            |x| x,
+            |limits| {
+                // The peak is tracked per stack frame.
+                assert!(limits.memory_growing(0, 1, None));
+                assert_eq!(limits.peak_exec_memory_bytes(), 4 + 1);
+            },
+        );
+        assert_eq!(limits.peak_exec_memory_bytes(), 4 + 4 + 2);
+    }
+
+    #[test]
+    fn memory_limit_exceeded() {
+        let mut limits = ExecResourceLimiter::new(4, 6);
+        assert!(!limits.memory_growing(0, 3, Some(2))); // The module's own maximum isn't our limit.
+        assert_eq!(limits.memory_limit_exceeded(), None);
+        assert!(!limits.memory_growing(0, 5, None));
+        assert_eq!(
+            limits.memory_limit_exceeded(),
+            Some(ResourceLimitExceeded {
+                kind: ResourceLimit::InstanceMemory,
+                limit: 4,
+                attempted: 5,
+            })
+        );
+        assert!(limits.memory_growing(0, 4, None));
+        ExecResourceLimiter::with_stack_frame(
+            &mut limits,
+            |x| x,
+            |limits| {
+                // Each stack frame tracks its own failures.
+                assert_eq!(limits.memory_limit_exceeded(), None);
+                assert!(!limits.memory_growing(0, 3, None));
+                assert_eq!(
+                    limits.memory_limit_exceeded(),
+                    Some(ResourceLimitExceeded {
+                        kind: ResourceLimit::ExecMemory,
+                        limit: 6,
+                        attempted: 7,
+                    })
+                );
+            },
+        );
+        assert_eq!(
+            limits.memory_limit_exceeded().map(|l| l.kind),
+            Some(ResourceLimit::InstanceMemory)
+        );
     }
 
     #[test]
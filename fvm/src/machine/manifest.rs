@@ -31,6 +31,7 @@ pub struct Manifest {
     eam_code: Cid,
     singletons: HashSet<Cid>,
 
+    by_name: HashMap<String, Cid>,
     by_id: HashMap<u32, Cid>,
     by_code: HashMap<Cid, u32>,
 }
@@ -140,6 +141,7 @@ impl Manifest {
             embryo_code,
             eam_code,
             singletons,
+            by_name,
             by_id,
             by_code,
         })
@@ -150,6 +152,11 @@ impl Manifest {
         self.by_id.get(&id)
     }
 
+    /// Returns the code CID for a builtin actor, given the actor's name in the manifest.
+    pub fn code_by_name(&self, name: &str) -> Option<&Cid> {
+        self.by_name.get(name)
+    }
+
     /// Returns the the actor code's "id" if it's a builtin actor. Otherwise, returns 0.
     pub fn id_by_code(&self, code: &Cid) -> u32 {
         self.by_code.get(code).copied().unwrap_or(0)
@@ -1,3 +1,6 @@
+use std::borrow::Cow;
+use std::sync::Arc;
+
 use cid::Cid;
 use derive_more::{Deref, DerefMut};
 use fvm_ipld_blockstore::Blockstore;
@@ -9,15 +12,18 @@ use fvm_shared::ActorID;
 use num_traits::Zero;
 use wasmtime::ResourceLimiter;
 
+use self::capabilities::Capabilities;
 use crate::externs::Externs;
 use crate::gas::{price_list_by_network_version, PriceList};
 use crate::kernel::Result;
-use crate::state_tree::{ActorState, StateTree};
+use crate::state_tree::{ActorState, StateTree, StateTreeNodeCache};
 
 mod default;
 
 pub use default::DefaultMachine;
 
+pub mod capabilities;
+
 pub mod limiter;
 mod manifest;
 
@@ -26,6 +32,7 @@ pub use manifest::Manifest;
 mod engine;
 
 pub use engine::{Engine, EngineConfig, MultiEngine};
+pub(crate) use engine::{STACK_HEIGHT_MAX_NAME, STACK_HEIGHT_NAME};
 use fvm_shared::event::StampedEvent;
 
 use self::limiter::ExecMemory;
@@ -141,18 +148,61 @@ pub struct NetworkConfig {
     /// DEFAULT: `false`
     pub actor_debugging: bool,
 
+    /// Record the peak Wasm stack height reached by each invocation (see
+    /// [`PeakResources::wasm_stack`](crate::call_manager::PeakResources::wasm_stack)), and
+    /// attribute stack overflows to the stack limit. This instruments every actor with additional
+    /// (unmetered) code. When disabled, the reported stack height is always zero.
+    ///
+    /// DEFAULT: `false`
+    pub wasm_stack_reporting: bool,
+
     /// The price list.
     ///
     /// DEFAULT: The price-list for the current network version.
-    pub price_list: &'static PriceList,
+    pub price_list: Cow<'static, PriceList>,
 
     /// Actor redirects for debug execution
     pub actor_redirect: Vec<(Cid, Cid)>,
+
+    /// Report exceeding the Wasm stack limit, and exceeding memory limits while instantiating an
+    /// actor, through the dedicated `SYS_STACK_LIMIT_EXCEEDED` and `SYS_MEMORY_LIMIT_EXCEEDED` exit
+    /// codes instead of `SYS_ILLEGAL_INSTRUCTION` and a fatal error respectively. Stack overflows
+    /// can only be told apart from other traps with [`NetworkConfig::wasm_stack_reporting`].
+    ///
+    /// DEFAULT: `false`
+    pub limit_exit_codes: bool,
+
+    /// The syscalls each actor may call.
+    ///
+    /// DEFAULT: All syscalls are granted to all actors (see [`Capabilities::unrestricted`]).
+    pub capabilities: Capabilities,
 }
 
 impl NetworkConfig {
-    /// Create a new network config for the given network version.
+    /// Create a new network config for the given network version, using the built-in price list
+    /// for that version.
+    ///
+    /// Panics if there is no built-in price list for the network version. Use
+    /// [`NetworkConfig::new_with_price_list`] to configure other network versions (e.g., on
+    /// devnets).
     pub fn new(network_version: NetworkVersion) -> Self {
+        Self::with_price_list(
+            network_version,
+            Cow::Borrowed(price_list_by_network_version(network_version)),
+        )
+    }
+
+    /// Create a new network config for the given network version with a custom price list, e.g.,
+    /// one loaded through [`PriceList::load`]. Unlike [`NetworkConfig::new`], this works for any
+    /// network version.
+    pub fn new_with_price_list(network_version: NetworkVersion, price_list: PriceList) -> Self {
+        Self::with_price_list(network_version, Cow::Owned(price_list))
+    }
+
+    fn with_price_list(
+        network_version: NetworkVersion,
+        price_list: Cow<'static, PriceList>,
+    ) -> Self {
         NetworkConfig {
             network_version,
             max_call_depth: 1024,
@@ -160,9 +210,12 @@ impl NetworkConfig {
             max_inst_memory_bytes: 512 * (1 << 20),
             max_exec_memory_bytes: 512 * (1 << 20),
             actor_debugging: false,
+            wasm_stack_reporting: false,
+            limit_exit_codes: false,
             builtin_actors_override: None,
-            price_list: price_list_by_network_version(network_version),
+            price_list,
             actor_redirect: vec![],
+            capabilities: Capabilities::default(),
         }
     }
 
@@ -173,6 +226,22 @@ impl NetworkConfig {
         self
     }
 
+    /// Enable Wasm stack height reporting (see [`NetworkConfig::wasm_stack_reporting`]). This
+    /// instruments all actor code so it should only be enabled for local testing or as a
+    /// network-wide parameter.
+    pub fn enable_wasm_stack_reporting(&mut self) -> &mut Self {
+        self.wasm_stack_reporting = true;
+        self
+    }
+
+    /// Enable the dedicated exit codes for exceeded resource limits (see
+    /// [`NetworkConfig::limit_exit_codes`]). This is a consensus-critical option (changes receipts)
+    /// so it should only be enabled for local testing or as a network-wide parameter.
+    pub fn enable_limit_exit_codes(&mut self) -> &mut Self {
+        self.limit_exit_codes = true;
+        self
+    }
+
     /// Override actors with the specific manifest. This is primarily useful for testing, or
     /// networks prior to NV16 (where the actor's "manifest" isn't specified on-chain).
     pub fn override_actors(&mut self, manifest: Cid) -> &mut Self {
@@ -180,6 +249,21 @@ impl NetworkConfig {
         self
     }
 
+    /// Override the price list, e.g. with one loaded through [`PriceList::load`]. This is a
+    /// consensus-critical option, so it should only be used for local testing, devnets and gas
+    /// experiments.
+    pub fn override_price_list(&mut self, price_list: PriceList) -> &mut Self {
+        self.price_list = Cow::Owned(price_list);
+        self
+    }
+
+    /// Override the syscall capability table, e.g. to sandbox user actors. This is a
+    /// consensus-critical option.
+    pub fn override_capabilities(&mut self, capabilities: Capabilities) -> &mut Self {
+        self.capabilities = capabilities;
+        self
+    }
+
     /// Set actor redirects for debug execution
     pub fn redirect_actors(&mut self, actor_redirect: Vec<(Cid, Cid)>) -> &mut Self {
         self.actor_redirect = actor_redirect;
@@ -200,6 +284,7 @@ impl NetworkConfig {
             initial_state_root: initial_state,
             circ_supply: fvm_shared::TOTAL_FILECOIN.clone(),
             tracing: false,
+            state_tree_node_cache: None,
         }
     }
 
@@ -215,6 +300,7 @@ impl NetworkConfig {
             initial_state_root: initial_state,
             circ_supply: fvm_shared::TOTAL_FILECOIN.clone(),
             tracing: false,
+            state_tree_node_cache: None,
         }
     }
 }
@@ -260,6 +346,11 @@ pub struct MachineContext {
     /// Whether or not to produce execution traces in the returned result.
     /// Not consensus-critical, but has a performance impact.
     pub tracing: bool,
+
+    /// A cache of decoded state tree nodes, shared with other machines. Not consensus-critical.
+    ///
+    /// DEFAULT: No cache.
+    pub state_tree_node_cache: Option<Arc<StateTreeNodeCache>>,
 }
 
 impl MachineContext {
@@ -280,4 +371,12 @@ impl MachineContext {
         self.tracing = true;
         self
     }
+
+    /// Set [`MachineContext::state_tree_node_cache`]. Sharing the same cache between the
+    /// machines of consecutive epochs avoids decoding the same state tree nodes over and over
+    /// again.
+    pub fn set_state_tree_node_cache(&mut self, cache: Arc<StateTreeNodeCache>) -> &mut Self {
+        self.state_tree_node_cache = Some(cache);
+        self
+    }
 }
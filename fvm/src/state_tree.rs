@@ -3,13 +3,14 @@
 
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use anyhow::{anyhow, Context as _};
 use cid::{multihash, Cid};
 use fvm_ipld_blockstore::Blockstore;
 use fvm_ipld_encoding::tuple::*;
 use fvm_ipld_encoding::CborStore;
-use fvm_ipld_hamt::Hamt;
+use fvm_ipld_hamt::{BytesKey, Hamt, HamtNodeCache};
 use fvm_shared::address::{Address, Payload};
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::state::{StateInfo0, StateRoot, StateTreeVersion};
@@ -22,6 +23,10 @@ use crate::init_actor::State as InitActorState;
 use crate::kernel::{ClassifyResult, Context as _, ExecutionError, Result};
 use crate::{syscall_error, EMPTY_ARR_CID};
 
+/// A cache of decoded state tree nodes that can be shared between state trees. See
+/// [`StateTree::with_node_cache`].
+pub type StateTreeNodeCache = HamtNodeCache<BytesKey, ActorState>;
+
 /// State tree implementation using hamt. This structure is not threadsafe and should only be used
 /// in sync contexts.
 pub struct StateTree<S> {
@@ -281,6 +286,15 @@ where
         }
     }
 
+    /// Attaches a shared cache of decoded nodes, so that state trees loaded from the same (or
+    /// similar) roots don't decode the same nodes over and over again.
+    pub fn with_node_cache(self, cache: Arc<StateTreeNodeCache>) -> Self {
+        Self {
+            hamt: self.hamt.with_node_cache(cache),
+            ..self
+        }
+    }
+
     /// Retrieve store reference to modify db.
     pub fn store(&self) -> &S {
         self.hamt.store()
@@ -682,6 +696,8 @@ pub mod json {
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
     use cid::multihash::Code::Blake2b256;
     use cid::multihash::Multihash;
     use cid::Cid;
@@ -695,7 +711,7 @@ mod tests {
 
     use crate::init_actor;
     use crate::init_actor::INIT_ACTOR_ADDR;
-    use crate::state_tree::{ActorState, StateTree};
+    use crate::state_tree::{ActorState, StateTree, StateTreeNodeCache};
 
     lazy_static! {
         pub static ref DUMMY_ACCOUNT_ACTOR_CODE_ID: Cid = Cid::new_v1(
@@ -732,6 +748,27 @@ mod tests {
         assert_eq!(tree.get_actor(&addr).unwrap().unwrap(), act_a);
     }
 
+    #[test]
+    fn shared_node_cache() {
+        let store = MemoryBlockstore::default();
+        let mut tree = StateTree::new(&store, StateTreeVersion::V5).unwrap();
+        for id in 0..1000 {
+            let act = ActorState::new(empty_cid(), empty_cid(), Default::default(), id, None);
+            tree.set_actor(&Address::new_id(id), act).unwrap();
+        }
+        let root = tree.flush().unwrap();
+
+        let cache = Arc::new(StateTreeNodeCache::new(1024));
+        for _ in 0..2 {
+            let tree = StateTree::new_from_root(&store, &root)
+                .unwrap()
+                .with_node_cache(cache.clone());
+            let act = tree.get_actor_id(500).unwrap().unwrap();
+            assert_eq!(act.sequence, 500);
+        }
+        assert!(!cache.is_empty());
+    }
+
     #[test]
     fn delete_actor() {
         let store = MemoryBlockstore::default();
@@ -7,14 +7,18 @@ use wasmtime::{Caller, Linker, WasmTy};
 use super::context::Memory;
 use super::error::Abort;
 use super::{charge_for_exec, update_gas_available, Context, InvocationData};
-use crate::call_manager::backtrace;
+use crate::call_manager::{backtrace, ResourceLimitExceeded};
 use crate::kernel::{self, ExecutionError, Kernel, SyscallError};
+use crate::machine::capabilities::SyscallGroups;
+use crate::machine::limiter::ExecMemory;
 
 /// Binds syscalls to a linker, converting the returned error according to the syscall convention:
 ///
 /// 1. If the error is a syscall error, it's returned as the first return value.
 /// 2. If the error is a fatal error, a Trap is returned.
-pub(super) trait BindSyscall<Args, Ret, Func> {
+///
+/// Kernels can use this to bind custom syscalls in [`Kernel::bind_custom_syscalls`].
+pub trait BindSyscall<Args, Ret, Func> {
     /// Bind a syscall to the linker.
     ///
     /// 1. The return type will be automatically adjusted to return `Result<u32, Trap>` where
@@ -92,6 +96,40 @@ fn memory_and_data<'a, K: Kernel>(
     (Memory::new(mem), data)
 }
 
+/// Checks that the actor may call the given syscall. If it may not, records a
+/// [`Forbidden`](ErrorNumber::Forbidden) error and returns its code.
+fn check_capabilities<K: Kernel>(
+    data: &mut InvocationData<K>,
+    group: SyscallGroups,
+    module: &'static str,
+    name: &'static str,
+) -> Option<u32> {
+    if data.capabilities.contains(group) {
+        return None;
+    }
+    log::trace!("syscall {}::{}: forbidden", module, name);
+    let err = SyscallError::new(
+        ErrorNumber::Forbidden,
+        format!("actor may not call {}::{}", module, name),
+    );
+    data.last_error = Some(backtrace::Cause::from_syscall(module, name, err));
+    Some(ErrorNumber::Forbidden as u32)
+}
+
+/// Returns the cause of a syscall failure: the resource limit it exceeded, if any, or the syscall
+/// error itself.
+fn syscall_error_cause(
+    limit_exceeded: Option<ResourceLimitExceeded>,
+    module: &'static str,
+    name: &'static str,
+    err: SyscallError,
+) -> backtrace::Cause {
+    match limit_exceeded {
+        Some(limit) => backtrace::Cause::from_limit(limit),
+        None => backtrace::Cause::from_syscall(module, name, err),
+    }
+}
+
 macro_rules! charge_syscall_gas {
     ($kernel:expr) => {
         let charge = $kernel.price_list().on_syscall();
@@ -118,6 +156,7 @@ macro_rules! impl_bind_syscalls {
                 name: &'static str,
                 syscall: Func,
             ) -> anyhow::Result<&mut Self> {
+                let group = SyscallGroups::of_syscall(module, name);
                 if mem::size_of::<Ret::Value>() == 0 {
                     // If we're returning a zero-sized "value", we return no value therefore and expect no out pointer.
                     self.func_wrap(module, name, move |mut caller: Caller<'_, InvocationData<K>> $(, $t: $t)*| {
@@ -125,9 +164,15 @@ macro_rules! impl_bind_syscalls {
 
                         let (mut memory, mut data) = memory_and_data(&mut caller);
                         charge_syscall_gas!(data.kernel);
+                        if let Some(code) = check_capabilities(data, group, module, name) {
+                            update_gas_available(&mut caller)?;
+                            return Ok(code);
+                        }
 
                         let ctx = Context{kernel: &mut data.kernel, memory: &mut memory};
                         let out = syscall(ctx $(, $t)*).into();
+                        // Always take the exceeded limit, so it can't be blamed for a later failure.
+                        let limit_exceeded = data.kernel.limiter_mut().take_syscall_limit_exceeded();
 
                         let result = match out {
                             Ok(Ok(_)) => {
@@ -138,7 +183,7 @@ macro_rules! impl_bind_syscalls {
                             Ok(Err(err)) => {
                                 let code = err.1;
                                 log::trace!("syscall {}::{}: fail ({})", module, name, code as u32);
-                                data.last_error = Some(backtrace::Cause::from_syscall(module, name, err));
+                                data.last_error = Some(syscall_error_cause(limit_exceeded, module, name, err));
                                 Ok(code as u32)
                             },
                             Err(e) => Err(e.into()),
@@ -155,6 +200,10 @@ macro_rules! impl_bind_syscalls {
 
                         let (mut memory, mut data) = memory_and_data(&mut caller);
                         charge_syscall_gas!(data.kernel);
+                        if let Some(code) = check_capabilities(data, group, module, name) {
+                            update_gas_available(&mut caller)?;
+                            return Ok(code);
+                        }
 
                         // We need to check to make sure we can store the return value _before_ we do anything.
                         if (ret as u64) > (memory.len() as u64)
@@ -165,7 +214,10 @@ macro_rules! impl_bind_syscalls {
                         }
 
                         let ctx = Context{kernel: &mut data.kernel, memory: &mut memory};
-                        let result = match syscall(ctx $(, $t)*).into() {
+                        let out = syscall(ctx $(, $t)*).into();
+                        // Always take the exceeded limit, so it can't be blamed for a later failure.
+                        let limit_exceeded = data.kernel.limiter_mut().take_syscall_limit_exceeded();
+                        let result = match out {
                             Ok(Ok(value)) => {
                                 log::trace!("syscall {}::{}: ok", module, name);
                                 unsafe { *(memory.as_mut_ptr().offset(ret as isize) as *mut Ret::Value) = value };
@@ -175,7 +227,7 @@ macro_rules! impl_bind_syscalls {
                             Ok(Err(err)) => {
                                 let code = err.1;
                                 log::trace!("syscall {}::{}: fail ({})", module, name, code as u32);
-                                data.last_error = Some(backtrace::Cause::from_syscall(module, name, err));
+                                data.last_error = Some(syscall_error_cause(limit_exceeded, module, name, err));
                                 Ok(code as u32)
                             },
                             Err(e) => Err(e.into()),
@@ -199,3 +251,4 @@ impl_bind_syscalls!(A B C D);
 impl_bind_syscalls!(A B C D E);
 impl_bind_syscalls!(A B C D E F);
 impl_bind_syscalls!(A B C D E F G);
+impl_bind_syscalls!(A B C D E F G H);
@@ -7,6 +7,7 @@ use wasmtime::{AsContextMut, ExternType, Global, Linker, Memory, Module, Val};
 use crate::call_manager::backtrace;
 use crate::gas::Gas;
 use crate::kernel::ExecutionError;
+use crate::machine::capabilities::SyscallGroups;
 use crate::machine::limiter::ExecMemory;
 use crate::Kernel;
 
@@ -26,7 +27,8 @@ mod send;
 mod sself;
 mod vm;
 
-pub(self) use context::Context;
+pub use bind::{BindSyscall, IntoSyscallResult};
+pub use context::{Context, Memory};
 
 /// Invocation data attached to a wasm "store" and available to the syscall binding.
 pub struct InvocationData<K> {
@@ -50,6 +52,9 @@ pub struct InvocationData<K> {
 
     /// The invocation's imported "memory".
     pub memory: Memory,
+
+    /// The syscall groups the actor may call.
+    pub capabilities: SyscallGroups,
 }
 
 pub fn update_gas_available(
@@ -149,7 +154,6 @@ fn min_memory_bytes(module: &Module) -> crate::kernel::Result<usize> {
     }
 }
 
-use self::bind::BindSyscall;
 use self::error::Abort;
 
 // Binds the syscall handlers so they can handle invocations
@@ -159,6 +163,7 @@ pub fn bind_syscalls(
 ) -> anyhow::Result<()> {
     linker.bind("vm", "abort", vm::abort)?;
     linker.bind("vm", "message_context", vm::message_context)?;
+    linker.bind("vm", "context_flags", vm::context_flags)?;
 
     linker.bind("network", "base_fee", network::base_fee)?;
     linker.bind(
@@ -244,6 +249,7 @@ pub fn bind_syscalls(
 
     // Ok, this singled-out syscall should probably be in another category.
     linker.bind("send", "send", send::send)?;
+    linker.bind("send", "send_v2", send::send_v2)?;
 
     linker.bind("debug", "log", debug::log)?;
     linker.bind("debug", "enabled", debug::enabled)?;
@@ -4,8 +4,9 @@ use fvm_shared::error::ExitCode;
 use fvm_shared::sys;
 
 use super::Context;
+use crate::gas::Gas;
 use crate::kernel::{Result, SendResult};
-use crate::Kernel;
+use crate::{syscall_error, Kernel};
 
 /// Send a message to another actor. The result is placed as a CBOR-encoded
 /// receipt in the block registry, and can be retrieved by the returned BlockId.
@@ -17,13 +18,47 @@ pub fn send(
     params_id: u32,
     value_hi: u64,
     value_lo: u64,
+) -> Result<sys::out::send::Send> {
+    send_v2(
+        context,
+        recipient_off,
+        recipient_len,
+        method,
+        params_id,
+        value_hi,
+        value_lo,
+        u64::MAX,
+        0,
+    )
+}
+
+/// Like [`send`], but with a gas limit and [`SendFlags`](sys::SendFlags). A `gas_limit` of
+/// `u64::MAX` means the callee may use all the remaining gas.
+#[allow(clippy::too_many_arguments)]
+pub fn send_v2(
+    context: Context<'_, impl Kernel>,
+    recipient_off: u32,
+    recipient_len: u32,
+    method: u64,
+    params_id: u32,
+    value_hi: u64,
+    value_lo: u64,
+    gas_limit: u64,
+    flags: u64,
 ) -> Result<sys::out::send::Send> {
     let recipient: Address = context.memory.read_address(recipient_off, recipient_len)?;
     let value = TokenAmount::from_atto((value_hi as u128) << 64 | value_lo as u128);
+    let gas_limit =
+        (gas_limit != u64::MAX).then(|| Gas::new(gas_limit.min(i64::MAX as u64) as i64));
+    let flags = sys::SendFlags::from_bits(flags)
+        .ok_or_else(|| syscall_error!(IllegalArgument; "invalid send flags: {:#x}", flags))?;
     // An execution error here means that something went wrong in the FVM.
     // Actor errors are communicated in the receipt.
     Ok(
-        match context.kernel.send(&recipient, method, params_id, &value)? {
+        match context
+            .kernel
+            .send(&recipient, method, params_id, &value, gas_limit, flags)?
+        {
             SendResult::Return(id, stat) => sys::out::send::Send {
                 exit_code: ExitCode::OK.value(),
                 return_id: id,
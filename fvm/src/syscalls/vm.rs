@@ -1,6 +1,6 @@
 use fvm_shared::error::ExitCode;
 use fvm_shared::sys::out::vm::MessageContext;
-use fvm_shared::sys::SyscallSafe;
+use fvm_shared::sys::{ContextFlags, SyscallSafe};
 
 use super::error::Abort;
 use super::Context;
@@ -70,3 +70,11 @@ pub fn message_context(context: Context<'_, impl Kernel>) -> crate::kernel::Resu
         gas_limit: context.kernel.msg_gas_limit(),
     })
 }
+
+pub fn context_flags(context: Context<'_, impl Kernel>) -> crate::kernel::Result<ContextFlags> {
+    Ok(if context.kernel.read_only() {
+        ContextFlags::READ_ONLY
+    } else {
+        ContextFlags::empty()
+    })
+}
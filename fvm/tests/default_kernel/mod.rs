@@ -27,6 +27,7 @@ pub fn build_inspecting_test() -> anyhow::Result<(TestingKernel, Rc<RefCell<Test
         0,
         0,
         Zero::zero(),
+        false,
     );
     Ok((kern, test_data))
 }
@@ -46,6 +47,23 @@ pub fn build_inspecting_gas_test(
         0,
         0,
         Zero::zero(),
+        false,
+    );
+    Ok((kern, test_data))
+}
+
+/// build a kernel executing in read-only mode
+pub fn build_read_only_test() -> anyhow::Result<(TestingKernel, Rc<RefCell<TestData>>)> {
+    let (call_manager, test_data) = dummy::DummyCallManager::new_stub();
+
+    let kern = TestingKernel::new(
+        call_manager,
+        BlockRegistry::default(),
+        0,
+        0,
+        0,
+        Zero::zero(),
+        true,
     );
     Ok((kern, test_data))
 }
@@ -326,7 +326,7 @@ mod ipld {
 
         // assert gas
         {
-            let price_list = call_manager.machine.context().price_list;
+            let price_list = &call_manager.machine.context().price_list;
             let expected_create_price = price_list.on_block_create(block.len() as usize).total();
             let expected_read_price = price_list.on_block_read(block.len() as usize).total();
 
@@ -392,7 +392,7 @@ mod ipld {
 
         // assert gas
         {
-            let price_list = call_manager.machine.context().price_list;
+            let price_list = &call_manager.machine.context().price_list;
             let expected_create_price = price_list.on_block_create(block.len() as usize).total();
             let expected_stat_price = price_list.on_block_stat().total();
 
@@ -543,3 +543,34 @@ mod gas {
         Ok(())
     }
 }
+
+mod read_only {
+    use fvm::kernel::{ActorOps, EventOps, MessageOps, SelfOps};
+    use fvm_shared::address::Address;
+    use fvm_shared::event::{ActorEvent, Entry, Flags};
+    use multihash::MultihashDigest;
+
+    use super::*;
+
+    #[test]
+    fn mutations_fail() -> anyhow::Result<()> {
+        let (mut kern, _) = build_read_only_test()?;
+        assert!(kern.read_only());
+
+        let cid = cid::Cid::new_v1(fvm_ipld_encoding::DAG_CBOR, Code::Blake2b256.digest(b"foo"));
+        expect_syscall_err!(ReadOnly, kern.set_root(cid));
+        expect_syscall_err!(ReadOnly, kern.self_destruct(&Address::new_id(1)));
+        expect_syscall_err!(ReadOnly, kern.create_actor(cid, 1000, None));
+
+        let evt = ActorEvent::from(vec![Entry {
+            flags: Flags::FLAG_INDEXED_ALL,
+            key: "foo".to_owned(),
+            value: vec![0x01].into(),
+        }]);
+        expect_syscall_err!(ReadOnly, kern.emit_event(evt));
+
+        let (kern, _) = build_inspecting_test()?;
+        assert!(!kern.read_only());
+        Ok(())
+    }
+}
@@ -4,7 +4,9 @@ use std::rc::Rc;
 
 use anyhow::Context;
 use cid::Cid;
-use fvm::call_manager::{Backtrace, CallManager, FinishRet, InvocationResult};
+use fvm::call_manager::{
+    Backtrace, CallManager, FinishRet, InvocationResult, ResourceLimitExceeded,
+};
 use fvm::externs::{Consensus, Externs, Rand};
 use fvm::gas::{Gas, GasCharge, GasTracker};
 use fvm::machine::limiter::ExecMemory;
@@ -83,6 +85,20 @@ impl ExecMemory for DummyLimiter {
         self.curr_exec_memory_bytes
     }
 
+    fn peak_exec_memory_bytes(&self) -> usize {
+        self.curr_exec_memory_bytes
+    }
+
+    fn memory_limit_exceeded(&self) -> Option<ResourceLimitExceeded> {
+        None
+    }
+
+    fn set_syscall_limit_exceeded(&mut self, _limit: ResourceLimitExceeded) {}
+
+    fn take_syscall_limit_exceeded(&mut self) -> Option<ResourceLimitExceeded> {
+        None
+    }
+
     fn with_stack_frame<T, G, F, R>(t: &mut T, g: G, f: F) -> R
     where
         G: Fn(&mut T) -> &mut Self,
@@ -293,6 +309,8 @@ impl CallManager for DummyCallManager {
         _method: fvm_shared::MethodNum,
         _params: Option<kernel::Block>,
         _value: &fvm_shared::econ::TokenAmount,
+        _gas_limit: Option<Gas>,
+        _read_only: bool,
     ) -> kernel::Result<InvocationResult> {
         // Ok(InvocationResult::Return(None))
         todo!()
@@ -316,6 +334,7 @@ impl CallManager for DummyCallManager {
                 },
                 exec_trace: Vec::new(),
                 events: Vec::new(),
+                resource_usage: Default::default(),
             },
             self.machine,
         )
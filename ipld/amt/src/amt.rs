@@ -1,6 +1,8 @@
 // Copyright 2019-2022 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
+use std::sync::Arc;
+
 use anyhow::anyhow;
 use cid::multihash::Code;
 use cid::Cid;
@@ -11,11 +13,13 @@ use fvm_ipld_encoding::CborStore;
 use itertools::sorted;
 
 use super::ValueMut;
-use crate::node::{CollapsedNode, Link};
+use crate::builder::SortedBuilder;
+use crate::node::Link;
 use crate::root::version::{Version as AmtVersion, V0, V3};
 use crate::root::RootImpl;
 use crate::{
-    init_sized_vec, nodes_for_height, Error, Node, DEFAULT_BIT_WIDTH, MAX_HEIGHT, MAX_INDEX,
+    init_sized_vec, nodes_for_height, AmtNodeCache, Error, Node, DEFAULT_BIT_WIDTH, MAX_HEIGHT,
+    MAX_INDEX,
 };
 
 #[derive(Debug)]
@@ -23,6 +27,8 @@ use crate::{
 pub struct AmtImpl<V, BS, Ver> {
     root: RootImpl<V, Ver>,
     block_store: BS,
+    /// Optional cache of decoded nodes, shared between AMTs.
+    node_cache: Option<Arc<AmtNodeCache<V>>>,
 }
 
 /// Array Mapped Trie allows for the insertion and persistence of data, serializable to a CID.
@@ -56,6 +62,12 @@ impl<V: PartialEq, BS: Blockstore, Ver: PartialEq> PartialEq for AmtImpl<V, BS,
     }
 }
 
+impl<V, BS, Ver> AmtImpl<V, BS, Ver> {
+    fn node_cache(&self) -> Option<&AmtNodeCache<V>> {
+        self.node_cache.as_deref()
+    }
+}
+
 impl<V, BS, Ver> AmtImpl<V, BS, Ver>
 where
     V: DeserializeOwned + Serialize,
@@ -72,6 +84,7 @@ where
         Self {
             root: RootImpl::new_with_bit_width(bit_width),
             block_store,
+            node_cache: None,
         }
     }
 
@@ -91,7 +104,36 @@ where
             return Err(Error::MaxHeight(root.height, MAX_HEIGHT));
         }
 
-        Ok(Self { root, block_store })
+        Ok(Self {
+            root,
+            block_store,
+            node_cache: None,
+        })
+    }
+
+    /// Attaches a shared cache of decoded nodes to this AMT.
+    ///
+    /// Inner nodes loaded while reading the AMT are looked up in (and added to) the cache, so that
+    /// multiple loads of the same AMT don't decode the same nodes over and over again. The cache
+    /// must only be shared between AMTs with the same bit width.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fvm_ipld_amt::{Amt, AmtNodeCache};
+    /// use std::sync::Arc;
+    ///
+    /// let store = fvm_ipld_blockstore::MemoryBlockstore::default();
+    /// let cache = Arc::new(AmtNodeCache::new(1024));
+    ///
+    /// let cid = Amt::new_from_iter(&store, (0..100).map(|i| i.to_string())).unwrap();
+    ///
+    /// let amt: Amt<String, _> = Amt::load(&cid, &store).unwrap().with_node_cache(cache);
+    /// assert_eq!(amt.get(42).unwrap(), Some(&"42".to_string()));
+    /// ```
+    pub fn with_node_cache(mut self, cache: Arc<AmtNodeCache<V>>) -> Self {
+        self.node_cache = Some(cache);
+        self
     }
 
     /// Gets the height of the `Amt`.
@@ -106,11 +148,58 @@ where
 
     /// Generates an AMT with block store and array of cbor marshallable objects and returns Cid
     pub fn new_from_iter(block_store: BS, vals: impl IntoIterator<Item = V>) -> Result<Cid, Error> {
-        let mut t = Self::new(block_store);
+        Self::new_from_sorted(block_store, (0u64..).zip(vals))?.flush()
+    }
 
-        t.batch_set(vals)?;
+    /// Builds an AMT from `(index, value)` pairs with strictly increasing indices.
+    ///
+    /// Unlike repeated calls to [`set`](Self::set), the tree is built bottom-up in a single pass:
+    /// every node is written to the block store exactly once, as soon as it's complete. The root
+    /// node is kept in memory and written on [`flush`](Self::flush). The resulting AMT is
+    /// identical to one built by setting the same values one by one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fvm_ipld_amt::Amt;
+    ///
+    /// let store = fvm_ipld_blockstore::MemoryBlockstore::default();
+    ///
+    /// let vals = [(1, "a"), (8, "b"), (100, "c")].map(|(i, v)| (i, v.to_owned()));
+    /// let mut amt = Amt::new_from_sorted(&store, vals).unwrap();
+    /// assert_eq!(amt.count(), 3);
+    /// assert_eq!(amt.get(8).unwrap(), Some(&"b".to_owned()));
+    ///
+    /// let mut expected = Amt::new(&store);
+    /// expected.set(100, "c".to_owned()).unwrap();
+    /// expected.set(1, "a".to_owned()).unwrap();
+    /// expected.set(8, "b".to_owned()).unwrap();
+    /// assert_eq!(amt.flush().unwrap(), expected.flush().unwrap());
+    /// ```
+    pub fn new_from_sorted(
+        block_store: BS,
+        vals: impl IntoIterator<Item = (u64, V)>,
+    ) -> Result<Self, Error> {
+        Self::new_from_sorted_with_bit_width(block_store, DEFAULT_BIT_WIDTH, vals)
+    }
 
-        t.flush()
+    /// Builds an AMT with the given bit width from `(index, value)` pairs with strictly
+    /// increasing indices. See [`new_from_sorted`](Self::new_from_sorted).
+    pub fn new_from_sorted_with_bit_width(
+        block_store: BS,
+        bit_width: u32,
+        vals: impl IntoIterator<Item = (u64, V)>,
+    ) -> Result<Self, Error> {
+        let mut builder = SortedBuilder::new(&block_store, bit_width);
+        for (i, val) in vals {
+            builder.push(i, val)?;
+        }
+        let root = builder.finish()?;
+        Ok(Self {
+            root,
+            block_store,
+            node_cache: None,
+        })
     }
 
     /// Get value at index of AMT
@@ -123,9 +212,13 @@ where
             return Ok(None);
         }
 
-        self.root
-            .node
-            .get(&self.block_store, self.height(), self.bit_width(), i)
+        self.root.node.get(
+            &self.block_store,
+            self.node_cache(),
+            self.height(),
+            self.bit_width(),
+            i,
+        )
     }
 
     /// Set value at index
@@ -218,16 +311,7 @@ where
                             *std::mem::replace(node, Box::new(Node::empty()))
                         }
                         Some(Link::Cid { cid, cache }) => {
-                            let cache_node = std::mem::take(cache);
-                            if let Some(sn) = cache_node.into_inner() {
-                                *sn
-                            } else {
-                                // Only retrieve sub node if not found in cache
-                                self.block_store
-                                    .get_cbor::<CollapsedNode<V>>(cid)?
-                                    .ok_or_else(|| Error::CidNotFound(cid.to_string()))?
-                                    .expand(self.root.bit_width)?
-                            }
+                            *Node::take_link(cid, cache, &self.block_store, self.root.bit_width)?
                         }
                         _ => unreachable!("First index checked to be Some in `can_collapse`"),
                     },
@@ -272,6 +356,17 @@ where
         Ok(self.block_store.put_cbor(&self.root, Code::Blake2b256)?)
     }
 
+    /// Flush root and return Cid used as key in block store, hashing independent dirty nodes in
+    /// parallel.
+    ///
+    /// Produces the same CID as [`flush`](Self::flush). New blocks are written with
+    /// `put_many_keyed`, one batch per level of dirty nodes.
+    #[cfg(feature = "parallel")]
+    pub fn flush_parallel(&mut self) -> Result<Cid, Error> {
+        self.root.node.flush_parallel(&self.block_store)?;
+        Ok(self.block_store.put_cbor(&self.root, Code::Blake2b256)?)
+    }
+
     /// Iterates over each value in the Amt and runs a function on the values.
     ///
     /// The index in the amt is a `u64` and the value is the generic parameter `V` as defined
@@ -316,6 +411,7 @@ where
             .node
             .for_each_while(
                 &self.block_store,
+                self.node_cache(),
                 self.height(),
                 self.bit_width(),
                 0,
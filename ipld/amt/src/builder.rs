@@ -0,0 +1,135 @@
+// Copyright 2019-2022 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use anyhow::anyhow;
+use cid::multihash::Code;
+use cid::Cid;
+use fvm_ipld_blockstore::Blockstore;
+use fvm_ipld_encoding::CborStore;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::node::Link;
+use crate::root::RootImpl;
+use crate::{init_sized_vec, nodes_for_height, Error, Node, MAX_INDEX};
+
+/// Builds an AMT bottom-up from values with strictly increasing indices.
+///
+/// Only the nodes on the path to the last inserted index are kept in memory. Every other node is
+/// written to the blockstore as soon as it's complete, and every node is written exactly once.
+pub(crate) struct SortedBuilder<'a, V, BS> {
+    block_store: &'a BS,
+    bit_width: u32,
+    /// The leaf currently being filled, along with its position among the leaves.
+    leaf: Option<(u64, Vec<Option<V>>)>,
+    /// The link nodes currently being filled (one per height, starting at height 1), along with
+    /// their position among the nodes at that height.
+    links: Vec<Option<(u64, Vec<Option<Link<V>>>)>>,
+    last: Option<u64>,
+    count: u64,
+}
+
+impl<'a, V, BS> SortedBuilder<'a, V, BS>
+where
+    V: Serialize + DeserializeOwned,
+    BS: Blockstore,
+{
+    pub(crate) fn new(block_store: &'a BS, bit_width: u32) -> Self {
+        Self {
+            block_store,
+            bit_width,
+            leaf: None,
+            links: Vec::new(),
+            last: None,
+            count: 0,
+        }
+    }
+
+    /// Appends a value. The index must be greater than the index of the previous value.
+    pub(crate) fn push(&mut self, i: u64, val: V) -> Result<(), Error> {
+        if i > MAX_INDEX {
+            return Err(Error::OutOfRange(i));
+        }
+        if let Some(last) = self.last {
+            if i <= last {
+                return Err(
+                    anyhow!("indices must be strictly increasing: {} after {}", i, last).into(),
+                );
+            }
+        }
+
+        let leaf_no = i >> self.bit_width;
+        if matches!(self.leaf, Some((no, _)) if no != leaf_no) {
+            let (no, vals) = self.leaf.take().expect("checked above");
+            self.write_node(0, no, Node::Leaf { vals })?;
+        }
+        let bit_width = self.bit_width;
+        let (_, vals) = self
+            .leaf
+            .get_or_insert_with(|| (leaf_no, init_sized_vec(bit_width)));
+        vals[(i & mask(bit_width)) as usize] = Some(val);
+
+        self.last = Some(i);
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Writes out all remaining nodes (except for the root) and returns the root of the AMT.
+    pub(crate) fn finish<Ver>(mut self) -> Result<RootImpl<V, Ver>, Error> {
+        let mut root = RootImpl::new_with_bit_width(self.bit_width);
+        let last = match self.last {
+            Some(last) => last,
+            None => return Ok(root),
+        };
+        let height = (0..)
+            .find(|&h| last < nodes_for_height(self.bit_width, h + 1))
+            .expect("index is at most MAX_INDEX");
+
+        let (mut no, vals) = self.leaf.take().expect("at least one value was pushed");
+        let mut node = Node::Leaf { vals };
+        for h in 0..height {
+            // Write the node and move up to its (pending) parent.
+            self.write_node(h, no, node)?;
+            let (parent_no, links) = self.links[h as usize]
+                .take()
+                .expect("parent was linked above");
+            no = parent_no;
+            node = Node::Link { links };
+        }
+
+        root.height = height;
+        root.count = self.count;
+        root.node = node;
+        Ok(root)
+    }
+
+    /// Writes a complete node at the given height and links it into its parent.
+    fn write_node(&mut self, height: u32, no: u64, node: Node<V>) -> Result<(), Error> {
+        let cid = self.block_store.put_cbor(&node, Code::Blake2b256)?;
+        self.add_link(height + 1, no, cid)
+    }
+
+    /// Links the `child_no`th node at `height - 1` into its parent at `height`, writing out the
+    /// previously pending parent if this child belongs to a new one.
+    fn add_link(&mut self, height: u32, child_no: u64, cid: Cid) -> Result<(), Error> {
+        let parent_no = child_no >> self.bit_width;
+        let level = (height - 1) as usize;
+        if self.links.len() <= level {
+            self.links.resize_with(level + 1, || None);
+        }
+
+        if matches!(self.links[level], Some((no, _)) if no != parent_no) {
+            let (no, links) = self.links[level].take().expect("checked above");
+            self.write_node(height, no, Node::Link { links })?;
+        }
+        let bit_width = self.bit_width;
+        let (_, links) =
+            self.links[level].get_or_insert_with(|| (parent_no, init_sized_vec(bit_width)));
+        links[(child_no & mask(bit_width)) as usize] = Some(Link::from(cid));
+        Ok(())
+    }
+}
+
+fn mask(bit_width: u32) -> u64 {
+    (1 << bit_width) - 1
+}
@@ -7,16 +7,83 @@
 //! https://github.com/ipld/specs/blob/51fab05b4fe4930d3d851d50cc1e5f1a02092deb/data-structures/vector.md
 
 mod amt;
+mod builder;
 mod error;
 mod node;
 mod root;
 mod value_mut;
 
+use std::sync::Arc;
+
+use cid::Cid;
+use fvm_ipld_encoding::NodeCache;
+
 pub use self::amt::{Amt, Amtv0};
 pub use self::error::Error;
 pub(crate) use self::node::Node;
 pub use self::value_mut::ValueMut;
 
+/// A bounded cache of decoded AMT nodes that can be shared (behind an `Arc`, also across threads)
+/// between multiple AMTs with the same value type. See [`Amt::with_node_cache`].
+///
+/// The cache only holds nodes as decoded from the store: AMTs get their own copy of a cached node,
+/// so nodes loaded beneath it are owned by the AMT and never kept alive by the cache. Nodes are
+/// keyed by their CID and the bit width they were decoded with.
+#[derive(Debug)]
+pub struct AmtNodeCache<V> {
+    nodes: NodeCache<Node<V>, (Cid, u32)>,
+    copy: fn(&Node<V>) -> Node<V>,
+}
+
+impl<V> AmtNodeCache<V> {
+    /// Creates a new cache that holds at most `capacity` nodes. A capacity of zero disables the
+    /// cache.
+    pub fn new(capacity: usize) -> Self
+    where
+        V: Clone,
+    {
+        Self {
+            nodes: NodeCache::new(capacity),
+            copy: Node::clone_unloaded,
+        }
+    }
+
+    /// Returns the maximum number of nodes held by the cache.
+    pub fn capacity(&self) -> usize {
+        self.nodes.capacity()
+    }
+
+    /// Returns the number of nodes currently cached.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns true if no nodes are cached.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Removes all nodes from the cache.
+    pub fn clear(&self) {
+        self.nodes.clear()
+    }
+
+    /// Returns a copy of the cached node for the given CID and bit width, if any.
+    fn get(&self, cid: &Cid, bit_width: u32) -> Option<Node<V>> {
+        self.nodes
+            .get(&(*cid, bit_width))
+            .map(|node| (self.copy)(&node))
+    }
+
+    /// Caches a copy of a node freshly decoded from the store with the given bit width.
+    fn insert(&self, cid: Cid, bit_width: u32, node: &Node<V>) {
+        if self.nodes.capacity() > 0 {
+            self.nodes
+                .insert((cid, bit_width), Arc::new((self.copy)(node)));
+        }
+    }
+}
+
 const DEFAULT_BIT_WIDTH: u32 = 3;
 const MAX_HEIGHT: u32 = 64;
 
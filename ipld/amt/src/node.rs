@@ -2,18 +2,19 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 
 use std::convert::{TryFrom, TryInto};
+use std::sync::Arc;
 
 use anyhow::anyhow;
 use cid::multihash::Code;
 use cid::Cid;
 use fvm_ipld_blockstore::Blockstore;
 use fvm_ipld_encoding::{strict_bytes, BytesSer, CborStore};
-use once_cell::unsync::OnceCell;
+use once_cell::sync::OnceCell;
 use serde::de::{self, DeserializeOwned};
 use serde::{ser, Deserialize, Serialize};
 
 use super::ValueMut;
-use crate::{bmap_bytes, init_sized_vec, nodes_for_height, Error};
+use crate::{bmap_bytes, init_sized_vec, nodes_for_height, AmtNodeCache, Error};
 
 /// This represents a link to another Node
 #[derive(Debug)]
@@ -21,7 +22,7 @@ pub(super) enum Link<V> {
     /// Unchanged link to data with an atomic cache.
     Cid {
         cid: Cid,
-        cache: OnceCell<Box<Node<V>>>,
+        cache: OnceCell<Arc<Node<V>>>,
     },
     /// Modifications have been made to the link, requires flush to clear
     Dirty(Box<Node<V>>),
@@ -77,6 +78,37 @@ pub(super) enum Node<V> {
     Leaf { vals: Vec<Option<V>> },
 }
 
+/// A dirty node detached from the tree while flushing in parallel.
+#[cfg(feature = "parallel")]
+struct DetachedNode<V> {
+    /// The node, until it's flushed.
+    node: Option<Box<Node<V>>>,
+    /// The index of the parent among the detached nodes, if it's not the node being flushed.
+    parent: Option<usize>,
+    /// The index of the node among its parent's links.
+    index: usize,
+}
+
+impl<V: Clone> Node<V> {
+    /// Returns a copy of the node without any of the nodes loaded beneath it.
+    pub(crate) fn clone_unloaded(&self) -> Self {
+        match self {
+            Node::Link { links } => Node::Link {
+                links: links
+                    .iter()
+                    .map(|l| {
+                        l.as_ref().map(|l| match l {
+                            Link::Cid { cid, .. } => Link::from(*cid),
+                            Link::Dirty(node) => Link::Dirty(Box::new(node.clone_unloaded())),
+                        })
+                    })
+                    .collect(),
+            },
+            Node::Leaf { vals } => Node::Leaf { vals: vals.clone() },
+        }
+    }
+}
+
 impl<V> Serialize for Node<V>
 where
     V: Serialize,
@@ -195,7 +227,7 @@ where
                     let existing = std::mem::replace(n, Box::new(Node::empty()));
 
                     // Can keep the flushed node in link cache
-                    let cache = OnceCell::from(existing);
+                    let cache = OnceCell::from(Arc::from(existing));
                     *link = Link::Cid { cid, cache };
                 }
             }
@@ -204,6 +236,126 @@ where
         Ok(())
     }
 
+    /// Flushes dirty nodes like [`flush`](Self::flush), but hashes them in parallel.
+    ///
+    /// Dirty nodes are detached from the tree in a single pass, then flushed in waves by height,
+    /// starting with the ones without any dirty children. Each wave is serialized on the current
+    /// thread, hashed on the rayon thread pool, and written to the store with a single
+    /// `put_many_keyed` call. The resulting CIDs are identical to the ones produced by a
+    /// sequential flush. On error, the nodes that weren't flushed yet are put back as they were.
+    #[cfg(feature = "parallel")]
+    pub(super) fn flush_parallel<DB: Blockstore>(&mut self, bs: &DB) -> Result<(), Error> {
+        let mut detached = Vec::new();
+        let mut waves = Vec::new();
+        self.detach_dirty(None, &mut detached, &mut waves);
+        let result = self.flush_detached(bs, &mut detached, waves);
+
+        // Put back the nodes that weren't flushed, children before their parents.
+        while let Some(DetachedNode {
+            node,
+            parent,
+            index,
+        }) = detached.pop()
+        {
+            if let Some(node) = node {
+                self.attach(&mut detached, parent, index, Link::Dirty(node));
+            }
+        }
+        result
+    }
+
+    /// Moves all dirty descendants to `detached`, leaving empty placeholders behind, and adds
+    /// their indices to the wave matching their height. Returns the height of this node: zero if
+    /// it has no dirty children, and one more than its highest dirty child otherwise.
+    #[cfg(feature = "parallel")]
+    fn detach_dirty(
+        &mut self,
+        parent: Option<usize>,
+        detached: &mut Vec<DetachedNode<V>>,
+        waves: &mut Vec<Vec<usize>>,
+    ) -> usize {
+        let mut height = 0;
+        if let Node::Link { links } = self {
+            for (index, link) in links.iter_mut().enumerate() {
+                if let Some(Link::Dirty(n)) = link {
+                    let mut node = std::mem::replace(n, Box::new(Node::empty()));
+                    let id = detached.len();
+                    detached.push(DetachedNode {
+                        node: None,
+                        parent,
+                        index,
+                    });
+                    let node_height = node.detach_dirty(Some(id), detached, waves);
+                    detached[id].node = Some(node);
+                    if waves.len() <= node_height {
+                        waves.resize_with(node_height + 1, Vec::new);
+                    }
+                    waves[node_height].push(id);
+                    height = height.max(node_height + 1);
+                }
+            }
+        }
+        height
+    }
+
+    /// Flushes the detached nodes wave by wave, linking each flushed node from its parent.
+    #[cfg(feature = "parallel")]
+    fn flush_detached<DB: Blockstore>(
+        &mut self,
+        bs: &DB,
+        detached: &mut [DetachedNode<V>],
+        waves: Vec<Vec<usize>>,
+    ) -> Result<(), Error> {
+        use cid::multihash::MultihashDigest;
+        use fvm_ipld_encoding::DAG_CBOR;
+        use rayon::prelude::*;
+
+        for wave in waves {
+            let blocks = wave
+                .iter()
+                .map(|&id| {
+                    let node = detached[id].node.as_ref().expect("node not flushed yet");
+                    fvm_ipld_encoding::to_vec(node)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let cids: Vec<Cid> = blocks
+                .par_iter()
+                .map(|b| Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(b)))
+                .collect();
+            bs.put_many_keyed(cids.iter().copied().zip(&blocks))?;
+
+            for (id, cid) in wave.into_iter().zip(cids) {
+                let node = detached[id].node.take().expect("node not flushed yet");
+                let (parent, index) = (detached[id].parent, detached[id].index);
+                // Can keep the flushed node in link cache
+                let cache = OnceCell::from(Arc::from(node));
+                self.attach(detached, parent, index, Link::Cid { cid, cache });
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets the link at `index` in the given detached parent, or in this node if there is none.
+    #[cfg(feature = "parallel")]
+    fn attach(
+        &mut self,
+        detached: &mut [DetachedNode<V>],
+        parent: Option<usize>,
+        index: usize,
+        link: Link<V>,
+    ) {
+        let parent = match parent {
+            Some(parent) => detached[parent]
+                .node
+                .as_deref_mut()
+                .expect("parents are flushed after their children"),
+            None => self,
+        };
+        if let Node::Link { links } = parent {
+            links[index] = Some(link);
+        }
+    }
+
     /// Returns true if there is only a link in the first index of the values.
     /// This node can be collapsed into the parent node.
     pub(super) fn can_collapse(&self) -> bool {
@@ -232,6 +384,7 @@ where
     pub(super) fn get<DB: Blockstore>(
         &self,
         bs: &DB,
+        node_cache: Option<&AmtNodeCache<V>>,
         height: u32,
         bit_width: u32,
         i: u64,
@@ -244,15 +397,11 @@ where
                     .unwrap();
                 match links.get(sub_i).and_then(|v| v.as_ref()) {
                     Some(Link::Cid { cid, cache }) => {
-                        let cached_node = cache.get_or_try_init(|| {
-                            bs.get_cbor::<CollapsedNode<V>>(cid)?
-                                .ok_or_else(|| Error::CidNotFound(cid.to_string()))?
-                                .expand(bit_width)
-                                .map(Box::new)
-                        })?;
+                        let cached_node = Self::load_link(cid, cache, bs, node_cache, bit_width)?;
 
                         cached_node.get(
                             bs,
+                            node_cache,
                             height - 1,
                             bit_width,
                             i % nodes_for_height(bit_width, height),
@@ -260,6 +409,7 @@ where
                     }
                     Some(Link::Dirty(n)) => n.get(
                         bs,
+                        node_cache,
                         height - 1,
                         bit_width,
                         i % nodes_for_height(bit_width, height),
@@ -291,18 +441,7 @@ where
         if let Node::Link { links } = self {
             links[idx] = match &mut links[idx] {
                 Some(Link::Cid { cid, cache }) => {
-                    let cache_node = std::mem::take(cache);
-                    let sub_node = if let Some(sn) = cache_node.into_inner() {
-                        sn
-                    } else {
-                        // Only retrieve sub node if not found in cache
-                        bs.get_cbor::<CollapsedNode<V>>(cid)?
-                            .ok_or_else(|| Error::CidNotFound(cid.to_string()))?
-                            .expand(bit_width)
-                            .map(Box::new)?
-                    };
-
-                    Some(Link::Dirty(sub_node))
+                    Some(Link::Dirty(Self::take_link(cid, cache, bs, bit_width)?))
                 }
                 None => {
                     let node = match height {
@@ -381,13 +520,7 @@ where
                     }
                     Some(Link::Cid { cid, cache }) => {
                         // Take cache, will be replaced if no nodes deleted
-                        cache.get_or_try_init(|| {
-                            bs.get_cbor::<CollapsedNode<V>>(cid)?
-                                .ok_or_else(|| Error::CidNotFound(cid.to_string()))?
-                                .expand(bit_width)
-                                .map(Box::new)
-                        })?;
-                        let sub_node = cache.get_mut().expect("filled line above");
+                        let mut sub_node = Self::take_link(cid, cache, bs, bit_width)?;
                         let deleted = sub_node.delete(
                             bs,
                             height - 1,
@@ -396,9 +529,9 @@ where
                         )?;
                         if deleted.is_none() {
                             // Index to be deleted was not found
+                            *cache = OnceCell::from(Arc::from(sub_node));
                             return Ok(None);
                         };
-                        let sub_node = std::mem::replace(sub_node, Box::new(Node::empty()));
 
                         if sub_node.is_empty() {
                             // Sub node is empty, clear link.
@@ -419,9 +552,53 @@ where
         }
     }
 
+    /// Returns the node behind a link for reading. The link's own cache is consulted first, then
+    /// the shared node cache (if any) and finally the store. Nodes loaded from the store are
+    /// added to the shared node cache. Either way, the link's cache gets its own copy of the node.
+    fn load_link<'a, DB: Blockstore>(
+        cid: &Cid,
+        cache: &'a OnceCell<Arc<Self>>,
+        bs: &DB,
+        node_cache: Option<&AmtNodeCache<V>>,
+        bit_width: u32,
+    ) -> Result<&'a Self, Error> {
+        let node = cache.get_or_try_init(|| {
+            if let Some(node) = node_cache.and_then(|c| c.get(cid, bit_width)) {
+                return Ok(Arc::new(node));
+            }
+            let node = Self::load(cid, bs, bit_width)?;
+            if let Some(c) = node_cache {
+                c.insert(*cid, bit_width, &node);
+            }
+            Ok::<_, Error>(Arc::new(node))
+        })?;
+        Ok(node)
+    }
+
+    /// Takes ownership of the node behind a link for mutation, loading it from the store if it
+    /// isn't loaded yet.
+    pub(super) fn take_link<DB: Blockstore>(
+        cid: &Cid,
+        cache: &mut OnceCell<Arc<Self>>,
+        bs: &DB,
+        bit_width: u32,
+    ) -> Result<Box<Self>, Error> {
+        if let Some(node) = cache.take().and_then(|n| Arc::try_unwrap(n).ok()) {
+            return Ok(Box::new(node));
+        }
+        Self::load(cid, bs, bit_width).map(Box::new)
+    }
+
+    fn load<DB: Blockstore>(cid: &Cid, bs: &DB, bit_width: u32) -> Result<Self, Error> {
+        bs.get_cbor::<CollapsedNode<V>>(cid)?
+            .ok_or_else(|| Error::CidNotFound(cid.to_string()))?
+            .expand(bit_width)
+    }
+
     pub(super) fn for_each_while<S, F>(
         &self,
         bs: &S,
+        node_cache: Option<&AmtNodeCache<V>>,
         height: u32,
         bit_width: u32,
         offset: u64,
@@ -449,17 +626,20 @@ where
                         let offs = offset + (i * nodes_for_height(bit_width, height));
                         let keep_going = match l {
                             Link::Dirty(sub) => {
-                                sub.for_each_while(bs, height - 1, bit_width, offs, f)?
+                                sub.for_each_while(bs, node_cache, height - 1, bit_width, offs, f)?
                             }
                             Link::Cid { cid, cache } => {
-                                let cached_node = cache.get_or_try_init(|| {
-                                    bs.get_cbor::<CollapsedNode<V>>(cid)?
-                                        .ok_or_else(|| Error::CidNotFound(cid.to_string()))?
-                                        .expand(bit_width)
-                                        .map(Box::new)
-                                })?;
-
-                                cached_node.for_each_while(bs, height - 1, bit_width, offs, f)?
+                                let cached_node =
+                                    Self::load_link(cid, cache, bs, node_cache, bit_width)?;
+
+                                cached_node.for_each_while(
+                                    bs,
+                                    node_cache,
+                                    height - 1,
+                                    bit_width,
+                                    offs,
+                                    f,
+                                )?
                             }
                         };
 
@@ -516,23 +696,16 @@ where
                                 sub.for_each_while_mut(bs, height - 1, bit_width, offs, f)?
                             }
                             Link::Cid { cid, cache } => {
-                                cache.get_or_try_init(|| {
-                                    bs.get_cbor::<CollapsedNode<V>>(cid)?
-                                        .ok_or_else(|| Error::CidNotFound(cid.to_string()))?
-                                        .expand(bit_width)
-                                        .map(Box::new)
-                                })?;
-                                let node = cache.get_mut().expect("cache filled on line above");
+                                let mut node = Self::take_link(cid, cache, bs, bit_width)?;
 
                                 let (keep_going, did_mutate_node) =
                                     node.for_each_while_mut(bs, height - 1, bit_width, offs, f)?;
 
                                 if did_mutate_node {
                                     // Cache was mutated, switch it to dirty
-                                    *link = Link::Dirty(std::mem::replace(
-                                        node,
-                                        Box::new(Node::empty()),
-                                    ));
+                                    *link = Link::Dirty(node);
+                                } else {
+                                    *cache = OnceCell::from(Arc::from(node));
                                 }
 
                                 (keep_going, did_mutate_node)
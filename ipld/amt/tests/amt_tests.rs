@@ -2,8 +2,9 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 
 use std::fmt::Debug;
+use std::sync::Arc;
 
-use fvm_ipld_amt::{Amt, Amtv0, Error, MAX_INDEX};
+use fvm_ipld_amt::{Amt, AmtNodeCache, Amtv0, Error, MAX_INDEX};
 use fvm_ipld_blockstore::tracking::{BSStats, TrackingBlockstore};
 use fvm_ipld_blockstore::{Blockstore, MemoryBlockstore};
 use fvm_ipld_encoding::de::DeserializeOwned;
@@ -169,6 +170,58 @@ fn bulk_insert() {
     assert_eq!(*db.stats.borrow(), BSStats {r: 717, w: 717, br: 94379, bw: 94379});
 }
 
+#[test]
+fn bulk_sorted_construction() {
+    let mem = MemoryBlockstore::default();
+    let db = TrackingBlockstore::new(&mem);
+
+    let iterations: u64 = 5000;
+    let mut a =
+        Amt::new_from_sorted(&db, (0..iterations).map(|i| (i, tbytes(b"foo foo bar")))).unwrap();
+    assert_eq!(a.count(), iterations);
+
+    // Same root as setting the values one by one, and every node is written exactly once.
+    let c = a.flush().unwrap();
+    assert_eq!(
+        c.to_string().as_str(),
+        "bafy2bzacecfquuqzqzlox25aynodzw2qhxijdzfvno6tibyes3kb6nd3f7uxa"
+    );
+    #[rustfmt::skip]
+    assert_eq!(*db.stats.borrow(), BSStats {r: 0, w: 717, br: 0, bw: 94379});
+}
+
+#[test]
+fn bulk_sorted_sparse() {
+    let db = MemoryBlockstore::default();
+    let indices: Vec<u64> = (0..300u64).map(|i| i * i * 37 + (i % 7)).collect();
+
+    for bit_width in [1, 3, 5, 8] {
+        let mut expected = Amt::new_with_bit_width(&db, bit_width);
+        for &i in indices.iter().rev() {
+            expected.set(i, i).unwrap();
+        }
+        let mut a =
+            Amt::new_from_sorted_with_bit_width(&db, bit_width, indices.iter().map(|&i| (i, i)))
+                .unwrap();
+        assert_eq!(a.height(), expected.height());
+        assert_eq!(a.flush().unwrap(), expected.flush().unwrap());
+    }
+
+    // Single value at the largest index.
+    let mut expected = Amt::new(&db);
+    expected.set(MAX_INDEX, 1u8).unwrap();
+    let mut a = Amt::new_from_sorted(&db, [(MAX_INDEX, 1u8)]).unwrap();
+    assert_eq!(a.flush().unwrap(), expected.flush().unwrap());
+
+    // Empty input.
+    let mut a = Amt::<u8, _>::new_from_sorted(&db, []).unwrap();
+    assert_eq!(a.flush().unwrap(), Amt::<u8, _>::new(&db).flush().unwrap());
+
+    // Unsorted and duplicate indices are rejected.
+    assert!(Amt::new_from_sorted(&db, [(2, 1u8), (1, 1u8)]).is_err());
+    assert!(Amt::new_from_sorted(&db, [(1, 1u8), (1, 1u8)]).is_err());
+}
+
 #[test]
 fn flush_read() {
     let mem = MemoryBlockstore::default();
@@ -430,6 +483,39 @@ fn for_each_mutate() {
     assert_eq!(*db.stats.borrow(), BSStats {r: 12, w: 12, br: 573, bw: 573});
 }
 
+#[test]
+#[cfg(feature = "parallel")]
+fn parallel_flush() {
+    let db = MemoryBlockstore::default();
+    let par_db = MemoryBlockstore::default();
+
+    for bit_width in [1, 3, 5, 8] {
+        let mut expected = Amt::new_with_bit_width(&db, bit_width);
+        let mut a = Amt::new_with_bit_width(&par_db, bit_width);
+        for i in (0..2000u64).map(|i| i * 13) {
+            expected.set(i, i).unwrap();
+            a.set(i, i).unwrap();
+        }
+        assert_eq!(a.flush_parallel().unwrap(), expected.flush().unwrap());
+
+        // Only part of the tree is dirty the second time around.
+        for i in (0..2000u64).step_by(11).map(|i| i * 13) {
+            expected.delete(i).unwrap();
+            a.delete(i).unwrap();
+        }
+        expected.set(1 << 20, 1).unwrap();
+        a.set(1 << 20, 1).unwrap();
+        let c = a.flush_parallel().unwrap();
+        assert_eq!(c, expected.flush().unwrap());
+
+        // Every block reachable from the new root was written.
+        let reloaded: Amt<u64, _> = Amt::load(&c, &par_db).unwrap();
+        assert_eq!(reloaded.count(), expected.count());
+        assert_eq!(reloaded.get(13).unwrap(), Some(&13));
+        assert_eq!(reloaded.get(0).unwrap(), None);
+    }
+}
+
 #[test]
 fn delete_bug_test() {
     let mem = MemoryBlockstore::default();
@@ -453,6 +539,44 @@ fn delete_bug_test() {
     assert_eq!(*db.stats.borrow(), BSStats {r:0, w:2, br:0, bw:18});
 }
 
+#[test]
+fn shared_node_cache() {
+    let mem = MemoryBlockstore::default();
+    let db = TrackingBlockstore::new(&mem);
+
+    let c = Amt::new_from_iter(&db, (0..1000u64).map(|i| i * 2)).unwrap();
+    let cache = Arc::new(AmtNodeCache::new(1024));
+    let sum = |a: &Amt<u64, _>| {
+        let mut sum = 0;
+        a.for_each(|_, v| {
+            sum += v;
+            Ok(())
+        })
+        .unwrap();
+        sum
+    };
+
+    // The first load populates the cache.
+    let before = *db.stats.borrow();
+    let a: Amt<u64, _> = Amt::load(&c, &db).unwrap().with_node_cache(cache.clone());
+    assert_eq!(sum(&a), 999 * 1000);
+    assert_eq!(db.stats.borrow().r - before.r, cache.len() + 1);
+
+    // The second load only needs to read the root.
+    let before = *db.stats.borrow();
+    let mut a: Amt<u64, _> = Amt::load(&c, &db).unwrap().with_node_cache(cache.clone());
+    assert_eq!(sum(&a), 999 * 1000);
+    assert_eq!(a.get(500).unwrap(), Some(&1000));
+    assert_eq!(db.stats.borrow().r - before.r, 1);
+
+    // Mutating through a cached AMT must not affect the cached nodes.
+    a.set(500, 0).unwrap();
+    assert_ne!(a.flush().unwrap(), c);
+
+    let a: Amt<u64, _> = Amt::load(&c, &db).unwrap().with_node_cache(cache);
+    assert_eq!(a.get(500).unwrap(), Some(&1000));
+}
+
 fn tbytes(bz: &[u8]) -> BytesDe {
     BytesDe(bz.to_vec())
 }
@@ -8,6 +8,7 @@
 pub mod iter;
 mod ops;
 mod range;
+mod rank;
 mod rleplus;
 mod unvalidated;
 
@@ -16,9 +17,10 @@ use std::ops::Range;
 
 use iter::{ranges_from_bits, RangeIterator};
 pub(crate) use range::RangeSize;
-pub use rleplus::Error;
+pub use rank::RankIndex;
+pub use rleplus::{encode_ranges, DecodedRanges, Error};
 use thiserror::Error;
-pub use unvalidated::{UnvalidatedBitField, Validate};
+pub use unvalidated::{UnvalidatedBitField, Validate, ValidationLimits};
 
 /// MaxEncodedSize is the maximum encoded size of a bitfield. When expanded into
 /// a slice of runs, a bitfield of this size should not exceed 2MiB of memory.
@@ -320,6 +322,40 @@ impl BitField {
         self.ranges().map(|range| range.size()).sum()
     }
 
+    /// Returns the number of set bits strictly below `index`.
+    ///
+    /// This walks the bit field's ranges; use [`rank_index`](Self::rank_index) when issuing many
+    /// queries against the same bit field.
+    pub fn rank(&self, index: u64) -> u64 {
+        self.ranges()
+            .take_while(|range| range.start < index)
+            .map(|range| (range.start..index.min(range.end)).size())
+            .sum()
+    }
+
+    /// Returns the index of the `k`th set bit (starting at 0), or `None` if the bit field
+    /// contains fewer than `k + 1` set bits.
+    pub fn select(&self, k: u64) -> Option<u64> {
+        self.ranges().skip_bits(k).next().map(|range| range.start)
+    }
+
+    /// Returns the number of set bits in the given range.
+    pub fn count_in_range(&self, range: Range<u64>) -> u64 {
+        if range.start >= range.end {
+            return 0;
+        }
+        self.ranges()
+            .intersection(iter::Ranges::new(std::iter::once(range)))
+            .map(|range| range.size())
+            .sum()
+    }
+
+    /// Returns an index of the bit field's set bits that answers rank and select queries in
+    /// logarithmic time.
+    pub fn rank_index(&self) -> RankIndex {
+        RankIndex::new(self.ranges())
+    }
+
     /// Returns a new bit field containing the bits in `self` that remain
     /// after "cutting" out the bits in `other`, and shifting remaining
     /// bits to the left if necessary. For example:
@@ -0,0 +1,74 @@
+// Copyright 2019-2022 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use std::ops::Range;
+
+use crate::iter::RangeIterator;
+use crate::RangeSize;
+
+/// A snapshot of a bit field's ranges along with the number of set bits before each range,
+/// answering rank and select queries in logarithmic time.
+///
+/// Build one with [`BitField::rank_index`](crate::BitField::rank_index) when issuing many queries
+/// against the same bit field. Later changes to the bit field are not reflected in the index.
+#[derive(Debug, Default, Clone)]
+pub struct RankIndex {
+    ranges: Vec<Range<u64>>,
+    /// The number of set bits before the range with the same position in `ranges`.
+    prefix_counts: Vec<u64>,
+    len: u64,
+}
+
+impl RankIndex {
+    /// Creates a new index over the ranges of a `RangeIterator`.
+    pub fn new(iter: impl RangeIterator) -> Self {
+        let mut index = Self::default();
+        for range in iter {
+            index.prefix_counts.push(index.len);
+            index.len += range.size();
+            index.ranges.push(range);
+        }
+        index
+    }
+
+    /// Returns the number of set bits.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns `true` if no bits are set.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of set bits strictly below `index`.
+    pub fn rank(&self, index: u64) -> u64 {
+        // the number of ranges starting before `index`
+        match self.ranges.partition_point(|r| r.start < index) {
+            0 => 0,
+            i => {
+                let range = &self.ranges[i - 1];
+                self.prefix_counts[i - 1] + (range.start..index.min(range.end)).size()
+            }
+        }
+    }
+
+    /// Returns the index of the `k`th set bit (starting at 0), or `None` if fewer than `k + 1`
+    /// bits are set.
+    pub fn select(&self, k: u64) -> Option<u64> {
+        if k >= self.len {
+            return None;
+        }
+        // the last range with fewer than `k + 1` set bits before it
+        let i = self.prefix_counts.partition_point(|&count| count <= k) - 1;
+        Some(self.ranges[i].start + (k - self.prefix_counts[i]))
+    }
+
+    /// Returns the number of set bits in the given range.
+    pub fn count_in_range(&self, range: Range<u64>) -> u64 {
+        if range.start >= range.end {
+            return 0;
+        }
+        self.rank(range.end) - self.rank(range.start)
+    }
+}
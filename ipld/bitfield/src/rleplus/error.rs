@@ -10,4 +10,10 @@ pub enum Error {
     RLEOverflow,
     #[error("invalid varint")]
     InvalidVarint,
+    #[error("bitfield has more than {0} set bits")]
+    TooManySetBits(u64),
+    #[error("bitfield has set bits above index {0}")]
+    IndexTooLarge(u64),
+    #[error("bitfield has more than {0} runs of set bits")]
+    TooManyRuns(u64),
 }
@@ -62,6 +62,7 @@
 //!
 
 mod error;
+mod ranges;
 mod reader;
 mod writer;
 
@@ -71,12 +72,13 @@ use std::borrow::Cow;
 use arbitrary::{size_hint, Arbitrary, Unstructured};
 pub use error::Error;
 use fvm_ipld_encoding::strict_bytes;
+pub use ranges::{encode_ranges, DecodedRanges};
 pub use reader::BitReader;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 pub use writer::BitWriter;
 
 use super::BitField;
-use crate::{RangeSize, MAX_ENCODED_SIZE};
+use crate::{ValidationLimits, MAX_ENCODED_SIZE};
 
 impl Serialize for BitField {
     fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
@@ -155,40 +157,24 @@ impl<'a> Arbitrary<'a> for BitField {
 impl BitField {
     /// Decodes RLE+ encoded bytes into a bit field.
     pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
-        let mut reader = BitReader::new(bytes)?;
-
-        let version = reader.read(2);
-        if version != 0 {
-            return Err(Error::UnsupportedVersion);
-        }
-
-        let mut next_value = reader.read(1) == 1;
+        let mut iter = DecodedRanges::new_unchecked(bytes)?;
         let mut ranges = Vec::new();
-        let mut index = 0u64;
-        let mut total_len: u64 = 0;
-
-        while let Some(len) = reader.read_len()? {
-            let (new_total_len, ovf) = total_len.overflowing_add(len);
-            if ovf {
-                return Err(Error::RLEOverflow);
-            }
-            total_len = new_total_len;
-            let start = index;
-            index += len;
-            let end = index;
-
-            if next_value {
-                ranges.push(start..end);
-            }
-
-            next_value = !next_value;
+        while let Some(range) = iter.try_next()? {
+            ranges.push(range);
         }
 
-        // next_value equal true means we just read a run of zeros
-        // which means that there is a trailing run of zeros
-        if next_value {
-            return Err(Error::NotMinimal);
-        }
+        Ok(Self {
+            ranges,
+            ..Default::default()
+        })
+    }
+
+    /// Decodes RLE+ encoded bytes into a bit field, failing as soon as the decoded bits exceed
+    /// the given limits.
+    pub fn from_bytes_with_limits(bytes: &[u8], limits: &ValidationLimits) -> Result<Self, Error> {
+        let mut iter = DecodedRanges::new_unchecked(bytes)?;
+        let mut ranges = Vec::new();
+        limits.check_ranges(|| iter.try_next(), |range| ranges.push(range))?;
 
         Ok(Self {
             ranges,
@@ -198,35 +184,7 @@ impl BitField {
 
     /// Turns a bit field into its RLE+ encoded form.
     pub fn to_bytes(&self) -> Vec<u8> {
-        let mut iter = self.ranges();
-
-        let first_range = match iter.next() {
-            Some(range) => range,
-            None => return Default::default(),
-        };
-
-        let mut writer = BitWriter::new();
-        writer.write(0, 2); // version 00
-
-        if first_range.start == 0 {
-            writer.write(1, 1); // the first bit is a 1
-        } else {
-            writer.write(0, 1); // the first bit is a 0
-            writer.write_len(first_range.start); // the number of leading 0s
-        }
-
-        writer.write_len(first_range.size());
-        let mut index = first_range.end;
-
-        // for each range of 1s we first encode the number of 0s that came prior
-        // before encoding the number of 1s
-        for range in iter {
-            writer.write_len(range.start - index); // zeros
-            writer.write_len(range.size()); // ones
-            index = range.end;
-        }
-
-        writer.finish()
+        encode_ranges(self.ranges())
     }
 }
 
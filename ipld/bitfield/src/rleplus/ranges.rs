@@ -0,0 +1,202 @@
+// Copyright 2019-2022 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use std::ops::Range;
+
+use super::{BitReader, BitWriter, Error};
+use crate::iter::RangeIterator;
+use crate::RangeSize;
+
+/// A `RangeIterator` that lazily decodes the ranges of an RLE+ encoded bit field, straight from
+/// the encoded bytes.
+///
+/// The input is validated when the iterator is created, without allocating. Combined with
+/// [`encode_ranges`], this allows bit fields to be combined without ever decoding them into
+/// memory:
+///
+/// ```
+/// use fvm_ipld_bitfield::iter::RangeIterator;
+/// use fvm_ipld_bitfield::{bitfield, encode_ranges, DecodedRanges};
+///
+/// let a = bitfield![1, 1, 0, 0, 1].to_bytes();
+/// let b = bitfield![0, 1, 1, 0, 0].to_bytes();
+///
+/// let union = encode_ranges(DecodedRanges::new(&a)?.union(DecodedRanges::new(&b)?));
+/// assert_eq!(union, bitfield![1, 1, 1, 0, 1].to_bytes());
+/// # Ok::<(), fvm_ipld_bitfield::Error>(())
+/// ```
+#[derive(Clone)]
+pub struct DecodedRanges<'a> {
+    reader: BitReader<'a>,
+    /// The index of the first bit of the next run.
+    index: u64,
+    /// The value of the bits in the next run.
+    next_value: bool,
+}
+
+impl<'a> DecodedRanges<'a> {
+    /// Creates a new iterator over the ranges of an RLE+ encoded bit field, returning an error
+    /// if the encoding is invalid.
+    pub fn new(bytes: &'a [u8]) -> Result<Self, Error> {
+        let iter = Self::new_unchecked(bytes)?;
+        let mut check = iter.clone();
+        while check.try_next()?.is_some() {}
+        Ok(iter)
+    }
+
+    /// Creates a new iterator after checking only the header. Errors in the rest of the encoding
+    /// are reported by [`try_next`](Self::try_next).
+    pub(crate) fn new_unchecked(bytes: &'a [u8]) -> Result<Self, Error> {
+        let mut reader = BitReader::new(bytes)?;
+
+        let version = reader.read(2);
+        if version != 0 {
+            return Err(Error::UnsupportedVersion);
+        }
+
+        let next_value = reader.read(1) == 1;
+        Ok(Self {
+            reader,
+            index: 0,
+            next_value,
+        })
+    }
+
+    /// Decodes the next range of 1s.
+    pub(crate) fn try_next(&mut self) -> Result<Option<Range<u64>>, Error> {
+        while let Some(len) = self.reader.read_len()? {
+            let start = self.index;
+            self.index = start.checked_add(len).ok_or(Error::RLEOverflow)?;
+
+            let value = self.next_value;
+            self.next_value = !value;
+            if value {
+                return Ok(Some(start..self.index));
+            }
+        }
+
+        // next_value equal true means we just read a run of zeros
+        // which means that there is a trailing run of zeros
+        if self.next_value {
+            return Err(Error::NotMinimal);
+        }
+        Ok(None)
+    }
+}
+
+impl Iterator for DecodedRanges<'_> {
+    type Item = Range<u64>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.try_next()
+            .expect("encoding was validated when the iterator was created")
+    }
+}
+
+impl RangeIterator for DecodedRanges<'_> {}
+
+/// Encodes the ranges of a `RangeIterator` into RLE+, without collecting them first.
+pub fn encode_ranges(mut iter: impl RangeIterator) -> Vec<u8> {
+    let first_range = match iter.next() {
+        Some(range) => range,
+        None => return Default::default(),
+    };
+
+    let mut writer = BitWriter::new();
+    writer.write(0, 2); // version 00
+
+    if first_range.start == 0 {
+        writer.write(1, 1); // the first bit is a 1
+    } else {
+        writer.write(0, 1); // the first bit is a 0
+        writer.write_len(first_range.start); // the number of leading 0s
+    }
+
+    writer.write_len(first_range.size());
+    let mut index = first_range.end;
+
+    // for each range of 1s we first encode the number of 0s that came prior
+    // before encoding the number of 1s
+    for range in iter {
+        writer.write_len(range.start - index); // zeros
+        writer.write_len(range.size()); // ones
+        index = range.end;
+    }
+
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    use super::{encode_ranges, DecodedRanges};
+    use crate::iter::RangeIterator;
+    use crate::{BitField, Error};
+
+    fn random_bitfield(rng: &mut XorShiftRng) -> BitField {
+        let len: u64 = rng.gen_range(0..1000);
+        let density: f64 = rng.gen();
+        BitField::try_from_bits((0..len).filter(|_| rng.gen_bool(density))).unwrap()
+    }
+
+    #[test]
+    fn decode_matches_from_bytes() {
+        let mut rng = XorShiftRng::seed_from_u64(2);
+
+        for _ in 0..1000 {
+            let bf = random_bitfield(&mut rng);
+            let bytes = bf.to_bytes();
+            let ranges: Vec<_> = DecodedRanges::new(&bytes).unwrap().collect();
+            assert_eq!(ranges, bf.ranges().collect::<Vec<_>>());
+            assert_eq!(encode_ranges(DecodedRanges::new(&bytes).unwrap()), bytes);
+        }
+    }
+
+    fn decode(bytes: &[u8]) -> DecodedRanges<'_> {
+        DecodedRanges::new(bytes).unwrap()
+    }
+
+    #[test]
+    fn encoded_set_algebra() {
+        let mut rng = XorShiftRng::seed_from_u64(3);
+
+        for _ in 0..1000 {
+            let (a, b) = (random_bitfield(&mut rng), random_bitfield(&mut rng));
+            let (a_bytes, b_bytes) = (a.to_bytes(), b.to_bytes());
+
+            assert_eq!(
+                encode_ranges(decode(&a_bytes).union(decode(&b_bytes))),
+                (&a | &b).to_bytes()
+            );
+            assert_eq!(
+                encode_ranges(decode(&a_bytes).intersection(decode(&b_bytes))),
+                (&a & &b).to_bytes()
+            );
+            assert_eq!(
+                encode_ranges(decode(&a_bytes).difference(decode(&b_bytes))),
+                (&a - &b).to_bytes()
+            );
+            assert_eq!(
+                encode_ranges(decode(&a_bytes).cut(decode(&b_bytes))),
+                a.cut(&b).to_bytes()
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_encoding() {
+        // version 00, starts with 1, a run of one 1 and a trailing run of one 0
+        assert_eq!(
+            DecodedRanges::new(&[0b0001_1100]).err(),
+            Some(Error::NotMinimal)
+        );
+        // version 01
+        assert_eq!(
+            DecodedRanges::new(&[0b0000_0001]).err(),
+            Some(Error::UnsupportedVersion)
+        );
+        assert_eq!(DecodedRanges::new(&[]).unwrap().next(), None);
+    }
+}
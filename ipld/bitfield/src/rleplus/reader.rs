@@ -10,6 +10,7 @@ const VARINT_MAX_BYTES: usize = 10;
 ///
 /// It works by always storing at least the next 8 bits in `bits`, which lets us conveniently
 /// and efficiently read bits that cross a byte boundary.
+#[derive(Clone)]
 pub struct BitReader<'a> {
     /// The bytes that have not been read from yet.
     bytes: &'a [u8],
@@ -2,12 +2,13 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 
 use std::convert::TryFrom;
+use std::ops::Range;
 
 use fvm_ipld_encoding::strict_bytes;
 use serde::{Deserialize, Deserializer, Serialize};
 
 use super::BitField;
-use crate::{Error, MAX_ENCODED_SIZE};
+use crate::{Error, RangeSize, MAX_ENCODED_SIZE};
 
 /// A trait for types that can produce a `&BitField` (or fail to do so).
 /// Generalizes over `&BitField` and `&mut UnvalidatedBitField`.
@@ -52,6 +53,75 @@ impl UnvalidatedBitField {
             Self::Unvalidated(_) => unreachable!(),
         }
     }
+
+    /// Validates the RLE+ encoding of the bit field and checks it against the given limits,
+    /// returning a unique reference to the decoded bit field.
+    ///
+    /// The limits are enforced while decoding, so an oversized bit field is rejected before it's
+    /// fully expanded into memory. An already validated bit field is checked against the limits
+    /// as well.
+    pub fn validate_mut_with(&mut self, limits: &ValidationLimits) -> Result<&mut BitField, Error> {
+        match self {
+            Self::Unvalidated(bytes) => {
+                *self = Self::Validated(BitField::from_bytes_with_limits(bytes, limits)?);
+            }
+            Self::Validated(bf) => {
+                let mut ranges = bf.ranges();
+                limits.check_ranges(|| Ok(ranges.next()), |_| {})?;
+            }
+        }
+
+        match self {
+            Self::Validated(bf) => Ok(bf),
+            Self::Unvalidated(_) => unreachable!(),
+        }
+    }
+}
+
+/// Limits checked while validating an [`UnvalidatedBitField`], on top of the RLE+ encoding
+/// itself. Unset limits aren't enforced.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ValidationLimits {
+    /// The maximum number of set bits.
+    pub max_set_bits: Option<u64>,
+    /// The maximum index of a set bit.
+    pub max_index: Option<u64>,
+    /// The maximum number of runs of consecutive set bits.
+    pub max_runs: Option<u64>,
+}
+
+impl ValidationLimits {
+    /// Checks a sequence of ranges against the limits, passing each range to `f` once it has
+    /// been checked. Stops at the first range exceeding a limit.
+    pub(crate) fn check_ranges(
+        &self,
+        mut next: impl FnMut() -> Result<Option<Range<u64>>, Error>,
+        mut f: impl FnMut(Range<u64>),
+    ) -> Result<(), Error> {
+        let mut runs = 0u64;
+        let mut set_bits = 0u64;
+        while let Some(range) = next()? {
+            runs += 1;
+            if let Some(max) = self.max_runs {
+                if runs > max {
+                    return Err(Error::TooManyRuns(max));
+                }
+            }
+            if let Some(max) = self.max_index {
+                if range.end - 1 > max {
+                    return Err(Error::IndexTooLarge(max));
+                }
+            }
+            set_bits += range.size();
+            if let Some(max) = self.max_set_bits {
+                if set_bits > max {
+                    return Err(Error::TooManySetBits(max));
+                }
+            }
+            f(range);
+        }
+        Ok(())
+    }
 }
 #[cfg(feature = "enable-arbitrary")]
 use arbitrary::{Arbitrary, Unstructured};
@@ -3,7 +3,7 @@
 
 use std::collections::HashSet;
 
-use fvm_ipld_bitfield::{bitfield, BitField, UnvalidatedBitField};
+use fvm_ipld_bitfield::{bitfield, BitField, Error, UnvalidatedBitField, ValidationLimits};
 use rand::{Rng, SeedableRng};
 use rand_xorshift::XorShiftRng;
 
@@ -375,3 +375,93 @@ fn bitfield_custom() {
         }
     }
 }
+
+#[test]
+fn rank_select() {
+    let mut rng = XorShiftRng::seed_from_u64(4);
+
+    for seed in 0..50 {
+        let mut bf = BitField::try_from_bits(random_indices(rng.gen_range(0..500), seed)).unwrap();
+        // Exercise the buffered set/unset bits as well.
+        for _ in 0..20 {
+            let bit = rng.gen_range(0..600);
+            if rng.gen::<bool>() {
+                bf.set(bit);
+            } else {
+                bf.unset(bit);
+            }
+        }
+
+        let bits: Vec<u64> = bf.iter().collect();
+        let index = bf.rank_index();
+        assert_eq!(index.len(), bits.len() as u64);
+
+        for i in 0..610 {
+            let rank = bits.iter().filter(|&&b| b < i).count() as u64;
+            assert_eq!(bf.rank(i), rank);
+            assert_eq!(index.rank(i), rank);
+
+            let select = bits.get(i as usize).copied();
+            assert_eq!(bf.select(i), select);
+            assert_eq!(index.select(i), select);
+        }
+
+        for _ in 0..100 {
+            let start = rng.gen_range(0..610);
+            let end = rng.gen_range(0..610);
+            let count = bits.iter().filter(|&&b| start <= b && b < end).count() as u64;
+            assert_eq!(bf.count_in_range(start..end), count);
+            assert_eq!(index.count_in_range(start..end), count);
+        }
+    }
+
+    let empty = BitField::new();
+    assert_eq!(empty.rank(u64::MAX), 0);
+    assert_eq!(empty.select(0), None);
+    assert_eq!(empty.rank_index().select(0), None);
+}
+
+#[test]
+fn validation_limits() {
+    let bf = bitfield![0, 1, 1, 0, 1, 0, 0, 1, 1, 1];
+    let bytes = bf.to_bytes();
+
+    let check = |limits: ValidationLimits| {
+        let unvalidated = UnvalidatedBitField::Unvalidated(bytes.clone());
+        let validated = UnvalidatedBitField::Validated(bf.clone());
+        [unvalidated, validated].map(|mut ubf| ubf.validate_mut_with(&limits).map(|bf| bf.len()))
+    };
+
+    assert_eq!(check(ValidationLimits::default()), [Ok(6), Ok(6)]);
+    let limits = ValidationLimits {
+        max_set_bits: Some(6),
+        max_index: Some(9),
+        max_runs: Some(3),
+    };
+    assert_eq!(check(limits), [Ok(6), Ok(6)]);
+
+    let err = Err(Error::TooManySetBits(5));
+    assert_eq!(
+        check(ValidationLimits {
+            max_set_bits: Some(5),
+            ..limits
+        }),
+        [err.clone(), err]
+    );
+    let err = Err(Error::IndexTooLarge(8));
+    assert_eq!(
+        check(ValidationLimits {
+            max_index: Some(8),
+            ..limits
+        }),
+        [err.clone(), err]
+    );
+    let err = Err(Error::TooManyRuns(2));
+    assert_eq!(
+        check(ValidationLimits {
+            max_runs: Some(2),
+            ..limits
+        }),
+        [err.clone(), err]
+    );
+}
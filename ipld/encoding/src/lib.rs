@@ -5,6 +5,7 @@ mod bytes;
 mod cbor;
 mod cbor_store;
 mod errors;
+mod node_cache;
 mod vec;
 use std::io;
 
@@ -14,6 +15,7 @@ pub use self::bytes::*;
 pub use self::cbor::*;
 pub use self::cbor_store::CborStore;
 pub use self::errors::*;
+pub use self::node_cache::NodeCache;
 pub use self::vec::*;
 
 // TODO: these really don't work all that well in a shared context like this as anyone importing
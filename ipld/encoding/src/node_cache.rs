@@ -0,0 +1,155 @@
+// Copyright 2019-2022 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use cid::Cid;
+
+/// A bounded cache of decoded IPLD nodes, keyed by CID. Nodes whose decoding depends on more than
+/// their data can be keyed by the CID and those parameters instead.
+///
+/// The cache is meant to be shared (behind an `Arc`) between multiple loads of the same data
+/// structure so that hot inner nodes are only decoded once. Nodes are immutable once cached; data
+/// structures must decode a fresh copy when they need to mutate a node. Cached nodes also must not
+/// hold on to the nodes loaded beneath them (e.g. in link caches), or those would escape the bound.
+///
+/// Eviction follows the CLOCK (second chance) policy: when full, the oldest entry is evicted
+/// unless it has been read since it was last considered, in which case it's moved to the back of
+/// the queue.
+#[derive(Debug)]
+pub struct NodeCache<T, K = Cid> {
+    capacity: usize,
+    inner: Mutex<NodeCacheInner<T, K>>,
+}
+
+#[derive(Debug)]
+struct NodeCacheInner<T, K> {
+    entries: HashMap<K, CacheEntry<T>>,
+    queue: VecDeque<K>,
+}
+
+#[derive(Debug)]
+struct CacheEntry<T> {
+    node: Arc<T>,
+    referenced: bool,
+}
+
+impl<T, K: Eq + Hash + Clone> NodeCache<T, K> {
+    /// Creates a new cache that holds at most `capacity` nodes. A capacity of zero disables the
+    /// cache.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            inner: Mutex::new(NodeCacheInner {
+                entries: HashMap::new(),
+                queue: VecDeque::new(),
+            }),
+        }
+    }
+
+    /// Returns the maximum number of nodes held by the cache.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of nodes currently cached.
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    /// Returns true if no nodes are cached.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the cached node for the given key, if any.
+    pub fn get(&self, key: &K) -> Option<Arc<T>> {
+        let mut inner = self.lock();
+        let entry = inner.entries.get_mut(key)?;
+        entry.referenced = true;
+        Some(entry.node.clone())
+    }
+
+    /// Inserts a decoded node, evicting another node if the cache is full.
+    pub fn insert(&self, key: K, node: Arc<T>) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut inner = self.lock();
+        if inner.entries.contains_key(&key) {
+            return;
+        }
+        while inner.entries.len() >= self.capacity {
+            let oldest = inner.queue.pop_front().expect("queue tracks every entry");
+            let entry = inner
+                .entries
+                .get_mut(&oldest)
+                .expect("queue tracks every entry");
+            if std::mem::replace(&mut entry.referenced, false) {
+                inner.queue.push_back(oldest);
+            } else {
+                inner.entries.remove(&oldest);
+            }
+        }
+        inner.queue.push_back(key.clone());
+        inner.entries.insert(
+            key,
+            CacheEntry {
+                node,
+                referenced: false,
+            },
+        );
+    }
+
+    /// Removes all nodes from the cache.
+    pub fn clear(&self) {
+        let mut inner = self.lock();
+        inner.entries.clear();
+        inner.queue.clear();
+    }
+
+    fn lock(&self) -> MutexGuard<'_, NodeCacheInner<T, K>> {
+        // The cache is never left in an inconsistent state, so a poisoned lock is still usable.
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use multihash::{Code, MultihashDigest};
+
+    use super::*;
+    use crate::DAG_CBOR;
+
+    fn cid(i: u8) -> Cid {
+        Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(&[i]))
+    }
+
+    #[test]
+    fn bounded_second_chance() {
+        let cache = NodeCache::new(2);
+        cache.insert(cid(0), Arc::new(0u8));
+        cache.insert(cid(1), Arc::new(1u8));
+        assert_eq!(cache.len(), 2);
+
+        // Touch the oldest entry so it survives the next eviction.
+        assert_eq!(cache.get(&cid(0)).as_deref(), Some(&0));
+        cache.insert(cid(2), Arc::new(2u8));
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&cid(0)).as_deref(), Some(&0));
+        assert_eq!(cache.get(&cid(1)), None);
+        assert_eq!(cache.get(&cid(2)).as_deref(), Some(&2));
+
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn zero_capacity() {
+        let cache = NodeCache::new(0);
+        cache.insert(cid(0), Arc::new(0u8));
+        assert!(cache.is_empty());
+    }
+}
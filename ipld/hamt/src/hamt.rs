@@ -2,7 +2,10 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 
 use std::borrow::Borrow;
+use std::cmp::Ordering;
 use std::marker::PhantomData;
+use std::ops::{Bound, RangeBounds};
+use std::sync::Arc;
 
 use cid::Cid;
 use forest_hash_utils::BytesKey;
@@ -13,7 +16,7 @@ use serde::de::DeserializeOwned;
 use serde::{Serialize, Serializer};
 
 use crate::node::Node;
-use crate::{Error, Hash, HashAlgorithm, Sha256, DEFAULT_BIT_WIDTH};
+use crate::{Error, HamtNodeCache, Hash, HashAlgorithm, Sha256, DEFAULT_BIT_WIDTH};
 
 /// Implementation of the HAMT data structure for IPLD.
 ///
@@ -39,6 +42,8 @@ pub struct Hamt<BS, V, K = BytesKey, H = Sha256> {
     hash: PhantomData<H>,
     /// Remember the last flushed CID until it changes.
     flushed_cid: Option<Cid>,
+    /// Optional cache of decoded nodes, shared between HAMTs.
+    node_cache: Option<Arc<HamtNodeCache<K, V, H>>>,
 }
 
 impl<BS, V, K, H> Serialize for Hamt<BS, V, K, H>
@@ -61,6 +66,12 @@ impl<K: PartialEq, V: PartialEq, S: Blockstore, H: HashAlgorithm> PartialEq for
     }
 }
 
+impl<BS, V, K, H> Hamt<BS, V, K, H> {
+    fn node_cache(&self) -> Option<&HamtNodeCache<K, V, H>> {
+        self.node_cache.as_deref()
+    }
+}
+
 impl<BS, V, K, H> Hamt<BS, V, K, H>
 where
     K: Hash + Eq + PartialOrd + Serialize + DeserializeOwned,
@@ -80,9 +91,77 @@ where
             bit_width,
             hash: Default::default(),
             flushed_cid: None,
+            node_cache: None,
         }
     }
 
+    /// Builds a HAMT from an iterator of key-value pairs, in any order.
+    ///
+    /// Unlike repeated calls to [`set`](Self::set), the entries are sorted by hash up-front and
+    /// the tree is built bottom-up in a single pass: every inner node is written to the store
+    /// exactly once, and no intermediate nodes are created. The root node is kept in memory and
+    /// written on [`flush`](Self::flush). The resulting HAMT is identical to one built by setting
+    /// the same entries one by one.
+    ///
+    /// Returns an error if a key appears more than once.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fvm_ipld_hamt::Hamt;
+    ///
+    /// let store = fvm_ipld_blockstore::MemoryBlockstore::default();
+    ///
+    /// let entries = (0..100).map(|i| (i, i * 2));
+    /// let mut map: Hamt<_, _, usize> = Hamt::new_from_iter(&store, entries).unwrap();
+    /// assert_eq!(map.get(&10).unwrap(), Some(&20));
+    ///
+    /// let mut expected: Hamt<_, _, usize> = Hamt::new(&store);
+    /// for i in 0..100 {
+    ///     expected.set(i, i * 2).unwrap();
+    /// }
+    /// assert_eq!(map.flush().unwrap(), expected.flush().unwrap());
+    /// ```
+    pub fn new_from_iter(
+        store: BS,
+        entries: impl IntoIterator<Item = (K, V)>,
+    ) -> Result<Self, Error> {
+        Self::new_from_iter_with_bit_width(store, DEFAULT_BIT_WIDTH, entries)
+    }
+
+    /// Builds a HAMT with a bit width from an iterator of key-value pairs. See
+    /// [`new_from_iter`](Self::new_from_iter).
+    pub fn new_from_iter_with_bit_width(
+        store: BS,
+        bit_width: u32,
+        entries: impl IntoIterator<Item = (K, V)>,
+    ) -> Result<Self, Error> {
+        let mut entries: Vec<_> = entries
+            .into_iter()
+            .map(|(k, v)| (H::hash(&k), KeyValuePair::new(k, v)))
+            .collect();
+        entries.sort_unstable_by(|(ha, a), (hb, b)| {
+            ha.cmp(hb)
+                .then_with(|| a.key().partial_cmp(b.key()).unwrap_or(Ordering::Equal))
+        });
+        if entries
+            .windows(2)
+            .any(|w| w[0].0 == w[1].0 && w[0].1.key() == w[1].1.key())
+        {
+            return Err("duplicate key in HAMT construction".into());
+        }
+
+        let root = Node::from_sorted(entries, 0, bit_width, &store)?;
+        Ok(Self {
+            root,
+            store,
+            bit_width,
+            hash: Default::default(),
+            flushed_cid: None,
+            node_cache: None,
+        })
+    }
+
     /// Lazily instantiate a hamt from this root Cid.
     pub fn load(cid: &Cid, store: BS) -> Result<Self, Error> {
         Self::load_with_bit_width(cid, store, DEFAULT_BIT_WIDTH)
@@ -97,11 +176,38 @@ where
                 bit_width,
                 hash: Default::default(),
                 flushed_cid: Some(*cid),
+                node_cache: None,
             }),
             None => Err(Error::CidNotFound(cid.to_string())),
         }
     }
 
+    /// Attaches a shared cache of decoded nodes to this HAMT.
+    ///
+    /// Inner nodes loaded while reading the HAMT are looked up in (and added to) the cache, so
+    /// that multiple loads of the same HAMT don't decode the same nodes over and over again.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fvm_ipld_hamt::{Hamt, HamtNodeCache};
+    /// use std::sync::Arc;
+    ///
+    /// let store = fvm_ipld_blockstore::MemoryBlockstore::default();
+    /// let cache = Arc::new(HamtNodeCache::new(1024));
+    ///
+    /// let mut map: Hamt<_, _, usize> = Hamt::new(&store);
+    /// map.set(1, "a".to_string()).unwrap();
+    /// let cid = map.flush().unwrap();
+    ///
+    /// let map: Hamt<_, String, usize> = Hamt::load(&cid, &store).unwrap().with_node_cache(cache);
+    /// assert_eq!(map.get(&1).unwrap(), Some(&"a".to_string()));
+    /// ```
+    pub fn with_node_cache(mut self, cache: Arc<HamtNodeCache<K, V, H>>) -> Self {
+        self.node_cache = Some(cache);
+        self
+    }
+
     /// Sets the root based on the Cid of the root node using the Hamt store
     pub fn set_root(&mut self, cid: &Cid) -> Result<(), Error> {
         match self.store.get_cbor(cid)? {
@@ -225,7 +331,10 @@ where
         Q: Hash + Eq,
         V: DeserializeOwned,
     {
-        match self.root.get(k, self.store.borrow(), self.bit_width)? {
+        match self
+            .root
+            .get(k, self.store.borrow(), self.node_cache(), self.bit_width)?
+        {
             Some(v) => Ok(Some(v)),
             None => Ok(None),
         }
@@ -258,7 +367,7 @@ where
     {
         Ok(self
             .root
-            .get(k, self.store.borrow(), self.bit_width)?
+            .get(k, self.store.borrow(), self.node_cache(), self.bit_width)?
             .is_some())
     }
 
@@ -309,6 +418,21 @@ where
         Ok(cid)
     }
 
+    /// Flush root and return Cid for hamt, hashing independent dirty nodes in parallel.
+    ///
+    /// Produces the same CID as [`flush`](Self::flush). New blocks are written with
+    /// `put_many_keyed`, one batch per level of dirty nodes.
+    #[cfg(feature = "parallel")]
+    pub fn flush_parallel(&mut self) -> Result<Cid, Error> {
+        if let Some(cid) = self.flushed_cid {
+            return Ok(cid);
+        }
+        self.root.flush_parallel(self.store.borrow())?;
+        let cid = self.store.put_cbor(&self.root, Code::Blake2b256)?;
+        self.flushed_cid = Some(cid);
+        Ok(cid)
+    }
+
     /// Returns true if the HAMT has no entries
     pub fn is_empty(&self) -> bool {
         self.root.is_empty()
@@ -342,7 +466,8 @@ where
         V: DeserializeOwned,
         F: FnMut(&K, &V) -> anyhow::Result<()>,
     {
-        self.root.for_each(self.store.borrow(), &mut f)
+        self.root
+            .for_each(self.store.borrow(), self.node_cache(), &mut f)
     }
 
     /// Consumes this HAMT and returns the Blockstore it owns.
@@ -350,3 +475,64 @@ where
         self.store
     }
 }
+
+impl<BS, V, K, H> Hamt<BS, V, K, H>
+where
+    K: Hash + Eq + PartialOrd + Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+    BS: Blockstore,
+    H: OrderPreserving,
+{
+    /// Iterates, in key order, over the entries whose keys fall into `range`, and runs a
+    /// function on them. Subtrees outside of the range are never loaded.
+    ///
+    /// This is only available for HAMTs using an [order-preserving](OrderPreserving) hash
+    /// algorithm. Prefix queries can be expressed as a range from the prefix (inclusive) to the
+    /// next prefix (exclusive).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fvm_ipld_hamt::{Hamt, KeyPrefix};
+    ///
+    /// let store = fvm_ipld_blockstore::MemoryBlockstore::default();
+    ///
+    /// let mut map: Hamt<_, _, u64, KeyPrefix> = Hamt::new(store);
+    /// for i in (0..100).rev() {
+    ///     map.set(i, i * 2).unwrap();
+    /// }
+    ///
+    /// let mut keys = Vec::new();
+    /// map.for_each_range(10..15, |k, _: &u64| {
+    ///     keys.push(*k);
+    ///     Ok(())
+    /// }).unwrap();
+    /// assert_eq!(keys, vec![10, 11, 12, 13, 14]);
+    /// ```
+    pub fn for_each_range<Q: ?Sized, R, F>(&self, range: R, mut f: F) -> Result<(), Error>
+    where
+        K: Borrow<Q>,
+        Q: Hash + PartialOrd,
+        R: RangeBounds<Q>,
+        F: FnMut(&K, &V) -> anyhow::Result<()>,
+    {
+        let hash_bound = |b: Bound<&Q>| match b {
+            Bound::Included(q) | Bound::Excluded(q) => Some(H::hash(q)),
+            Bound::Unbounded => None,
+        };
+        let lower = hash_bound(range.start_bound());
+        let upper = hash_bound(range.end_bound());
+
+        self.root.for_each_range(
+            self.store.borrow(),
+            self.node_cache(),
+            self.bit_width,
+            (
+                lower.as_ref().map(HashBits::new),
+                upper.as_ref().map(HashBits::new),
+            ),
+            &range,
+            &mut f,
+        )
+    }
+}
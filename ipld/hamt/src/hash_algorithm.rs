@@ -14,6 +14,11 @@ pub trait HashAlgorithm {
         X: Hash;
 }
 
+/// Marker trait for hash algorithms that preserve the ordering of keys, i.e. for any two keys
+/// `a <= b`, `hash(a) <= hash(b)` (byte-wise). HAMTs using such an algorithm store their entries
+/// in key order and support range queries.
+pub trait OrderPreserving: HashAlgorithm {}
+
 /// Type is needed because the Sha256 hasher does not implement `std::hash::Hasher`
 #[derive(Default)]
 struct Sha2HasherWrapper(Sha256Hasher);
@@ -80,3 +85,121 @@ impl HashAlgorithm for Identity {
         ident_hasher.bz
     }
 }
+
+/// Hasher that writes the serialized key into the hash buffer, truncating after 32 bytes. Integers
+/// are written big-endian (with the sign bit flipped for signed integers) so that the byte-wise
+/// ordering of the output matches the numeric ordering of the input.
+#[derive(Default)]
+struct KeyPrefixHasher {
+    bz: HashedKey,
+    len: usize,
+}
+
+impl Hasher for KeyPrefixHasher {
+    fn finish(&self) -> u64 {
+        // u64 hash not used in hamt
+        0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        let n = std::cmp::min(bytes.len(), self.bz.len() - self.len);
+        self.bz[self.len..self.len + n].copy_from_slice(&bytes[..n]);
+        self.len += n;
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_be_bytes())
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_be_bytes())
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_be_bytes())
+    }
+
+    fn write_u128(&mut self, i: u128) {
+        self.write(&i.to_be_bytes())
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        // Always use 64 bits so the hash doesn't depend on the platform.
+        self.write_u64(i as u64)
+    }
+
+    fn write_i8(&mut self, i: i8) {
+        self.write_u8((i as u8) ^ (1 << 7))
+    }
+
+    fn write_i16(&mut self, i: i16) {
+        self.write_u16((i as u16) ^ (1 << 15))
+    }
+
+    fn write_i32(&mut self, i: i32) {
+        self.write_u32((i as u32) ^ (1 << 31))
+    }
+
+    fn write_i64(&mut self, i: i64) {
+        self.write_u64((i as u64) ^ (1 << 63))
+    }
+
+    fn write_i128(&mut self, i: i128) {
+        self.write_u128((i as u128) ^ (1 << 127))
+    }
+
+    fn write_isize(&mut self, i: isize) {
+        self.write_i64(i as i64)
+    }
+}
+
+/// Order-preserving hashing algorithm for the Hamt. The "hash" is the first 32 bytes of the key,
+/// zero padded, with integers encoded big-endian. A HAMT using this algorithm keeps its entries
+/// sorted by key and supports [range queries](crate::Hamt::for_each_range).
+///
+/// Keys must differ within their first 32 bytes (more precisely, at most 3 keys may share the
+/// same 32 byte prefix), and the ordering of `K` must match the byte-wise ordering of its encoding.
+/// This holds for byte strings, strings, integers, and tuples of fixed-size integers. Note that,
+/// unlike [`Sha256`], this algorithm doesn't protect against adversarially chosen keys that
+/// produce a deep, unbalanced tree.
+#[derive(Debug)]
+pub enum KeyPrefix {}
+
+impl HashAlgorithm for KeyPrefix {
+    fn hash<X: ?Sized>(key: &X) -> HashedKey
+    where
+        X: Hash,
+    {
+        let mut hasher = KeyPrefixHasher::default();
+        key.hash(&mut hasher);
+        hasher.bz
+    }
+}
+
+impl OrderPreserving for KeyPrefix {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_prefix_preserves_order() {
+        let ints = [i64::MIN, -300, -1, 0, 1, 255, 256, i64::MAX];
+        for w in ints.windows(2) {
+            assert!(KeyPrefix::hash(&w[0]) < KeyPrefix::hash(&w[1]));
+        }
+
+        let uints = [0u64, 1, 255, 256, 1 << 40, u64::MAX];
+        for w in uints.windows(2) {
+            assert!(KeyPrefix::hash(&w[0]) < KeyPrefix::hash(&w[1]));
+        }
+
+        let strings = ["", "a", "a\u{1}", "ab", "b", "ba"];
+        for w in strings.windows(2) {
+            assert!(KeyPrefix::hash(w[0]) < KeyPrefix::hash(w[1]));
+        }
+
+        // Tuples of fixed-width integers.
+        assert!(KeyPrefix::hash(&(1u32, u64::MAX)) < KeyPrefix::hash(&(2u32, 0u64)));
+    }
+}
@@ -18,7 +18,11 @@ mod hash_bits;
 mod node;
 mod pointer;
 
+use std::sync::Arc;
+
+use cid::Cid;
 pub use forest_hash_utils::{BytesKey, Hash};
+use fvm_ipld_encoding::NodeCache;
 use serde::{Deserialize, Serialize};
 
 pub use self::error::Error;
@@ -26,6 +30,65 @@ pub use self::hamt::Hamt;
 pub use self::hash::*;
 pub use self::hash_algorithm::*;
 
+/// A bounded cache of decoded HAMT nodes that can be shared (behind an `Arc`, also across threads)
+/// between multiple HAMTs with the same key, value, and hash types. See
+/// [`Hamt::with_node_cache`].
+///
+/// The cache only holds nodes as decoded from the store: HAMTs get their own copy of a cached
+/// node, so nodes loaded beneath it are owned by the HAMT and never kept alive by the cache.
+#[derive(Debug)]
+pub struct HamtNodeCache<K, V, H = Sha256> {
+    nodes: NodeCache<node::Node<K, V, H>>,
+    copy: fn(&node::Node<K, V, H>) -> node::Node<K, V, H>,
+}
+
+impl<K, V, H> HamtNodeCache<K, V, H> {
+    /// Creates a new cache that holds at most `capacity` nodes. A capacity of zero disables the
+    /// cache.
+    pub fn new(capacity: usize) -> Self
+    where
+        K: Clone,
+        V: Clone,
+    {
+        Self {
+            nodes: NodeCache::new(capacity),
+            copy: node::Node::clone_unloaded,
+        }
+    }
+
+    /// Returns the maximum number of nodes held by the cache.
+    pub fn capacity(&self) -> usize {
+        self.nodes.capacity()
+    }
+
+    /// Returns the number of nodes currently cached.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns true if no nodes are cached.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Removes all nodes from the cache.
+    pub fn clear(&self) {
+        self.nodes.clear()
+    }
+
+    /// Returns a copy of the cached node for the given CID, if any.
+    fn get(&self, cid: &Cid) -> Option<node::Node<K, V, H>> {
+        self.nodes.get(cid).map(|node| (self.copy)(&node))
+    }
+
+    /// Caches a copy of a node freshly decoded from the store.
+    fn insert(&self, cid: Cid, node: &node::Node<K, V, H>) {
+        if self.nodes.capacity() > 0 {
+            self.nodes.insert(cid, Arc::new((self.copy)(node)));
+        }
+    }
+}
+
 const MAX_ARRAY_WIDTH: usize = 3;
 
 /// Default bit width for indexing a hash at each depth level
@@ -33,7 +96,7 @@ const DEFAULT_BIT_WIDTH: u32 = 8;
 
 type HashedKey = [u8; 32];
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 struct KeyValuePair<K, V>(K, V);
 
 impl<K, V> KeyValuePair<K, V> {
@@ -2,20 +2,24 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 
 use std::borrow::Borrow;
+use std::cmp::Ordering;
 use std::fmt::Debug;
 use std::marker::PhantomData;
+use std::ops::RangeBounds;
+use std::sync::Arc;
 
+use cid::Cid;
 use fvm_ipld_blockstore::Blockstore;
 use fvm_ipld_encoding::CborStore;
 use multihash::Code;
-use once_cell::unsync::OnceCell;
+use once_cell::sync::OnceCell;
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use super::bitfield::Bitfield;
 use super::hash_bits::HashBits;
 use super::pointer::Pointer;
-use super::{Error, Hash, HashAlgorithm, KeyValuePair, MAX_ARRAY_WIDTH};
+use super::{Error, HamtNodeCache, Hash, HashAlgorithm, HashedKey, KeyValuePair, MAX_ARRAY_WIDTH};
 
 /// Node in Hamt tree which contains bitfield of set indexes and pointers to nodes
 #[derive(Debug)]
@@ -25,6 +29,17 @@ pub(crate) struct Node<K, V, H> {
     hash: PhantomData<H>,
 }
 
+/// A dirty node detached from the tree while flushing in parallel.
+#[cfg(feature = "parallel")]
+struct DetachedNode<K, V, H> {
+    /// The node, until it's flushed.
+    node: Option<Box<Node<K, V, H>>>,
+    /// The index of the parent among the detached nodes, if it's not the node being flushed.
+    parent: Option<usize>,
+    /// The index of the node among its parent's pointers.
+    index: usize,
+}
+
 impl<K: PartialEq, V: PartialEq, H> PartialEq for Node<K, V, H> {
     fn eq(&self, other: &Self) -> bool {
         (self.bitfield == other.bitfield) && (self.pointers == other.pointers)
@@ -72,6 +87,17 @@ impl<K, V, H> Default for Node<K, V, H> {
     }
 }
 
+impl<K: Clone, V: Clone, H> Node<K, V, H> {
+    /// Returns a copy of the node without any of the nodes loaded beneath it.
+    pub(crate) fn clone_unloaded(&self) -> Self {
+        Node {
+            bitfield: self.bitfield,
+            pointers: self.pointers.iter().map(Pointer::clone_unloaded).collect(),
+            hash: Default::default(),
+        }
+    }
+}
+
 impl<K, V, H> Node<K, V, H>
 where
     K: Hash + Eq + PartialOrd + Serialize + DeserializeOwned,
@@ -100,18 +126,66 @@ where
         )
     }
 
+    /// Builds a node from entries sorted by hash (and then by key) whose hashes all share the
+    /// first `depth` levels of bits. Child nodes are built bottom-up and written to the store as
+    /// soon as they're complete, so each one is written exactly once. The returned node itself is
+    /// not written.
+    pub(crate) fn from_sorted<S: Blockstore>(
+        entries: Vec<(HashedKey, KeyValuePair<K, V>)>,
+        depth: u32,
+        bit_width: u32,
+        store: &S,
+    ) -> Result<Self, Error> {
+        let consumed = depth * bit_width;
+        let index_of = |hash: &HashedKey| HashBits::new_at_index(hash, consumed).next(bit_width);
+
+        let mut node = Self::default();
+        let mut entries = entries.into_iter().peekable();
+        while let Some((hash, kv)) = entries.next() {
+            let idx = index_of(&hash)?;
+            let mut group = vec![(hash, kv)];
+            while let Some((next, _)) = entries.peek() {
+                if index_of(next)? != idx {
+                    break;
+                }
+                group.extend(entries.next());
+            }
+
+            let pointer = if group.len() <= MAX_ARRAY_WIDTH {
+                let mut vals: Vec<_> = group.into_iter().map(|(_, kv)| kv).collect();
+                vals.sort_unstable_by(|a, b| {
+                    a.key().partial_cmp(b.key()).unwrap_or(Ordering::Equal)
+                });
+                Pointer::Values(vals)
+            } else {
+                let child = Self::from_sorted(group, depth + 1, bit_width, store)?;
+                let cid = store.put_cbor(&child, Code::Blake2b256)?;
+                Pointer::Link {
+                    cid,
+                    cache: OnceCell::new(),
+                }
+            };
+            node.bitfield.set_bit(idx);
+            node.pointers.push(pointer);
+        }
+        Ok(node)
+    }
+
     #[inline]
     pub fn get<Q: ?Sized, S: Blockstore>(
         &self,
         k: &Q,
         store: &S,
+        node_cache: Option<&HamtNodeCache<K, V, H>>,
         bit_width: u32,
     ) -> Result<Option<&V>, Error>
     where
         K: Borrow<Q>,
         Q: Eq + Hash,
     {
-        Ok(self.search(k, store, bit_width)?.map(|kv| kv.value()))
+        Ok(self
+            .search(k, store, node_cache, bit_width)?
+            .map(|kv| kv.value()))
     }
 
     #[inline]
@@ -134,7 +208,12 @@ where
         self.pointers.is_empty()
     }
 
-    pub(crate) fn for_each<S, F>(&self, store: &S, f: &mut F) -> Result<(), Error>
+    pub(crate) fn for_each<S, F>(
+        &self,
+        store: &S,
+        node_cache: Option<&HamtNodeCache<K, V, H>>,
+        f: &mut F,
+    ) -> Result<(), Error>
     where
         F: FnMut(&K, &V) -> anyhow::Result<()>,
         S: Blockstore,
@@ -142,28 +221,75 @@ where
         for p in &self.pointers {
             match p {
                 Pointer::Link { cid, cache } => {
-                    if let Some(cached_node) = cache.get() {
-                        cached_node.for_each(store, f)?
-                    } else {
-                        let node = if let Some(node) = store.get_cbor(cid)? {
-                            node
-                        } else {
-                            #[cfg(not(feature = "ignore-dead-links"))]
-                            return Err(Error::CidNotFound(cid.to_string()));
+                    if let Some(node) = Self::load_link(cid, cache, store, node_cache)? {
+                        node.for_each(store, node_cache, f)?
+                    }
+                }
+                Pointer::Dirty(n) => n.for_each(store, node_cache, f)?,
+                Pointer::Values(kvs) => {
+                    for kv in kvs {
+                        f(kv.0.borrow(), kv.1.borrow())?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
 
-                            #[cfg(feature = "ignore-dead-links")]
-                            continue;
-                        };
+    /// Iterates over the entries whose keys fall into `range`, in hash order.
+    ///
+    /// `bounds` are the (partially consumed) hashes of the lower and upper bounds of the range,
+    /// if this node lies on the edge of the range. Children that lie entirely outside the range
+    /// are skipped. This is only correct for order-preserving hash algorithms.
+    pub(crate) fn for_each_range<Q: ?Sized, R, S, F>(
+        &self,
+        store: &S,
+        node_cache: Option<&HamtNodeCache<K, V, H>>,
+        bit_width: u32,
+        bounds: (Option<HashBits>, Option<HashBits>),
+        range: &R,
+        f: &mut F,
+    ) -> Result<(), Error>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd,
+        R: RangeBounds<Q>,
+        F: FnMut(&K, &V) -> anyhow::Result<()>,
+        S: Blockstore,
+    {
+        let (mut lower, mut upper) = bounds;
+        let lower_idx = lower.as_mut().map(|b| b.next(bit_width)).transpose()?;
+        let upper_idx = upper.as_mut().map(|b| b.next(bit_width)).transpose()?;
+
+        let set_bits = (0..1u32 << bit_width).filter(|&idx| self.bitfield.test_bit(idx));
+        for (idx, p) in set_bits.zip(&self.pointers) {
+            if matches!(lower_idx, Some(l) if idx < l) {
+                continue;
+            }
+            if matches!(upper_idx, Some(u) if idx > u) {
+                break;
+            }
+            // Only children on the edge of the range need to keep checking the bounds.
+            let child_bounds = (
+                lower.filter(|_| lower_idx == Some(idx)),
+                upper.filter(|_| upper_idx == Some(idx)),
+            );
 
-                        // Ignore error intentionally, the cache value will always be the same
-                        let cache_node = cache.get_or_init(|| node);
-                        cache_node.for_each(store, f)?
+            match p {
+                Pointer::Link { cid, cache } => {
+                    if let Some(node) = Self::load_link(cid, cache, store, node_cache)? {
+                        node.for_each_range(store, node_cache, bit_width, child_bounds, range, f)?
                     }
                 }
-                Pointer::Dirty(n) => n.for_each(store, f)?,
+                Pointer::Dirty(n) => {
+                    n.for_each_range(store, node_cache, bit_width, child_bounds, range, f)?
+                }
                 Pointer::Values(kvs) => {
                     for kv in kvs {
-                        f(kv.0.borrow(), kv.1.borrow())?;
+                        let key: &Q = kv.key().borrow();
+                        if range.contains(key) {
+                            f(kv.key(), kv.value())?;
+                        }
                     }
                 }
             }
@@ -176,6 +302,7 @@ where
         &self,
         q: &Q,
         store: &S,
+        node_cache: Option<&HamtNodeCache<K, V, H>>,
         bit_width: u32,
     ) -> Result<Option<&KeyValuePair<K, V>>, Error>
     where
@@ -183,7 +310,7 @@ where
         Q: Eq + Hash,
     {
         let hash = H::hash(q);
-        self.get_value(&mut HashBits::new(&hash), bit_width, q, store)
+        self.get_value(&mut HashBits::new(&hash), bit_width, q, store, node_cache)
     }
 
     fn get_value<Q: ?Sized, S: Blockstore>(
@@ -192,6 +319,7 @@ where
         bit_width: u32,
         key: &Q,
         store: &S,
+        node_cache: Option<&HamtNodeCache<K, V, H>>,
     ) -> Result<Option<&KeyValuePair<K, V>>, Error>
     where
         K: Borrow<Q>,
@@ -206,27 +334,11 @@ where
         let cindex = self.index_for_bit_pos(idx);
         let child = self.get_child(cindex);
         match child {
-            Pointer::Link { cid, cache } => {
-                if let Some(cached_node) = cache.get() {
-                    // Link node is cached
-                    cached_node.get_value(hashed_key, bit_width, key, store)
-                } else {
-                    let node: Box<Node<K, V, H>> = if let Some(node) = store.get_cbor(cid)? {
-                        node
-                    } else {
-                        #[cfg(not(feature = "ignore-dead-links"))]
-                        return Err(Error::CidNotFound(cid.to_string()));
-
-                        #[cfg(feature = "ignore-dead-links")]
-                        return Ok(None);
-                    };
-
-                    // Intentionally ignoring error, cache will always be the same.
-                    let cache_node = cache.get_or_init(|| node);
-                    cache_node.get_value(hashed_key, bit_width, key, store)
-                }
-            }
-            Pointer::Dirty(n) => n.get_value(hashed_key, bit_width, key, store),
+            Pointer::Link { cid, cache } => match Self::load_link(cid, cache, store, node_cache)? {
+                Some(node) => node.get_value(hashed_key, bit_width, key, store, node_cache),
+                None => Ok(None),
+            },
+            Pointer::Dirty(n) => n.get_value(hashed_key, bit_width, key, store, node_cache),
             Pointer::Values(vals) => Ok(vals.iter().find(|kv| key.eq(kv.key().borrow()))),
         }
     }
@@ -262,17 +374,14 @@ where
 
         match child {
             Pointer::Link { cid, cache } => {
-                cache.get_or_try_init(|| {
-                    store
-                        .get_cbor(cid)?
-                        .ok_or_else(|| Error::CidNotFound(cid.to_string()))
-                })?;
-                let child_node = cache.get_mut().expect("filled line above");
+                let mut child_node = Self::take_link(cid, cache, store)?;
 
                 let (old, modified) =
                     child_node.modify_value(hashed_key, bit_width, key, value, store, overwrite)?;
                 if modified {
-                    *child = Pointer::Dirty(std::mem::take(child_node));
+                    *child = Pointer::Dirty(child_node);
+                } else {
+                    *cache = OnceCell::from(Arc::from(child_node));
                 }
                 Ok((old, modified))
             }
@@ -360,18 +469,15 @@ where
 
         match child {
             Pointer::Link { cid, cache } => {
-                cache.get_or_try_init(|| {
-                    store
-                        .get_cbor(cid)?
-                        .ok_or_else(|| Error::CidNotFound(cid.to_string()))
-                })?;
-                let child_node = cache.get_mut().expect("filled line above");
+                let mut child_node = Self::take_link(cid, cache, store)?;
 
                 let deleted = child_node.rm_value(hashed_key, bit_width, key, store)?;
                 if deleted.is_some() {
-                    *child = Pointer::Dirty(std::mem::take(child_node));
+                    *child = Pointer::Dirty(child_node);
                     // Clean to retrieve canonical form
                     child.clean()?;
+                } else {
+                    *cache = OnceCell::from(Arc::from(child_node));
                 }
 
                 Ok(deleted)
@@ -416,7 +522,7 @@ where
                 let cid = store.put_cbor(node, Code::Blake2b256)?;
 
                 // Can keep the flushed node in link cache
-                let cache = OnceCell::from(std::mem::take(node));
+                let cache = OnceCell::from(Arc::from(std::mem::take(node)));
 
                 // Replace cached node with Cid link
                 *pointer = Pointer::Link { cid, cache };
@@ -426,6 +532,173 @@ where
         Ok(())
     }
 
+    /// Returns the node behind a link for reading. The link's own cache is consulted first, then
+    /// the shared node cache (if any) and finally the store. Nodes loaded from the store are
+    /// added to the shared node cache. Either way, the link's cache gets its own copy of the node.
+    ///
+    /// Returns `None` for dead links when the `ignore-dead-links` feature is enabled.
+    fn load_link<'a, S: Blockstore>(
+        cid: &Cid,
+        cache: &'a OnceCell<Arc<Self>>,
+        store: &S,
+        node_cache: Option<&HamtNodeCache<K, V, H>>,
+    ) -> Result<Option<&'a Self>, Error> {
+        if let Some(cached_node) = cache.get() {
+            return Ok(Some(&**cached_node));
+        }
+
+        let node = match node_cache.and_then(|c| c.get(cid)) {
+            Some(node) => node,
+            None => {
+                let node: Self = match store.get_cbor(cid)? {
+                    Some(node) => node,
+                    #[cfg(not(feature = "ignore-dead-links"))]
+                    None => return Err(Error::CidNotFound(cid.to_string())),
+                    #[cfg(feature = "ignore-dead-links")]
+                    None => return Ok(None),
+                };
+                if let Some(c) = node_cache {
+                    c.insert(*cid, &node);
+                }
+                node
+            }
+        };
+
+        // Intentionally ignoring error, cache will always be the same.
+        Ok(Some(&**cache.get_or_init(|| Arc::new(node))))
+    }
+
+    /// Takes ownership of the node behind a link for mutation, loading it from the store if it
+    /// isn't loaded yet.
+    fn take_link<S: Blockstore>(
+        cid: &Cid,
+        cache: &mut OnceCell<Arc<Self>>,
+        store: &S,
+    ) -> Result<Box<Self>, Error> {
+        if let Some(node) = cache.take().and_then(|n| Arc::try_unwrap(n).ok()) {
+            return Ok(Box::new(node));
+        }
+        store
+            .get_cbor(cid)?
+            .ok_or_else(|| Error::CidNotFound(cid.to_string()))
+    }
+
+    /// Flushes dirty nodes like [`flush`](Self::flush), but hashes them in parallel.
+    ///
+    /// Dirty nodes are detached from the tree in a single pass, then flushed in waves by height,
+    /// starting with the ones without any dirty children. Each wave is serialized on the current
+    /// thread, hashed on the rayon thread pool, and written to the store with a single
+    /// `put_many_keyed` call. The resulting CIDs are identical to the ones produced by a
+    /// sequential flush. On error, the nodes that weren't flushed yet are put back as they were.
+    #[cfg(feature = "parallel")]
+    pub fn flush_parallel<S: Blockstore>(&mut self, store: &S) -> Result<(), Error> {
+        let mut detached = Vec::new();
+        let mut waves = Vec::new();
+        self.detach_dirty(None, &mut detached, &mut waves);
+        let result = self.flush_detached(store, &mut detached, waves);
+
+        // Put back the nodes that weren't flushed, children before their parents.
+        while let Some(DetachedNode {
+            node,
+            parent,
+            index,
+        }) = detached.pop()
+        {
+            if let Some(node) = node {
+                self.attach(&mut detached, parent, index, Pointer::Dirty(node));
+            }
+        }
+        result
+    }
+
+    /// Moves all dirty descendants to `detached`, leaving empty placeholders behind, and adds
+    /// their indices to the wave matching their height. Returns the height of this node: zero if
+    /// it has no dirty children, and one more than its highest dirty child otherwise.
+    #[cfg(feature = "parallel")]
+    fn detach_dirty(
+        &mut self,
+        parent: Option<usize>,
+        detached: &mut Vec<DetachedNode<K, V, H>>,
+        waves: &mut Vec<Vec<usize>>,
+    ) -> usize {
+        let mut height = 0;
+        for (index, pointer) in self.pointers.iter_mut().enumerate() {
+            if let Pointer::Dirty(node) = pointer {
+                let mut node = std::mem::take(node);
+                let id = detached.len();
+                detached.push(DetachedNode {
+                    node: None,
+                    parent,
+                    index,
+                });
+                let node_height = node.detach_dirty(Some(id), detached, waves);
+                detached[id].node = Some(node);
+                if waves.len() <= node_height {
+                    waves.resize_with(node_height + 1, Vec::new);
+                }
+                waves[node_height].push(id);
+                height = height.max(node_height + 1);
+            }
+        }
+        height
+    }
+
+    /// Flushes the detached nodes wave by wave, linking each flushed node from its parent.
+    #[cfg(feature = "parallel")]
+    fn flush_detached<S: Blockstore>(
+        &mut self,
+        store: &S,
+        detached: &mut [DetachedNode<K, V, H>],
+        waves: Vec<Vec<usize>>,
+    ) -> Result<(), Error> {
+        use fvm_ipld_encoding::DAG_CBOR;
+        use multihash::MultihashDigest;
+        use rayon::prelude::*;
+
+        for wave in waves {
+            let blocks = wave
+                .iter()
+                .map(|&id| {
+                    let node = detached[id].node.as_ref().expect("node not flushed yet");
+                    fvm_ipld_encoding::to_vec(node)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let cids: Vec<Cid> = blocks
+                .par_iter()
+                .map(|b| Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(b)))
+                .collect();
+            store.put_many_keyed(cids.iter().copied().zip(&blocks))?;
+
+            for (id, cid) in wave.into_iter().zip(cids) {
+                let node = detached[id].node.take().expect("node not flushed yet");
+                let (parent, index) = (detached[id].parent, detached[id].index);
+                // Can keep the flushed node in link cache
+                let cache = OnceCell::from(Arc::from(node));
+                self.attach(detached, parent, index, Pointer::Link { cid, cache });
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets the pointer at `index` in the given detached parent, or in this node if there is none.
+    #[cfg(feature = "parallel")]
+    fn attach(
+        &mut self,
+        detached: &mut [DetachedNode<K, V, H>],
+        parent: Option<usize>,
+        index: usize,
+        pointer: Pointer<K, V, H>,
+    ) {
+        let parent = match parent {
+            Some(parent) => detached[parent]
+                .node
+                .as_deref_mut()
+                .expect("parents are flushed after their children"),
+            None => self,
+        };
+        parent.pointers[index] = pointer;
+    }
+
     fn rm_child(&mut self, i: usize, idx: u32) -> Pointer<K, V, H> {
         self.bitfield.clear_bit(idx);
         self.pointers.remove(i)
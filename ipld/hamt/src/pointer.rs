@@ -3,10 +3,11 @@
 
 use std::cmp::Ordering;
 use std::convert::{TryFrom, TryInto};
+use std::sync::Arc;
 
 use cid::Cid;
 use libipld_core::ipld::Ipld;
-use once_cell::unsync::OnceCell;
+use once_cell::sync::OnceCell;
 use serde::de::{self, DeserializeOwned};
 use serde::{ser, Deserialize, Deserializer, Serialize, Serializer};
 
@@ -19,7 +20,7 @@ pub(crate) enum Pointer<K, V, H> {
     Values(Vec<KeyValuePair<K, V>>),
     Link {
         cid: Cid,
-        cache: OnceCell<Box<Node<K, V, H>>>,
+        cache: OnceCell<Arc<Node<K, V, H>>>,
     },
     Dirty(Box<Node<K, V, H>>),
 }
@@ -35,6 +36,20 @@ impl<K: PartialEq, V: PartialEq, H> PartialEq for Pointer<K, V, H> {
     }
 }
 
+impl<K: Clone, V: Clone, H> Pointer<K, V, H> {
+    /// Returns a copy of the pointer without any of the nodes loaded beneath it.
+    pub(crate) fn clone_unloaded(&self) -> Self {
+        match self {
+            Pointer::Values(vals) => Pointer::Values(vals.clone()),
+            Pointer::Link { cid, .. } => Pointer::Link {
+                cid: *cid,
+                cache: Default::default(),
+            },
+            Pointer::Dirty(node) => Pointer::Dirty(Box::new(node.clone_unloaded())),
+        }
+    }
+}
+
 /// Serialize the Pointer like an untagged enum.
 impl<K, V, H> Serialize for Pointer<K, V, H>
 where
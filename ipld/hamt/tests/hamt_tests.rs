@@ -2,14 +2,16 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 
 use std::fmt::Display;
+use std::ops::Bound;
+use std::sync::Arc;
 
 use fvm_ipld_blockstore::tracking::{BSStats, TrackingBlockstore};
-use fvm_ipld_blockstore::MemoryBlockstore;
+use fvm_ipld_blockstore::{Blockstore, MemoryBlockstore};
 use fvm_ipld_encoding::strict_bytes::ByteBuf;
 use fvm_ipld_encoding::CborStore;
 #[cfg(feature = "identity")]
 use fvm_ipld_hamt::Identity;
-use fvm_ipld_hamt::{BytesKey, Hamt};
+use fvm_ipld_hamt::{BytesKey, Hamt, HamtNodeCache, KeyPrefix};
 use multihash::Code;
 
 // Redeclaring max array size of Hamt to avoid exposing value
@@ -316,6 +318,221 @@ fn for_each() {
     assert_eq!(*store.stats.borrow(), BSStats {r: 30, w: 30, br: 3209, bw: 3209});
 }
 
+#[test]
+fn bulk_construction() {
+    let mem = MemoryBlockstore::default();
+    let store = TrackingBlockstore::new(&mem);
+
+    // Insertion order doesn't matter.
+    let entries = (0..200).rev().map(|i| (tstring(i), tstring(i)));
+    let mut hamt: Hamt<_, BytesKey> =
+        Hamt::new_from_iter_with_bit_width(&store, 5, entries).unwrap();
+    assert_eq!(hamt.get(&tstring(42)).unwrap(), Some(&tstring(42)));
+
+    // Same root as setting the entries one by one, and every node is written exactly once.
+    let c = hamt.flush().unwrap();
+    assert_eq!(
+        c.to_string().as_str(),
+        "bafy2bzaceczhz54xmmz3xqnbmvxfbaty3qprr6dq7xh5vzwqbirlsnbd36z7a"
+    );
+    #[rustfmt::skip]
+    assert_eq!(*store.stats.borrow(), BSStats {r: 0, w: 30, br: 0, bw: 3209});
+
+    for bit_width in [1, 3, 8] {
+        let mut expected: Hamt<_, u64, u64> = Hamt::new_with_bit_width(&mem, bit_width);
+        for i in 0..500 {
+            expected.set(i, i).unwrap();
+        }
+        let mut hamt: Hamt<_, u64, u64> =
+            Hamt::new_from_iter_with_bit_width(&mem, bit_width, (0..500).map(|i| (i, i))).unwrap();
+        assert_eq!(hamt.flush().unwrap(), expected.flush().unwrap());
+    }
+
+    let empty: Hamt<_, u64, u64> = Hamt::new_from_iter(&mem, []).unwrap();
+    assert!(empty.is_empty());
+
+    assert!(Hamt::<_, u64, u64>::new_from_iter(&mem, [(1, 1), (1, 2)]).is_err());
+}
+
+#[test]
+#[cfg(feature = "parallel")]
+fn parallel_flush() {
+    let mem = MemoryBlockstore::default();
+    let par_mem = MemoryBlockstore::default();
+
+    for bit_width in [1, 3, 5, 8] {
+        let mut expected: Hamt<_, u64, u64> = Hamt::new_with_bit_width(&mem, bit_width);
+        let mut hamt: Hamt<_, u64, u64> = Hamt::new_with_bit_width(&par_mem, bit_width);
+        for i in 0..1000 {
+            expected.set(i, i).unwrap();
+            hamt.set(i, i).unwrap();
+        }
+        assert_eq!(hamt.flush_parallel().unwrap(), expected.flush().unwrap());
+
+        // Only part of the tree is dirty the second time around.
+        for i in (0..1000).step_by(7) {
+            expected.set(i, i * 2).unwrap();
+            hamt.set(i, i * 2).unwrap();
+            expected.delete(&(i + 1)).unwrap();
+            hamt.delete(&(i + 1)).unwrap();
+        }
+        let c = hamt.flush_parallel().unwrap();
+        assert_eq!(c, expected.flush().unwrap());
+
+        // Every block reachable from the new root was written.
+        let reloaded: Hamt<_, u64, u64> =
+            Hamt::load_with_bit_width(&c, &par_mem, bit_width).unwrap();
+        assert_eq!(reloaded.get(&7).unwrap(), Some(&14));
+        assert_eq!(reloaded.get(&8).unwrap(), None);
+    }
+}
+
+fn count_entries<BS: Blockstore>(hamt: &Hamt<BS, BytesKey>) -> usize {
+    let mut count = 0;
+    hamt.for_each(|k, v| {
+        assert_eq!(k, v);
+        count += 1;
+        Ok(())
+    })
+    .unwrap();
+    count
+}
+
+#[test]
+fn shared_node_cache() {
+    let mem = MemoryBlockstore::default();
+    let store = TrackingBlockstore::new(&mem);
+
+    let mut hamt: Hamt<_, BytesKey> = Hamt::new_with_bit_width(&store, 5);
+    for i in 0..200 {
+        hamt.set(tstring(i), tstring(i)).unwrap();
+    }
+    let c = hamt.flush().unwrap();
+
+    let cache = Arc::new(HamtNodeCache::new(64));
+
+    // The first load populates the cache.
+    let before = *store.stats.borrow();
+    let hamt: Hamt<_, BytesKey> = Hamt::load_with_bit_width(&c, &store, 5)
+        .unwrap()
+        .with_node_cache(cache.clone());
+    assert_eq!(count_entries(&hamt), 200);
+    let cold_reads = store.stats.borrow().r - before.r;
+    assert_eq!(cold_reads, cache.len() + 1);
+
+    // The second load only needs to read the root.
+    let before = *store.stats.borrow();
+    let mut hamt: Hamt<_, BytesKey> = Hamt::load_with_bit_width(&c, &store, 5)
+        .unwrap()
+        .with_node_cache(cache.clone());
+    assert_eq!(count_entries(&hamt), 200);
+    assert_eq!(store.stats.borrow().r - before.r, 1);
+
+    // Mutating through a cached HAMT must not affect the cached nodes.
+    hamt.set(tstring(1000), tstring(1000)).unwrap();
+    let modified = hamt.flush().unwrap();
+    assert_ne!(modified, c);
+
+    let hamt: Hamt<_, BytesKey> = Hamt::load_with_bit_width(&c, &store, 5)
+        .unwrap()
+        .with_node_cache(cache);
+    assert_eq!(count_entries(&hamt), 200);
+    assert_eq!(hamt.get(&tstring(1000)).unwrap(), None);
+}
+
+#[test]
+fn send_and_sync() {
+    fn assert_send<T: Send>() {}
+    fn assert_sync<T: Sync>() {}
+
+    // Caching decoded nodes must not prevent HAMTs from moving across threads, or caches from
+    // being shared between them.
+    assert_send::<Hamt<MemoryBlockstore, String, BytesKey>>();
+    assert_send::<HamtNodeCache<BytesKey, String>>();
+    assert_sync::<HamtNodeCache<BytesKey, String>>();
+}
+
+#[test]
+fn key_prefix_range() {
+    let store = MemoryBlockstore::default();
+    let mut hamt: Hamt<_, u64, u64, KeyPrefix> = Hamt::new_with_bit_width(&store, 5);
+
+    // Insert in a scrambled order.
+    for i in 0..1000u64 {
+        let k = (i * 7919) % 1000;
+        hamt.set(k, k * 2).unwrap();
+    }
+    let c = hamt.flush().unwrap();
+    let hamt: Hamt<_, u64, u64, KeyPrefix> = Hamt::load_with_bit_width(&c, &store, 5).unwrap();
+
+    let collect = |range: (Bound<u64>, Bound<u64>)| {
+        let mut keys = Vec::new();
+        hamt.for_each_range(range, |k, v| {
+            assert_eq!(*v, k * 2);
+            keys.push(*k);
+            Ok(())
+        })
+        .unwrap();
+        keys
+    };
+
+    assert_eq!(
+        collect((Bound::Unbounded, Bound::Unbounded)),
+        (0..1000).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        collect((Bound::Included(100), Bound::Excluded(200))),
+        (100..200).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        collect((Bound::Excluded(255), Bound::Included(256))),
+        vec![256]
+    );
+    assert_eq!(
+        collect((Bound::Included(990), Bound::Unbounded)),
+        (990..1000).collect::<Vec<_>>()
+    );
+    assert!(collect((Bound::Included(2000), Bound::Unbounded)).is_empty());
+
+    // Plain iteration is ordered as well.
+    let mut keys = Vec::new();
+    hamt.for_each(|k, _| {
+        keys.push(*k);
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(keys, (0..1000).collect::<Vec<_>>());
+}
+
+#[test]
+fn key_prefix_bytes_range() {
+    let store = MemoryBlockstore::default();
+    let mut hamt: Hamt<_, u8, BytesKey, KeyPrefix> = Hamt::new(&store);
+
+    let words = [
+        "queue/2",
+        "expiry/10",
+        "queue/1",
+        "expiry/09",
+        "queue/10",
+        "other",
+    ];
+    for w in words {
+        hamt.set(w.as_bytes().to_vec().into(), 0).unwrap();
+    }
+
+    let mut keys = Vec::new();
+    hamt.for_each_range(
+        BytesKey::from(b"queue/".to_vec())..BytesKey::from(b"queue0".to_vec()),
+        |k, _| {
+            keys.push(String::from_utf8(k.0.clone()).unwrap());
+            Ok(())
+        },
+    )
+    .unwrap();
+    assert_eq!(keys, vec!["queue/1", "queue/10", "queue/2"]);
+}
+
 #[cfg(feature = "identity")]
 fn add_and_remove_keys(
     bit_width: u32,
@@ -1,4 +1,5 @@
-use fvm_shared::event::ActorEvent;
+use fvm_shared::error::ErrorNumber;
+use fvm_shared::event::{ActorEvent, Event};
 
 use crate::{sys, SyscallResult};
 
@@ -8,3 +9,11 @@ pub fn emit_event(evt: &ActorEvent) -> SyscallResult<()> {
 
     unsafe { sys::event::emit_event(entries.as_ptr(), entries.len() as u32) }
 }
+
+/// Emits a typed event, such as one implemented with `#[derive(Event)]`.
+pub fn emit<E: Event>(evt: &E) -> SyscallResult<()> {
+    let evt = evt
+        .to_actor_event()
+        .map_err(|_| ErrorNumber::Serialization)?;
+    emit_event(&evt)
+}
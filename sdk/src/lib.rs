@@ -18,6 +18,12 @@ pub const NO_DATA_BLOCK_ID: u32 = 0;
 
 // TODO: provide a custom panic handler?
 
+// Used by the exported `fvm_syscalls!` macro.
+#[doc(hidden)]
+pub mod __private {
+    pub use num_traits;
+}
+
 #[inline]
 pub(crate) fn status_code_to_bool(code: i32) -> bool {
     code == 0
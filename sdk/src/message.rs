@@ -3,7 +3,7 @@ use std::convert::TryInto;
 use fvm_ipld_encoding::DAG_CBOR;
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::sys::out::vm::MessageContext;
-use fvm_shared::sys::{BlockId, Codec};
+use fvm_shared::sys::{BlockId, Codec, ContextFlags};
 use fvm_shared::{ActorID, MethodNum};
 
 use crate::{sys, SyscallResult, NO_DATA_BLOCK_ID};
@@ -14,6 +14,11 @@ lazy_static::lazy_static! {
             sys::vm::message_context().expect("failed to lookup message context")
         }
     };
+    pub(crate) static ref CONTEXT_FLAGS: ContextFlags = {
+        unsafe {
+            sys::vm::context_flags().expect("failed to lookup context flags")
+        }
+    };
 }
 
 /// Returns the ID address of the caller.
@@ -55,6 +60,13 @@ pub fn gas_limit() -> u64 {
     MESSAGE_CONTEXT.gas_limit
 }
 
+/// Returns true if this call is read-only, in which case any attempt to mutate state fails with
+/// [`ErrorNumber::ReadOnly`](fvm_shared::error::ErrorNumber::ReadOnly).
+#[inline(always)]
+pub fn read_only() -> bool {
+    CONTEXT_FLAGS.read_only()
+}
+
 /// Returns the execution gas premium
 pub fn gas_premium() -> TokenAmount {
     MESSAGE_CONTEXT
@@ -5,10 +5,23 @@ use fvm_shared::address::Address;
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::error::{ErrorNumber, ExitCode};
 use fvm_shared::receipt::Receipt;
+#[doc(inline)]
+pub use fvm_shared::sys::SendFlags;
 use fvm_shared::MethodNum;
 
 use crate::{sys, SyscallResult, NO_DATA_BLOCK_ID};
 
+/// Options for [`send_with_options`].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SendOptions {
+    /// The maximum amount of gas the callee may use. If `None`, the callee may use all of the
+    /// remaining gas.
+    pub gas_limit: Option<u64>,
+    /// The flags of the send. With [`SendFlags::READ_ONLY`], the callee (and anything it calls)
+    /// may not mutate state, making the send safe to use for view-style queries.
+    pub flags: SendFlags,
+}
+
 /// Sends a message to another actor.
 // TODO: Drop the use of receipts here as we don't return the gas used. Alternatively, we _could_
 // return gas used?
@@ -23,21 +36,10 @@ pub fn send(
         .try_into()
         .map_err(|_| ErrorNumber::InsufficientFunds)?;
     unsafe {
-        // Insert parameters as a block. Nil parameters is represented as the
-        // NO_DATA_BLOCK_ID block ID in the FFI interface.
-        let params_id = if params.len() > 0 {
-            sys::ipld::block_create(DAG_CBOR, params.as_ptr(), params.len() as u32)?
-        } else {
-            NO_DATA_BLOCK_ID
-        };
+        let params_id = params_block(&params)?;
 
         // Perform the syscall to send the message.
-        let fvm_shared::sys::out::send::Send {
-            exit_code,
-            return_id,
-            return_codec: _, // assume cbor for now.
-            return_size,
-        } = sys::send::send(
+        let ret = sys::send::send(
             recipient.as_ptr(),
             recipient.len() as u32,
             method,
@@ -46,26 +48,80 @@ pub fn send(
             value.lo,
         )?;
 
-        // Process the result.
-        let exit_code = ExitCode::new(exit_code);
-        let return_data = match exit_code {
-            ExitCode::OK if return_id != NO_DATA_BLOCK_ID => {
-                // Allocate a buffer to read the return data.
-                let mut bytes = vec![0; return_size as usize];
+        read_receipt(ret)
+    }
+}
 
-                // Now read the return data.
-                let unread = sys::ipld::block_read(return_id, 0, bytes.as_mut_ptr(), return_size)?;
-                assert_eq!(0, unread);
-                RawBytes::from(bytes)
-            }
-            _ => Default::default(),
-        };
+/// Sends a message to another actor with the given [`SendOptions`]. If a gas limit is specified,
+/// the callee may use at most that much gas, and running out of it fails the send with
+/// [`ExitCode::SYS_OUT_OF_GAS`] while leaving the caller's remaining gas untouched.
+pub fn send_with_options(
+    to: &Address,
+    method: MethodNum,
+    params: RawBytes,
+    value: TokenAmount,
+    options: SendOptions,
+) -> SyscallResult<Receipt> {
+    let recipient = to.to_bytes();
+    let value: fvm_shared::sys::TokenAmount = value
+        .try_into()
+        .map_err(|_| ErrorNumber::InsufficientFunds)?;
+    unsafe {
+        let params_id = params_block(&params)?;
 
-        Ok(Receipt {
-            exit_code,
-            return_data,
-            gas_used: 0,
-            events_root: Default::default(), // TODO; it's likely time to change the Receipt return type here.
-        })
+        // Perform the syscall to send the message.
+        let ret = sys::send::send_v2(
+            recipient.as_ptr(),
+            recipient.len() as u32,
+            method,
+            params_id,
+            value.hi,
+            value.lo,
+            options.gas_limit.unwrap_or(u64::MAX),
+            options.flags.bits(),
+        )?;
+
+        read_receipt(ret)
     }
 }
+
+/// Inserts the parameters as a block. Nil parameters is represented as the NO_DATA_BLOCK_ID block
+/// ID in the FFI interface.
+unsafe fn params_block(params: &RawBytes) -> SyscallResult<u32> {
+    if params.len() > 0 {
+        sys::ipld::block_create(DAG_CBOR, params.as_ptr(), params.len() as u32)
+    } else {
+        Ok(NO_DATA_BLOCK_ID)
+    }
+}
+
+/// Turns the result of a send syscall into a receipt, reading the return data.
+unsafe fn read_receipt(ret: sys::send::Send) -> SyscallResult<Receipt> {
+    let sys::send::Send {
+        exit_code,
+        return_id,
+        return_codec: _, // assume cbor for now.
+        return_size,
+    } = ret;
+
+    let exit_code = ExitCode::new(exit_code);
+    let return_data = match exit_code {
+        ExitCode::OK if return_id != NO_DATA_BLOCK_ID => {
+            // Allocate a buffer to read the return data.
+            let mut bytes = vec![0; return_size as usize];
+
+            // Now read the return data.
+            let unread = sys::ipld::block_read(return_id, 0, bytes.as_mut_ptr(), return_size)?;
+            assert_eq!(0, unread);
+            RawBytes::from(bytes)
+        }
+        _ => Default::default(),
+    };
+
+    Ok(Receipt {
+        exit_code,
+        return_data,
+        gas_used: 0,
+        events_root: Default::default(), // TODO; it's likely time to change the Receipt return type here.
+    })
+}
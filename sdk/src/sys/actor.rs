@@ -111,14 +111,16 @@ super::fvm_syscalls! {
     /// Generates a new actor address for an actor deployed
     /// by the calling actor.
     ///
-    /// **Privileged:** May only be called by the init actor.
+    /// **Privileged:** May only be called by the init actor. Other callers fail with
+    /// [`Forbidden`].
     #[doc(hidden)]
     pub fn new_actor_address(obuf_off: *mut u8, obuf_len: u32) -> Result<u32>;
 
     /// Creates a new actor in the state-tree with the specified actor ID, recording the specified
     /// "predictable" address in the actor root if non-empty, and returning a new stable address.
     ///
-    /// **Privileged:** May only be called by the init actor.
+    /// **Privileged:** May only be called by the init actor. Other callers fail with
+    /// [`Forbidden`].
     #[doc(hidden)]
     pub fn create_actor(
         actor_id: u64,
@@ -128,7 +130,8 @@ super::fvm_syscalls! {
     ) -> Result<()>;
 
     /// Installs and ensures actor code is valid and loaded.
-    /// **Privileged:** May only be called by the init actor.
+    /// **Privileged:** May only be called by the init actor. Other callers fail with
+    /// [`Forbidden`].
     #[cfg(feature = "m2-native")]
     pub fn install_actor(cid_off: *const u8) -> Result<()>;
 
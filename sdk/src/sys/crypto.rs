@@ -218,8 +218,9 @@ super::fvm_syscalls! {
     ///
     /// # Errors
     ///
-    /// | Error               | Reason                   |
-    /// |---------------------|--------------------------|
-    /// | [`IllegalArgument`] | an argument is malformed |
+    /// | Error               | Reason                                     |
+    /// |---------------------|--------------------------------------------|
+    /// | [`Forbidden`]       | the caller may not batch verify seals      |
+    /// | [`IllegalArgument`] | an argument is malformed                   |
     pub fn batch_verify_seals(batch_off: *const u8, batch_len: u32, result_off: *const u8) -> Result<()>;
 }
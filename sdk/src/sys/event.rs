@@ -16,6 +16,7 @@ super::fvm_syscalls! {
     /// | Error               | Reason                                                              |
     /// |---------------------|---------------------------------------------------------------------|
     /// | [`IllegalArgument`] | entries failed to validate due to improper encoding or invalid data |
+    /// | [`ReadOnly`]        | the actor is executing in read-only mode                            |
     pub fn emit_event(
         evt_off: *const u8,
         evt_len: u32,
@@ -49,6 +49,13 @@
 //! - `result` is an [`ErrorNumber`] or `0` on success.
 //! - `ret_ptr` is the offset (specified by the _caller_) where the FVM will write the return value
 //!   if, and only if the result is `0` (success).
+//!
+//! # Custom Syscalls
+//!
+//! Some embedders expose additional syscalls beyond the ones defined here. Actors targeting such an
+//! embedder can opt-in to them by declaring the syscall shims with [`fvm_syscalls!`], using the
+//! module and names the embedder binds them under. Calling them on a FVM that doesn't define them
+//! will fail to instantiate the actor.
 #[doc(inline)]
 pub use fvm_shared::error::ErrorNumber;
 #[doc(inline)]
@@ -67,7 +74,8 @@ pub mod send;
 pub mod sself;
 pub mod vm;
 
-/// Generate a set of FVM syscall shims.
+/// Generate a set of FVM syscall shims. This can be used to declare custom syscalls exposed by the
+/// embedder (see the [module documentation](self#custom-syscalls)).
 ///
 /// ```ignore
 /// fvm_sdk::sys::fvm_syscalls! {
@@ -96,6 +104,7 @@ pub mod vm;
 ///     pub fn aborts(arg: u32) -> !;
 /// }
 /// ```
+#[macro_export]
 macro_rules! fvm_syscalls {
     // Returns no values.
     (module = $module:literal; $(#[$attrs:meta])* $v:vis fn $name:ident($($args:ident : $args_ty:ty),*$(,)?) -> Result<()>; $($rest:tt)*) => {
@@ -113,7 +122,7 @@ macro_rules! fvm_syscalls {
             if code == 0 {
                 Ok(())
             } else {
-                Err(num_traits::FromPrimitive::from_u32(code)
+                Err($crate::__private::num_traits::FromPrimitive::from_u32(code)
                     .expect("syscall returned unrecognized exit code"))
             }
         }
@@ -138,7 +147,7 @@ macro_rules! fvm_syscalls {
             if code == 0 {
                 Ok(ret.assume_init())
             } else {
-                Err(num_traits::FromPrimitive::from_u32(code)
+                Err($crate::__private::num_traits::FromPrimitive::from_u32(code)
                     .expect("syscall returned unrecognized exit code"))
             }
         }
@@ -175,4 +184,4 @@ macro_rules! fvm_syscalls {
     (module = $module:literal;) => {};
 }
 
-pub(crate) use fvm_syscalls;
+pub use crate::fvm_syscalls;
@@ -46,4 +46,51 @@ super::fvm_syscalls! {
         value_hi: u64,
         value_lo: u64,
     ) -> Result<Send>;
+
+    /// Like [`send`], but lets the caller limit the gas available to the callee and pass
+    /// [`SendFlags`](fvm_shared::sys::SendFlags).
+    ///
+    /// # Arguments
+    ///
+    /// - `recipient_off` and `recipient_len` specify the location and length of the recipient's
+    ///   address (in wasm memory).
+    /// - `method` is the method number to invoke.
+    /// - `params` is the IPLD block handle of the method parameters.
+    /// - `value_hi` are the "high" bits of the token value to send (little-endian) in attoFIL.
+    /// - `value_lo` are the "high" bits of the token value to send (little-endian) in attoFIL.
+    /// - `gas_limit` is the maximum amount of gas the callee may use, or `u64::MAX` to let it use
+    ///   all of the remaining gas.
+    /// - `flags` are the [`SendFlags`](fvm_shared::sys::SendFlags) of the send. With `READ_ONLY`,
+    ///   the callee and everything it calls may not mutate state.
+    ///
+    /// **NOTE**: This syscall will transfer `(value_hi << 64) | (value_lo)` attoFIL to the
+    /// recipient.
+    ///
+    /// # Errors
+    ///
+    /// A syscall error in [`send_v2`] means the _caller_ did something wrong. If the _callee_
+    /// panics, exceeds some limit, aborts, aborts with an invalid code, etc., the syscall will
+    /// _succeed_ and the failure will be reflected in the exit code contained in the return value.
+    /// A callee that runs out of the gas given to it by `gas_limit` fails with `SYS_OUT_OF_GAS`,
+    /// and the caller keeps the rest of its gas.
+    ///
+    /// | Error                 | Reason                                               |
+    /// |-----------------------|------------------------------------------------------|
+    /// | [`NotFound`]          | target actor does not exist and cannot be created.   |
+    /// | [`InsufficientFunds`] | tried to send more FIL than available.               |
+    /// | [`InvalidHandle`]     | parameters block not found.                          |
+    /// | [`LimitExceeded`]     | recursion limit reached.                             |
+    /// | [`IllegalArgument`]   | invalid recipient address buffer or send flags.      |
+    /// | [`ReadOnly`]          | tried to transfer value or create an actor read-only.|
+    #[allow(clippy::too_many_arguments)]
+    pub fn send_v2(
+        recipient_off: *const u8,
+        recipient_len: u32,
+        method: u64,
+        params: u32,
+        value_hi: u64,
+        value_lo: u64,
+        gas_limit: u64,
+        flags: u64,
+    ) -> Result<Send>;
 }
@@ -37,6 +37,7 @@ super::fvm_syscalls! {
     /// |----------------------|------------------------------------------------|
     /// | [`IllegalOperation`] | actor has been deleted                         |
     /// | [`NotFound`]         | specified root CID is not in the reachable set |
+    /// | [`ReadOnly`]         | the actor is executing in read-only mode       |
     pub fn set_root(cid: *const u8) -> Result<()>;
 
     /// Gets the current balance for the calling actor.
@@ -61,5 +62,6 @@ super::fvm_syscalls! {
     /// | [`NotFound`]        | beneficiary isn't found                                        |
     /// | [`Forbidden`]       | beneficiary is not allowed (usually means beneficiary is self) |
     /// | [`IllegalArgument`] | if the passed address buffer isn't valid, in memory, etc.      |
+    /// | [`ReadOnly`]        | the actor is executing in read-only mode                       |
     pub fn self_destruct(addr_off: *const u8, addr_len: u32) -> Result<()>;
 }
@@ -2,6 +2,8 @@
 
 #[doc(inline)]
 pub use fvm_shared::sys::out::vm::MessageContext;
+#[doc(inline)]
+pub use fvm_shared::sys::ContextFlags;
 
 super::fvm_syscalls! {
     module = "vm";
@@ -31,4 +33,11 @@ super::fvm_syscalls! {
     ///
     /// None
     pub fn message_context() -> Result<MessageContext>;
+
+    /// Returns the [`ContextFlags`] of the current call, e.g. whether it is read-only.
+    ///
+    /// # Errors
+    ///
+    /// None
+    pub fn context_flags() -> Result<ContextFlags>;
 }
@@ -0,0 +1,122 @@
+//! Derive macros for `fvm_shared`. Use them through the re-exports in `fvm_shared` (with the
+//! `derive` feature enabled) rather than depending on this crate directly.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::spanned::Spanned;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Lit, Meta, NestedMeta};
+
+/// Implements `fvm_shared::event::Event` for a struct with named fields. See the documentation of
+/// that trait for the supported attributes.
+#[proc_macro_derive(Event, attributes(event))]
+pub fn derive_event(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_event(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// The entry an event field maps to.
+struct EventField {
+    ident: syn::Ident,
+    key: String,
+    flags: Vec<TokenStream2>,
+}
+
+impl EventField {
+    fn parse(field: &syn::Field) -> syn::Result<Self> {
+        let ident = field.ident.clone().expect("named field");
+        let mut key = ident.to_string();
+        let mut flags = Vec::new();
+
+        for attr in field.attrs.iter().filter(|a| a.path.is_ident("event")) {
+            let list = match attr.parse_meta()? {
+                Meta::List(list) => list,
+                meta => return Err(syn::Error::new(meta.span(), "expected #[event(...)]")),
+            };
+            for nested in list.nested {
+                match nested {
+                    NestedMeta::Meta(Meta::Path(path)) => {
+                        let flag = if path.is_ident("indexed") {
+                            quote!(FLAG_INDEXED_ALL)
+                        } else if path.is_ident("indexed_key") {
+                            quote!(FLAG_INDEXED_KEY)
+                        } else if path.is_ident("indexed_value") {
+                            quote!(FLAG_INDEXED_VALUE)
+                        } else {
+                            return Err(syn::Error::new(path.span(), "unknown event attribute"));
+                        };
+                        flags.push(quote!(::fvm_shared::event::Flags::#flag));
+                    }
+                    NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("rename") => {
+                        match nv.lit {
+                            Lit::Str(s) => key = s.value(),
+                            lit => return Err(syn::Error::new(lit.span(), "expected a string")),
+                        }
+                    }
+                    nested => {
+                        return Err(syn::Error::new(nested.span(), "unknown event attribute"))
+                    }
+                }
+            }
+        }
+
+        Ok(Self { ident, key, flags })
+    }
+}
+
+fn expand_event(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new(
+                    input.ident.span(),
+                    "Event can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new(
+                input.ident.span(),
+                "Event can only be derived for structs",
+            ))
+        }
+    };
+    let fields = fields
+        .iter()
+        .map(EventField::parse)
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let entries = fields.iter().map(|EventField { ident, key, flags }| {
+        quote! {
+            ::fvm_shared::event::Entry::new(
+                ::fvm_shared::event::Flags::empty() #(| #flags)*,
+                #key,
+                &self.#ident,
+            )?
+        }
+    });
+    let decoded = fields
+        .iter()
+        .map(|EventField { ident, key, .. }| quote!(#ident: event.decode_value(#key)?));
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::fvm_shared::event::Event for #name #ty_generics #where_clause {
+            fn to_actor_event(
+                &self,
+            ) -> ::std::result::Result<::fvm_shared::event::ActorEvent, ::fvm_shared::event::EventError> {
+                ::std::result::Result::Ok(::fvm_shared::event::ActorEvent::from(::std::vec![#(#entries),*]))
+            }
+
+            fn from_actor_event(
+                event: &::fvm_shared::event::ActorEvent,
+            ) -> ::std::result::Result<Self, ::fvm_shared::event::EventError> {
+                ::std::result::Result::Ok(Self { #(#decoded),* })
+            }
+        }
+    })
+}
@@ -14,6 +14,10 @@ use super::{BLS_PUB_LEN, SECP_PUB_LEN};
 pub enum Error {
     #[error("Unknown address network")]
     UnknownNetwork,
+    #[error("Invalid network prefix {0:?}, expected a lowercase ASCII letter")]
+    InvalidNetworkPrefix(char),
+    #[error("Network prefix {0:?} is reserved for mainnet or testnet")]
+    ReservedNetworkPrefix(char),
     #[error("Unknown address protocol")]
     UnknownProtocol,
     #[error("Invalid address payload")]
@@ -34,6 +38,8 @@ pub enum Error {
     NonIDAddress,
     #[error("Cannot get delegated address from non delegate address")]
     NonDelegatedAddress,
+    #[error("Address has no Ethereum equivalent")]
+    NonEthAddress,
 }
 
 impl From<num::ParseIntError> for Error {
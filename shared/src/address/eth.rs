@@ -0,0 +1,243 @@
+// Copyright 2019-2022 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Conversions between Ethereum addresses and Filecoin addresses.
+//!
+//! Ethereum addresses map to delegated (f4) addresses in the namespace of the Ethereum Address
+//! Manager (EAM), with the 20 byte Ethereum address as the subaddress. Actors that only have an ID
+//! address are represented on the Ethereum side by a "masked" ID address: `0xff`, followed by 11
+//! zero bytes and the big-endian actor ID.
+
+use std::fmt;
+use std::str::FromStr;
+
+use data_encoding::{HEXLOWER, HEXLOWER_PERMISSIVE};
+use multihash::{Code, MultihashDigest};
+
+use super::{Address, Error, Payload};
+use crate::ActorID;
+
+/// The actor ID of the Ethereum Address Manager, which is the namespace of Ethereum f4 addresses.
+pub const EAM_NAMESPACE: ActorID = 10;
+
+/// The length of an Ethereum address.
+pub const ETH_ADDRESS_LEN: usize = 20;
+
+/// The first byte of a masked ID address.
+const MASKED_ID_PREFIX: u8 = 0xff;
+
+/// A 20 byte Ethereum address.
+#[derive(Copy, Clone, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "arb", derive(arbitrary::Arbitrary))]
+pub struct EthAddress(pub [u8; ETH_ADDRESS_LEN]);
+
+impl EthAddress {
+    /// Creates an Ethereum address from a byte slice, which must be exactly 20 bytes long.
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, Error> {
+        bytes
+            .try_into()
+            .map(Self)
+            .map_err(|_| Error::InvalidPayloadLength(bytes.len()))
+    }
+
+    /// Returns the masked ID address of the given actor.
+    pub fn from_id(id: ActorID) -> Self {
+        let mut bytes = [0u8; ETH_ADDRESS_LEN];
+        bytes[0] = MASKED_ID_PREFIX;
+        bytes[12..].copy_from_slice(&id.to_be_bytes());
+        Self(bytes)
+    }
+
+    /// Returns the actor ID if this is a masked ID address.
+    pub fn as_id(&self) -> Option<ActorID> {
+        if self.is_masked_id() {
+            let mut id = [0u8; 8];
+            id.copy_from_slice(&self.0[12..]);
+            Some(ActorID::from_be_bytes(id))
+        } else {
+            None
+        }
+    }
+
+    /// Returns true if this is a masked ID address.
+    pub fn is_masked_id(&self) -> bool {
+        self.0[0] == MASKED_ID_PREFIX && self.0[1..12].iter().all(|&b| b == 0)
+    }
+
+    /// Converts a Filecoin address into an Ethereum address. ID addresses are converted into masked
+    /// ID addresses, and f4 addresses in the EAM namespace into their subaddress. Other addresses
+    /// have no Ethereum equivalent.
+    pub fn from_filecoin_address(addr: &Address) -> Result<Self, Error> {
+        match addr.payload() {
+            Payload::ID(id) => Ok(Self::from_id(*id)),
+            Payload::Delegated(addr) if addr.namespace() == EAM_NAMESPACE => {
+                let eth_addr = Self::from_slice(addr.subaddress())?;
+                // Masked IDs must be represented as ID addresses, not f4 addresses.
+                if eth_addr.is_masked_id() {
+                    return Err(Error::InvalidPayload);
+                }
+                Ok(eth_addr)
+            }
+            _ => Err(Error::NonEthAddress),
+        }
+    }
+
+    /// Converts this Ethereum address into a Filecoin address: an ID address for masked ID
+    /// addresses, and an f4 address in the EAM namespace otherwise.
+    pub fn to_filecoin_address(&self) -> Address {
+        match self.as_id() {
+            Some(id) => Address::new_id(id),
+            None => Address::new_delegated(EAM_NAMESPACE, &self.0)
+                .expect("an Ethereum address is a valid subaddress"),
+        }
+    }
+
+    /// Formats the address as a `0x` prefixed, EIP-55 checksummed hex string.
+    pub fn to_checksum_string(&self) -> String {
+        let hex = HEXLOWER.encode(&self.0);
+        let hash = Code::Keccak256.digest(hex.as_bytes());
+
+        let mut out = String::with_capacity(2 + hex.len());
+        out.push_str("0x");
+        for (i, c) in hex.chars().enumerate() {
+            if checksum_bit(hash.digest(), i) {
+                out.push(c.to_ascii_uppercase());
+            } else {
+                out.push(c);
+            }
+        }
+        out
+    }
+}
+
+/// Returns true if the `i`th hex character should be uppercase, according to EIP-55.
+fn checksum_bit(hash: &[u8], i: usize) -> bool {
+    let nibble = if i % 2 == 0 {
+        hash[i / 2] >> 4
+    } else {
+        hash[i / 2] & 0x0f
+    };
+    nibble >= 8
+}
+
+impl fmt::Display for EthAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_checksum_string())
+    }
+}
+
+impl FromStr for EthAddress {
+    type Err = Error;
+
+    /// Parses a `0x` prefixed hex string. Mixed-case strings must carry a valid EIP-55 checksum,
+    /// while all lowercase and all uppercase strings are accepted as is.
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let hex = s.strip_prefix("0x").ok_or(Error::InvalidPayload)?;
+        if hex.len() != 2 * ETH_ADDRESS_LEN {
+            return Err(Error::InvalidLength);
+        }
+        let bytes = HEXLOWER_PERMISSIVE
+            .decode(hex.as_bytes())
+            .map_err(|_| Error::InvalidPayload)?;
+        let addr = Self::from_slice(&bytes)?;
+
+        let has_lower = hex.chars().any(|c| c.is_ascii_lowercase());
+        let has_upper = hex.chars().any(|c| c.is_ascii_uppercase());
+        if has_lower && has_upper && addr.to_checksum_string()[2..] != *hex {
+            return Err(Error::InvalidChecksum);
+        }
+        Ok(addr)
+    }
+}
+
+impl TryFrom<&Address> for EthAddress {
+    type Error = Error;
+
+    fn try_from(addr: &Address) -> Result<Self, Error> {
+        Self::from_filecoin_address(addr)
+    }
+}
+
+impl From<EthAddress> for Address {
+    fn from(addr: EthAddress) -> Self {
+        addr.to_filecoin_address()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eip55_checksum() {
+        // Test vectors from EIP-55.
+        for s in [
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+            "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+            "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+            "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+        ] {
+            let addr: EthAddress = s.parse().unwrap();
+            assert_eq!(addr.to_string(), s);
+            assert_eq!(s.to_lowercase().parse::<EthAddress>(), Ok(addr));
+            assert_eq!(
+                format!("0x{}", s[2..].to_uppercase()).parse::<EthAddress>(),
+                Ok(addr)
+            );
+
+            // Flip the case of a couple of letters.
+            let bad = s.replacen('a', "A", 1).replacen('b', "B", 1);
+            assert_eq!(bad.parse::<EthAddress>(), Err(Error::InvalidChecksum));
+        }
+
+        assert_eq!(
+            "5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed".parse::<EthAddress>(),
+            Err(Error::InvalidPayload)
+        );
+        assert_eq!(
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeA".parse::<EthAddress>(),
+            Err(Error::InvalidLength)
+        );
+    }
+
+    #[test]
+    fn filecoin_conversion() {
+        let eth_addr: EthAddress = "0xd1220a0cf47c7b9be7a2e6ba89f429762e7b9adb"
+            .parse()
+            .unwrap();
+        let addr = eth_addr.to_filecoin_address();
+        assert_eq!(
+            addr,
+            Address::new_delegated(EAM_NAMESPACE, &eth_addr.0).unwrap()
+        );
+        assert_eq!(EthAddress::try_from(&addr), Ok(eth_addr));
+
+        // Masked ID addresses round-trip through ID addresses.
+        let masked = EthAddress::from_id(1234);
+        assert_eq!(
+            masked.to_string().to_lowercase(),
+            "0xff000000000000000000000000000000000004d2"
+        );
+        assert_eq!(masked.as_id(), Some(1234));
+        assert_eq!(eth_addr.as_id(), None);
+        assert_eq!(Address::from(masked), Address::new_id(1234));
+        assert_eq!(EthAddress::try_from(&Address::new_id(1234)), Ok(masked));
+
+        // Masked ID addresses can't be f4 addresses.
+        let f4_masked = Address::new_delegated(EAM_NAMESPACE, &masked.0).unwrap();
+        assert_eq!(EthAddress::try_from(&f4_masked), Err(Error::InvalidPayload));
+
+        // Other namespaces and protocols have no Ethereum equivalent.
+        let other = Address::new_delegated(EAM_NAMESPACE + 1, &eth_addr.0).unwrap();
+        assert_eq!(EthAddress::try_from(&other), Err(Error::NonEthAddress));
+        let actor = Address::new_actor(b"actor");
+        assert_eq!(EthAddress::try_from(&actor), Err(Error::NonEthAddress));
+
+        // The subaddress must be exactly 20 bytes.
+        let short = Address::new_delegated(EAM_NAMESPACE, &eth_addr.0[1..]).unwrap();
+        assert_eq!(
+            EthAddress::try_from(&short),
+            Err(Error::InvalidPayloadLength(19))
+        );
+    }
+}
@@ -2,6 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 
 mod errors;
+pub mod eth;
 mod network;
 mod payload;
 mod protocol;
@@ -17,7 +18,7 @@ use fvm_ipld_encoding::{strict_bytes, Cbor};
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
 pub use self::errors::Error;
-pub use self::network::{current_network, set_current_network, Network};
+pub use self::network::{current_network, set_current_network, Network, NetworkPrefix};
 pub use self::payload::{DelegatedAddress, Payload};
 pub use self::protocol::Protocol;
 use crate::ActorID;
@@ -177,14 +178,46 @@ impl Address {
             _ => Err(Error::NonIDAddress),
         }
     }
+
+    /// Formats the address for the given network, regardless of the current network.
+    pub fn to_string_for(&self, network: Network) -> String {
+        self.display_for(network).to_string()
+    }
+
+    /// Returns a `Display` adapter that formats the address for the given network, regardless of
+    /// the current network.
+    pub fn display_for(&self, network: Network) -> NetworkAddress<'_> {
+        NetworkAddress {
+            address: self,
+            network,
+        }
+    }
+
+    /// Parses an address of any network, returning the network it belongs to.
+    pub fn parse_with_network(addr: &str) -> Result<(Self, Network), Error> {
+        parse_address(addr)
+    }
 }
 
 impl fmt::Display for Address {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let protocol = self.protocol();
+        fmt::Display::fmt(&self.display_for(current_network()), f)
+    }
+}
+
+/// Formats an address for a specific network. See [`Address::display_for`].
+#[derive(Copy, Clone, Debug)]
+pub struct NetworkAddress<'a> {
+    address: &'a Address,
+    network: Network,
+}
+
+impl fmt::Display for NetworkAddress<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let protocol = self.address.protocol();
 
         // write `fP` where P is the protocol number.
-        write!(f, "{}{}", current_network().to_prefix(), protocol)?;
+        write!(f, "{}{}", self.network.prefix(), protocol)?;
 
         fn write_payload(
             f: &mut fmt::Formatter<'_>,
@@ -208,7 +241,7 @@ impl fmt::Display for Address {
             f.write_str(&ADDRESS_ENCODER.encode(&buf))
         }
 
-        match self.payload() {
+        match self.address.payload() {
             Payload::ID(id) => write!(f, "{}", id),
             Payload::Secp256k1(data) | Payload::Actor(data) => {
                 write_payload(f, protocol, None, data)
@@ -3,20 +3,21 @@
 
 use std::sync::atomic::{AtomicU8, Ordering};
 
-use num_derive::{FromPrimitive, ToPrimitive};
 use num_traits::{FromPrimitive, ToPrimitive};
 
 use super::{Address, Error, MAINNET_PREFIX, TESTNET_PREFIX};
 
-static ATOMIC_NETWORK: AtomicU8 = AtomicU8::new(0);
+/// The prefix byte of the current network.
+static ATOMIC_NETWORK: AtomicU8 = AtomicU8::new(MAINNET_PREFIX.as_bytes()[0]);
 
 /// Network defines the preconfigured networks to use with address encoding
-#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord, FromPrimitive, ToPrimitive)]
-#[repr(u8)]
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
 #[cfg_attr(feature = "arb", derive(arbitrary::Arbitrary))]
 pub enum Network {
-    Mainnet = 0,
-    Testnet = 1,
+    Mainnet,
+    Testnet,
+    /// A network with a custom address prefix, e.g. a devnet.
+    Custom(NetworkPrefix),
 }
 
 impl Default for Network {
@@ -26,25 +27,38 @@ impl Default for Network {
 }
 
 impl Network {
-    /// to_prefix is used to convert the network into a string
-    /// used when converting address to string
-    pub(super) fn to_prefix(self) -> &'static str {
+    /// Creates a network with a custom single-letter address prefix. The prefix must be a
+    /// lowercase ASCII letter other than the mainnet (`f`) and testnet (`t`) prefixes.
+    pub fn custom(prefix: char) -> Result<Self, Error> {
+        NetworkPrefix::new(prefix).map(Network::Custom)
+    }
+
+    /// Returns the prefix used when formatting and parsing addresses of this network.
+    pub fn prefix(&self) -> &str {
         match self {
             Network::Mainnet => MAINNET_PREFIX,
             Network::Testnet => TESTNET_PREFIX,
+            Network::Custom(prefix) => prefix.as_str(),
         }
     }
 
     /// from_prefix is used to convert the network from a string
     /// used when parsing
     pub(super) fn from_prefix(s: &str) -> Result<Self, Error> {
-        match s {
-            MAINNET_PREFIX => Ok(Network::Mainnet),
-            TESTNET_PREFIX => Ok(Network::Testnet),
+        match s.as_bytes() {
+            &[b] => Self::from_prefix_byte(b),
             _ => Err(Error::UnknownNetwork),
         }
     }
 
+    fn from_prefix_byte(b: u8) -> Result<Self, Error> {
+        match b {
+            b'f' => Ok(Network::Mainnet),
+            b't' => Ok(Network::Testnet),
+            _ => Self::custom(b as char).map_err(|_| Error::UnknownNetwork),
+        }
+    }
+
     /// Parse an address belonging to this network.
     pub fn parse_address(self, addr: &str) -> Result<Address, Error> {
         let (addr, network) = super::parse_address(addr)?;
@@ -55,9 +69,68 @@ impl Network {
     }
 }
 
+/// Converts the numeric codes of the preconfigured networks: 0 for mainnet and 1 for testnet.
+impl FromPrimitive for Network {
+    fn from_i64(n: i64) -> Option<Self> {
+        u64::try_from(n).ok().and_then(Self::from_u64)
+    }
+
+    fn from_u64(n: u64) -> Option<Self> {
+        match n {
+            0 => Some(Network::Mainnet),
+            1 => Some(Network::Testnet),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the numeric codes of the preconfigured networks. Custom networks have no code.
+impl ToPrimitive for Network {
+    fn to_i64(&self) -> Option<i64> {
+        self.to_u64().map(|n| n as i64)
+    }
+
+    fn to_u64(&self) -> Option<u64> {
+        match self {
+            Network::Mainnet => Some(0),
+            Network::Testnet => Some(1),
+            Network::Custom(_) => None,
+        }
+    }
+}
+
+/// A custom single-letter address prefix. See [`Network::custom`].
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct NetworkPrefix(u8);
+
+impl NetworkPrefix {
+    /// Creates a custom prefix, returning an error if the prefix is not a lowercase ASCII letter,
+    /// or is reserved for mainnet or testnet.
+    pub fn new(prefix: char) -> Result<Self, Error> {
+        match prefix {
+            'f' | 't' => Err(Error::ReservedNetworkPrefix(prefix)),
+            'a'..='z' => Ok(Self(prefix as u8)),
+            _ => Err(Error::InvalidNetworkPrefix(prefix)),
+        }
+    }
+
+    /// Returns the prefix as a string.
+    pub fn as_str(&self) -> &str {
+        std::str::from_utf8(std::slice::from_ref(&self.0)).expect("prefix is an ASCII letter")
+    }
+}
+
+#[cfg(feature = "arb")]
+impl<'a> arbitrary::Arbitrary<'a> for NetworkPrefix {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let prefix = *u.choose(b"abcdeghijklmnopqrsuvwxyz")?;
+        Ok(Self(prefix))
+    }
+}
+
 /// Gets the current network.
 pub fn current_network() -> Network {
-    Network::from_u8(ATOMIC_NETWORK.load(Ordering::Relaxed)).unwrap_or_default()
+    Network::from_prefix_byte(ATOMIC_NETWORK.load(Ordering::Relaxed)).unwrap_or_default()
 }
 
 /// Sets the default network.
@@ -67,8 +140,11 @@ pub fn current_network() -> Network {
 ///
 /// 1. Change the prefix used when formatting an address as a string.
 /// 2. Change the prefix _accepted_ when parsing an address.
+///
+/// Prefer [`Address::to_string_for`] and [`Network::parse_address`] when working with multiple
+/// networks at once.
 pub fn set_current_network(network: Network) {
-    ATOMIC_NETWORK.store(network.to_u8().unwrap_or_default(), Ordering::Relaxed)
+    ATOMIC_NETWORK.store(network.prefix().as_bytes()[0], Ordering::Relaxed)
 }
 
 #[cfg(test)]
@@ -76,7 +152,7 @@ mod tests {
     use std::str::FromStr;
 
     use super::*;
-    use crate::address::Address;
+    use crate::address::{Address, Error};
 
     #[test]
     fn set_network() {
@@ -102,4 +178,37 @@ mod tests {
         // Networks are relevent for parsing only.
         assert_eq!(addr1, addr2)
     }
+
+    #[test]
+    fn explicit_network() {
+        let addr = Address::new_id(1234);
+        let devnet = Network::custom('d').unwrap();
+
+        assert_eq!(addr.to_string_for(Network::Mainnet), "f01234");
+        assert_eq!(addr.to_string_for(Network::Testnet), "t01234");
+        assert_eq!(addr.to_string_for(devnet), "d01234");
+        assert_eq!(addr.display_for(devnet).to_string(), "d01234");
+
+        assert_eq!(devnet.parse_address("d01234"), Ok(addr));
+        assert_eq!(devnet.parse_address("f01234"), Err(Error::UnknownNetwork));
+        assert_eq!(Address::parse_with_network("d01234"), Ok((addr, devnet)));
+        assert_eq!(
+            Address::parse_with_network("t01234"),
+            Ok((addr, Network::Testnet))
+        );
+
+        assert_eq!(Network::custom('t'), Err(Error::ReservedNetworkPrefix('t')));
+        assert_eq!(Network::custom('D'), Err(Error::InvalidNetworkPrefix('D')));
+    }
+
+    #[test]
+    fn numeric_codes() {
+        assert_eq!(Network::from_u8(0), Some(Network::Mainnet));
+        assert_eq!(Network::from_u8(1), Some(Network::Testnet));
+        assert_eq!(Network::from_u8(2), None);
+        assert_eq!(Network::from_i64(-1), None);
+        assert_eq!(Network::Mainnet.to_u8(), Some(0));
+        assert_eq!(Network::Testnet.to_u8(), Some(1));
+        assert_eq!(Network::custom('d').unwrap().to_u8(), None);
+    }
 }
@@ -129,14 +129,29 @@ pub fn verify(
 
 #[cfg(feature = "crypto")]
 pub mod ops {
+    use std::collections::BTreeMap;
+
+    #[cfg(all(feature = "pairing", not(feature = "blst")))]
+    use bls12_381::{
+        Bls12, G1Affine, G1Projective, G2Affine, G2Prepared, G2Projective, Gt, Scalar,
+    };
     use bls_signatures::{
-        verify_messages, PublicKey as BlsPubKey, Serialize, Signature as BlsSignature,
+        verify_messages, PrivateKey as BlsPrivateKey, PublicKey as BlsPubKey, Serialize,
+        Signature as BlsSignature,
     };
+    #[cfg(feature = "blst")]
+    use blstrs::{Bls12, G1Affine, G1Projective, G2Affine, G2Prepared, G2Projective, Gt, Scalar};
+    use group::prime::PrimeCurveAffine;
+    use group::Group;
     use libsecp256k1::{
-        recover, Error as SecpError, Message, PublicKey, RecoveryId, Signature as EcsdaSignature,
+        recover, sign, Error as SecpError, Message, PublicKey, RecoveryId, SecretKey,
+        Signature as EcsdaSignature,
     };
+    use pairing_lib::{MillerLoopResult, MultiMillerLoop};
 
-    use super::{Error, SECP_SIG_LEN, SECP_SIG_MESSAGE_HASH_SIZE};
+    use super::{
+        Error, SignatureType, BLS_PUB_LEN, BLS_SIG_LEN, SECP_SIG_LEN, SECP_SIG_MESSAGE_HASH_SIZE,
+    };
     use crate::address::{Address, Payload, Protocol};
     use crate::crypto::signature::Signature;
 
@@ -223,6 +238,31 @@ pub mod ops {
         }
     }
 
+    /// Signs data with a secp256k1 key, returning the signature followed by the recovery ID.
+    pub fn sign_secp256k1(key: &SecretKey, data: &[u8]) -> [u8; SECP_SIG_LEN] {
+        // blake2b 256 hash
+        let hash = blake2b_simd::Params::new()
+            .hash_length(32)
+            .to_state()
+            .update(data)
+            .finalize();
+        let hash = hash.as_bytes().try_into().expect("fixed array size");
+
+        let (sig, recovery_id) = sign(&Message::parse(hash), key);
+        let mut signature = [0u8; SECP_SIG_LEN];
+        signature[..64].copy_from_slice(&sig.serialize());
+        signature[64] = recovery_id.serialize();
+        signature
+    }
+
+    /// Signs data with a BLS key.
+    pub fn sign_bls(key: &BlsPrivateKey, data: &[u8]) -> [u8; BLS_SIG_LEN] {
+        key.sign(data)
+            .as_bytes()
+            .try_into()
+            .expect("fixed array size")
+    }
+
     /// Aggregates and verifies bls signatures collectively.
     pub fn verify_bls_aggregate(
         data: &[&[u8]],
@@ -254,6 +294,133 @@ pub mod ops {
         verify_messages(&sig, data, &pks[..])
     }
 
+    /// Aggregates BLS signatures into a single signature.
+    pub fn aggregate_bls_signatures(signatures: &[Signature]) -> Result<Signature, Error> {
+        let sigs = signatures
+            .iter()
+            .map(|sig| {
+                if sig.signature_type() != SignatureType::BLS {
+                    return Err(Error::InvalidSignature(
+                        "cannot aggregate a non-BLS signature".to_owned(),
+                    ));
+                }
+                BlsSignature::from_bytes(sig.bytes())
+                    .map_err(|e| Error::InvalidSignature(e.to_string()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let agg =
+            bls_signatures::aggregate(&sigs).map_err(|e| Error::InvalidSignature(e.to_string()))?;
+        Ok(Signature::new_bls(agg.as_bytes()))
+    }
+
+    /// Aggregates BLS public keys into a single public key, which verifies an aggregate of
+    /// signatures over the _same_ data.
+    pub fn aggregate_bls_public_keys(pub_keys: &[&[u8]]) -> Result<[u8; BLS_PUB_LEN], Error> {
+        let mut agg: Option<G1Projective> = None;
+        for pk in pub_keys {
+            let pk =
+                BlsPubKey::from_bytes(pk).map_err(|e| Error::InvalidSignature(e.to_string()))?;
+            let pk = G1Projective::from(pk);
+            agg = Some(match agg {
+                Some(agg) => agg + pk,
+                None => pk,
+            });
+        }
+        let agg =
+            agg.ok_or_else(|| Error::InvalidSignature("no public keys to aggregate".to_owned()))?;
+        Ok(BlsPubKey::from(agg)
+            .as_bytes()
+            .try_into()
+            .expect("fixed array size"))
+    }
+
+    /// Verifies a batch of BLS signatures, each over its own data and from its own address.
+    ///
+    /// This is much faster than verifying each signature separately. Unlike verifying the
+    /// aggregate of the signatures, each signature is scaled by a pseudo-random factor derived
+    /// from the whole batch, so invalid signatures can't cancel each other out. The same data may
+    /// appear multiple times in the batch.
+    pub fn verify_bls_batch(batch: &[(&[u8], &Address, &Signature)]) -> bool {
+        if batch.is_empty() {
+            return true;
+        }
+
+        // Derive the scaling factors from the whole batch.
+        let mut hasher = blake2b_simd::Params::new().hash_length(32).to_state();
+        for (data, addr, sig) in batch {
+            hasher.update(&(data.len() as u64).to_le_bytes());
+            hasher.update(data);
+            hasher.update(&addr.to_bytes());
+            hasher.update(sig.bytes());
+        }
+        let seed = hasher.finalize();
+
+        // The scaled public keys, summed per distinct data.
+        let mut pub_keys: BTreeMap<&[u8], G1Projective> = BTreeMap::new();
+        let mut agg_sig: Option<G2Projective> = None;
+        for (i, (data, addr, sig)) in batch.iter().enumerate() {
+            if addr.protocol() != Protocol::BLS || sig.signature_type() != SignatureType::BLS {
+                return false;
+            }
+            let (pk, sig) = match (
+                BlsPubKey::from_bytes(&addr.payload_bytes()),
+                BlsSignature::from_bytes(sig.bytes()),
+            ) {
+                (Ok(pk), Ok(sig)) => (G1Projective::from(pk), G2Projective::from(sig)),
+                _ => return false,
+            };
+            if bool::from(Group::is_identity(&pk)) {
+                return false;
+            }
+
+            // Use 128-bit factors: with shorter ones, an invalid batch could pass with a
+            // non-negligible probability.
+            let factor = blake2b_simd::Params::new()
+                .hash_length(16)
+                .to_state()
+                .update(seed.as_bytes())
+                .update(&(i as u64).to_le_bytes())
+                .finalize();
+            let (lo, hi) = factor.as_bytes().split_at(8);
+            let lo = u64::from_le_bytes(lo.try_into().expect("fixed array size"));
+            let hi = u64::from_le_bytes(hi.try_into().expect("fixed array size"));
+            let lo = if lo == 0 && hi == 0 { 1 } else { lo };
+            let shift = Scalar::from(1u64 << 32) * Scalar::from(1u64 << 32);
+            let factor = Scalar::from(hi) * shift + Scalar::from(lo);
+
+            let scaled = sig * factor;
+            agg_sig = Some(match agg_sig {
+                Some(agg) => agg + scaled,
+                None => scaled,
+            });
+            let scaled = pk * factor;
+            pub_keys
+                .entry(*data)
+                .and_modify(|agg| *agg += scaled)
+                .or_insert(scaled);
+        }
+
+        // Check that e(g1, agg_sig) equals the product of e(pub_key, hash(data)) over the distinct
+        // data. Signatures over the same data are verified together, as the scaling factors keep
+        // invalid signatures from cancelling each other out. As they are derived from the batch
+        // itself, they don't protect against rogue public keys.
+        let agg_sig = G2Affine::from(agg_sig.expect("batch is not empty"));
+        let mut terms: Vec<(G1Affine, G2Prepared)> = pub_keys
+            .into_iter()
+            .map(|(data, pk)| {
+                let hash = G2Affine::from(bls_signatures::hash(data));
+                (G1Affine::from(pk), G2Prepared::from(hash))
+            })
+            .collect();
+        terms.push((
+            -<G1Affine as PrimeCurveAffine>::generator(),
+            G2Prepared::from(agg_sig),
+        ));
+        let terms: Vec<_> = terms.iter().map(|(pk, hash)| (pk, hash)).collect();
+        let result = <Bls12 as MultiMillerLoop>::multi_miller_loop(&terms);
+        MillerLoopResult::final_exponentiation(&result) == <Gt as Group>::identity()
+    }
+
     /// Return the public key used for signing a message given it's signing bytes hash and signature.
     pub fn recover_secp_public_key(
         hash: &[u8; SECP_SIG_MESSAGE_HASH_SIZE],
@@ -315,7 +482,10 @@ mod tests {
 
     use super::ops::recover_secp_public_key;
     use super::*;
-    use crate::crypto::signature::ops::{ecrecover, verify_bls_aggregate};
+    use crate::crypto::signature::ops::{
+        aggregate_bls_public_keys, aggregate_bls_signatures, ecrecover, verify_bls_aggregate,
+        verify_bls_batch, verify_bls_sig,
+    };
     use crate::Address;
 
     #[test]
@@ -351,6 +521,83 @@ mod tests {
         ),);
     }
 
+    #[test]
+    fn bls_aggregation() {
+        let rng = &mut ChaCha8Rng::seed_from_u64(12);
+
+        let keys: Vec<PrivateKey> = (0..5).map(|_| PrivateKey::generate(&mut *rng)).collect();
+        let pub_keys: Vec<Vec<u8>> = keys.iter().map(|k| k.public_key().as_bytes()).collect();
+        let pub_keys: Vec<&[u8]> = pub_keys.iter().map(|pk| &pk[..]).collect();
+
+        // Distinct data for every signer.
+        let data: Vec<Vec<u8>> = (0..5u8).map(|i| vec![i; 32]).collect();
+        let data: Vec<&[u8]> = data.iter().map(|d| &d[..]).collect();
+        let sigs: Vec<Signature> = keys
+            .iter()
+            .zip(&data)
+            .map(|(k, d)| Signature::new_bls(k.sign(d).as_bytes()))
+            .collect();
+        let agg = aggregate_bls_signatures(&sigs).unwrap();
+        assert!(verify_bls_aggregate(&data, &pub_keys, &agg));
+        assert!(!verify_bls_aggregate(&data[1..], &pub_keys[1..], &agg));
+
+        // The same data for every signer.
+        let same_data = b"same data";
+        let sigs: Vec<Signature> = keys
+            .iter()
+            .map(|k| Signature::new_bls(k.sign(same_data).as_bytes()))
+            .collect();
+        let agg = aggregate_bls_signatures(&sigs).unwrap();
+        let agg_pub_key = aggregate_bls_public_keys(&pub_keys).unwrap();
+        let agg_addr = Address::new_bls(&agg_pub_key).unwrap();
+        verify_bls_sig(agg.bytes(), same_data, &agg_addr).unwrap();
+
+        assert!(aggregate_bls_public_keys(&[]).is_err());
+        assert!(aggregate_bls_signatures(&[Signature::new_secp256k1(vec![0; 65])]).is_err());
+    }
+
+    #[test]
+    fn bls_batch_verify() {
+        let rng = &mut ChaCha8Rng::seed_from_u64(13);
+
+        let keys: Vec<PrivateKey> = (0..6).map(|_| PrivateKey::generate(&mut *rng)).collect();
+        let addrs: Vec<Address> = keys
+            .iter()
+            .map(|k| Address::new_bls(&k.public_key().as_bytes()).unwrap())
+            .collect();
+        // Two signers sign the same data.
+        let data: Vec<Vec<u8>> = (0..6u8).map(|i| vec![i / 2 * 2; 16]).collect();
+        let sigs: Vec<Signature> = keys
+            .iter()
+            .zip(&data)
+            .map(|(k, d)| Signature::new_bls(k.sign(d).as_bytes()))
+            .collect();
+
+        let batch = |sigs: &[Signature]| -> bool {
+            let batch: Vec<_> = data
+                .iter()
+                .zip(&addrs)
+                .zip(sigs)
+                .map(|((d, a), s)| (&d[..], a, s))
+                .collect();
+            verify_bls_batch(&batch)
+        };
+        assert!(batch(&sigs));
+        assert!(verify_bls_batch(&[]));
+
+        // A bad signature fails the batch.
+        let mut bad = sigs.clone();
+        bad[3] = sigs[4].clone();
+        assert!(!batch(&bad));
+
+        // Swapped signatures add up to the same aggregate, but fail the batch.
+        let mut swapped = sigs.clone();
+        swapped.swap(0, 1);
+        let swapped_agg = aggregate_bls_signatures(&swapped).unwrap();
+        assert_eq!(swapped_agg, aggregate_bls_signatures(&sigs).unwrap());
+        assert!(!batch(&swapped));
+    }
+
     #[test]
     fn recover_pubkey() {
         let rng = &mut ChaCha8Rng::seed_from_u64(8);
@@ -417,6 +664,9 @@ pub enum Error {
     /// Provided public key is not understood
     #[error("Invalid generated pub key to create address: {0}")]
     InvalidPubKey(#[from] AddressError),
+    /// Provided signature or public key can't be decoded or combined
+    #[error("Invalid signature: {0}")]
+    InvalidSignature(String),
 }
 
 impl From<Box<dyn error::Error>> for Error {
@@ -75,8 +75,15 @@ impl ExitCode {
     pub const SYS_ASSERTION_FAILED: ExitCode = ExitCode::new(10);
     /// Indicates the actor returned a block handle that doesn't exist
     pub const SYS_MISSING_RETURN: ExitCode = ExitCode::new(11);
-    // pub const SYS_RESERVED_12: ExitCode = ExitCode::new(12);
-    // pub const SYS_RESERVED_13: ExitCode = ExitCode::new(13);
+    /// Indicates the message receiver exceeded the Wasm stack limit. Only used when the FVM is
+    /// configured to report exceeded resource limits through exit codes.
+    pub const SYS_STACK_LIMIT_EXCEEDED: ExitCode = ExitCode::new(12);
+    /// Indicates the message receiver couldn't be instantiated within the Wasm instance or
+    /// execution memory limit. Only used when the FVM is configured to report exceeded resource
+    /// limits through exit codes.
+    pub const SYS_MEMORY_LIMIT_EXCEEDED: ExitCode = ExitCode::new(13);
+    // Exceeding the call depth or block limits has no exit code of its own, and is only reported
+    // through the FVM's `Cause::ResourceLimitExceeded` backtrace cause.
     // pub const SYS_RESERVED_14: ExitCode = ExitCode::new(14);
     // pub const SYS_RESERVED_15: ExitCode = ExitCode::new(15);
 
@@ -147,6 +154,8 @@ pub enum ErrorNumber {
     Forbidden = 11,
     /// The passed buffer is too small.
     BufferTooSmall = 12,
+    /// The actor is executing in a read-only context and tried to mutate state.
+    ReadOnly = 13,
 }
 
 impl std::fmt::Display for ErrorNumber {
@@ -165,6 +174,7 @@ impl std::fmt::Display for ErrorNumber {
             Serialization => "serialization error",
             Forbidden => "operation forbidden",
             BufferTooSmall => "buffer too small",
+            ReadOnly => "execution context is read-only",
         })
     }
 }
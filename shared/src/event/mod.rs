@@ -1,7 +1,9 @@
 use bitflags::bitflags;
 use fvm_ipld_encoding::{Cbor, RawBytes};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use serde_tuple::*;
+use thiserror::Error;
 
 use crate::ActorID;
 
@@ -21,6 +23,21 @@ impl StampedEvent {
     pub fn new(emitter: ActorID, event: ActorEvent) -> Self {
         Self { emitter, event }
     }
+
+    /// Returns the ID of the actor that emitted this event.
+    pub fn emitter(&self) -> ActorID {
+        self.emitter
+    }
+
+    /// Returns the event as emitted by the actor.
+    pub fn event(&self) -> &ActorEvent {
+        &self.event
+    }
+
+    /// Decodes the event into a typed [`Event`].
+    pub fn decode<E: Event>(&self) -> Result<E, EventError> {
+        E::from_actor_event(&self.event)
+    }
 }
 
 /// An event as originally emitted by the actor.
@@ -32,6 +49,20 @@ pub struct ActorEvent {
 
 impl Cbor for ActorEvent {}
 
+impl ActorEvent {
+    /// Returns the first entry with the given key.
+    pub fn get(&self, key: &str) -> Option<&Entry> {
+        self.entries.iter().find(|e| e.key == key)
+    }
+
+    /// Decodes the value of the first entry with the given key.
+    pub fn decode_value<T: DeserializeOwned>(&self, key: &str) -> Result<T, EventError> {
+        self.get(key)
+            .ok_or_else(|| EventError::MissingEntry(key.to_owned()))?
+            .decode_value()
+    }
+}
+
 impl From<Vec<Entry>> for ActorEvent {
     fn from(entries: Vec<Entry>) -> Self {
         Self { entries }
@@ -62,9 +93,115 @@ pub struct Entry {
 
 impl Cbor for Entry {}
 
-// TODO write macro
-// event!({
-//     "foo" | indexed1 | indexed2 => value1,
-//     "foo" => value2,
-//     "foo" => value3,
-// })
+impl Entry {
+    /// Creates an entry, encoding the value as DAG-CBOR.
+    pub fn new<T: Serialize + ?Sized>(
+        flags: Flags,
+        key: impl Into<String>,
+        value: &T,
+    ) -> Result<Self, EventError> {
+        let key = key.into();
+        match RawBytes::serialize(value) {
+            Ok(value) => Ok(Self { flags, key, value }),
+            Err(e) => Err(EventError::Encoding(key, e)),
+        }
+    }
+
+    /// Decodes the value of this entry.
+    pub fn decode_value<T: DeserializeOwned>(&self) -> Result<T, EventError> {
+        self.value
+            .deserialize()
+            .map_err(|e| EventError::Encoding(self.key.clone(), e))
+    }
+}
+
+/// Errors converting between typed events and [`ActorEvent`]s.
+#[derive(Error, Debug)]
+pub enum EventError {
+    #[error("event has no {0:?} entry")]
+    MissingEntry(String),
+    #[error("failed to encode or decode event entry {0:?}: {1}")]
+    Encoding(String, fvm_ipld_encoding::Error),
+}
+
+/// A typed event that maps to and from the entries of an [`ActorEvent`].
+///
+/// Usually implemented with `#[derive(Event)]` (enabled by the `derive` feature), which maps
+/// each field of a struct to an entry keyed by the field name:
+///
+/// ```
+/// use fvm_shared::event::{Event, Flags};
+///
+/// #[derive(Event, Debug, PartialEq)]
+/// struct Transfer {
+///     #[event(indexed)]
+///     from: u64,
+///     #[event(indexed, rename = "recipient")]
+///     to: u64,
+///     amount: u64,
+/// }
+///
+/// let transfer = Transfer { from: 100, to: 101, amount: 1234 };
+/// let evt = transfer.to_actor_event().unwrap();
+/// assert_eq!(evt.get("recipient").unwrap().flags, Flags::FLAG_INDEXED_ALL);
+/// assert_eq!(Transfer::from_actor_event(&evt).unwrap(), transfer);
+/// ```
+///
+/// Field attributes:
+///
+/// - `indexed`: sets [`Flags::FLAG_INDEXED_ALL`] on the entry.
+/// - `indexed_key`, `indexed_value`: set [`Flags::FLAG_INDEXED_KEY`] or
+///   [`Flags::FLAG_INDEXED_VALUE`] on the entry.
+/// - `rename = "..."`: uses the given key instead of the field name.
+pub trait Event: Sized {
+    /// Encodes the event as an [`ActorEvent`].
+    fn to_actor_event(&self) -> Result<ActorEvent, EventError>;
+
+    /// Decodes the event from an [`ActorEvent`]. Entries are looked up by key, and entries that
+    /// don't correspond to a field are ignored.
+    fn from_actor_event(event: &ActorEvent) -> Result<Self, EventError>;
+}
+
+#[cfg(feature = "derive")]
+pub use fvm_shared_derive::Event;
+
+/// Constructs an [`ActorEvent`] from a list of entries, each with a key, optional flags and a
+/// value to be encoded as DAG-CBOR. The flags are `indexed`, `indexed_key` and `indexed_value`.
+/// Fails with an [`EventError`] if a value fails to encode.
+///
+/// ```
+/// use fvm_shared::event;
+/// use fvm_shared::event::Flags;
+///
+/// let evt = event! {
+///     "type" | indexed => "transfer",
+///     "from" | indexed_value => 100u64,
+///     "amount" => 1234u64,
+/// }
+/// .unwrap();
+/// assert_eq!(evt.entries[0].flags, Flags::FLAG_INDEXED_ALL);
+/// assert_eq!(evt.entries[1].flags, Flags::FLAG_INDEXED_VALUE);
+/// assert_eq!(evt.entries[2].flags, Flags::empty());
+/// assert_eq!(evt.decode_value::<u64>("amount").unwrap(), 1234);
+/// ```
+#[macro_export]
+macro_rules! event {
+    (@flag indexed) => { $crate::event::Flags::FLAG_INDEXED_ALL };
+    (@flag indexed_key) => { $crate::event::Flags::FLAG_INDEXED_KEY };
+    (@flag indexed_value) => { $crate::event::Flags::FLAG_INDEXED_VALUE };
+    ($($key:literal $(| $flag:ident)* => $value:expr),* $(,)?) => {
+        ::std::vec![$(
+            $crate::event::Entry::new(
+                $crate::event::Flags::empty() $(| $crate::event!(@flag $flag))*,
+                $key,
+                &$value,
+            )
+        ),*]
+        .into_iter()
+        .collect::<::std::result::Result<
+            ::std::vec::Vec<$crate::event::Entry>,
+            $crate::event::EventError,
+        >>()
+        .map($crate::event::ActorEvent::from)
+    };
+}
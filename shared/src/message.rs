@@ -2,13 +2,21 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 
 use anyhow::anyhow;
+use cid::Cid;
 use fvm_ipld_encoding::de::{Deserialize, Deserializer};
 use fvm_ipld_encoding::ser::{Serialize, Serializer};
-use fvm_ipld_encoding::{Cbor, RawBytes};
+use fvm_ipld_encoding::tuple::*;
+use fvm_ipld_encoding::{to_vec, Cbor, Error as EncodingError, RawBytes, DAG_CBOR};
+use multihash::{Code, MultihashDigest};
+use thiserror::Error;
 
-use crate::address::Address;
+use crate::address::{Address, Protocol};
+use crate::crypto::signature::{Signature, SignatureType};
 use crate::econ::TokenAmount;
-use crate::MethodNum;
+use crate::{MethodNum, BLOCK_GAS_LIMIT, TOTAL_FILECOIN};
+
+/// The maximum size of a serialized message accepted for inclusion in a block.
+pub const MAX_MESSAGE_SIZE: usize = 64 << 10;
 
 /// Default Unsigned VM message type which includes all data needed for a state transition
 #[cfg_attr(feature = "testing", derive(Default))]
@@ -29,6 +37,13 @@ pub struct Message {
 impl Cbor for Message {}
 
 impl Message {
+    /// Returns the CID of the message's canonical CBOR encoding. This is the data signed by the
+    /// sender, and identifies the message on chain unless it's signed with a non-BLS signature
+    /// (see [`SignedMessage::chain_cid`]).
+    pub fn chain_cid(&self) -> Result<Cid, EncodingError> {
+        cbor_cid(self)
+    }
+
     /// Does some basic checks on the Message to see if the fields are valid.
     pub fn check(self: &Message) -> anyhow::Result<()> {
         if self.gas_limit == 0 {
@@ -39,6 +54,111 @@ impl Message {
         }
         Ok(())
     }
+
+    /// Checks that the message is syntactically valid for inclusion in a block, independent of
+    /// any chain state.
+    pub fn validate(&self, limits: &MessageLimits) -> Result<(), MessageError> {
+        if self.version != 0 {
+            return Err(MessageError::UnsupportedVersion(self.version));
+        }
+
+        if self.to.is_bls_zero_address() {
+            return Err(MessageError::InvalidTo);
+        }
+        // Actor (f2) addresses are derived from actor data rather than keys, so they can't sign.
+        if self.from.protocol() == Protocol::Actor {
+            return Err(MessageError::InvalidFromProtocol(self.from.protocol()));
+        }
+
+        if self.value.is_negative() {
+            return Err(MessageError::NegativeValue);
+        }
+        if self.value > *TOTAL_FILECOIN {
+            return Err(MessageError::ValueExceedsSupply);
+        }
+
+        if self.gas_fee_cap.is_negative() {
+            return Err(MessageError::NegativeGasFeeCap);
+        }
+        if self.gas_premium.is_negative() {
+            return Err(MessageError::NegativeGasPremium);
+        }
+        if self.gas_premium > self.gas_fee_cap {
+            return Err(MessageError::GasFeeCapBelowPremium);
+        }
+
+        if self.gas_limit <= 0 {
+            return Err(MessageError::GasLimitNotPositive(self.gas_limit));
+        }
+        if self.gas_limit > limits.block_gas_limit {
+            return Err(MessageError::GasLimitExceedsBlock {
+                gas_limit: self.gas_limit,
+                block_gas_limit: limits.block_gas_limit,
+            });
+        }
+
+        let size = to_vec(self)
+            .map_err(|e| MessageError::Serialization(e.to_string()))?
+            .len();
+        if size > limits.max_message_size {
+            return Err(MessageError::TooLarge {
+                size,
+                max_size: limits.max_message_size,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Network dependent limits checked by [`Message::validate`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MessageLimits {
+    /// The maximum gas limit of a single message.
+    pub block_gas_limit: i64,
+    /// The maximum size of the serialized message.
+    pub max_message_size: usize,
+}
+
+impl Default for MessageLimits {
+    fn default() -> Self {
+        Self {
+            block_gas_limit: BLOCK_GAS_LIMIT,
+            max_message_size: MAX_MESSAGE_SIZE,
+        }
+    }
+}
+
+/// The reason a message failed [`Message::validate`].
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub enum MessageError {
+    #[error("unsupported message version {0}")]
+    UnsupportedVersion(i64),
+    #[error("invalid 'to' address")]
+    InvalidTo,
+    #[error("messages can't be sent from {0} protocol addresses")]
+    InvalidFromProtocol(Protocol),
+    #[error("'value' field cannot be negative")]
+    NegativeValue,
+    #[error("'value' field cannot be greater than the total filecoin supply")]
+    ValueExceedsSupply,
+    #[error("'gas_fee_cap' field cannot be negative")]
+    NegativeGasFeeCap,
+    #[error("'gas_premium' field cannot be negative")]
+    NegativeGasPremium,
+    #[error("'gas_fee_cap' field cannot be less than 'gas_premium'")]
+    GasFeeCapBelowPremium,
+    #[error("'gas_limit' field must be positive, was {0}")]
+    GasLimitNotPositive(i64),
+    #[error("'gas_limit' field {gas_limit} cannot be greater than the block gas limit {block_gas_limit}")]
+    GasLimitExceedsBlock {
+        gas_limit: i64,
+        block_gas_limit: i64,
+    },
+    #[error("serialized message is {size} bytes, cannot be larger than {max_size}")]
+    TooLarge { size: usize, max_size: usize },
+    #[error("failed to serialize message: {0}")]
+    Serialization(String),
 }
 
 impl Serialize for Message {
@@ -93,3 +213,217 @@ impl<'de> Deserialize<'de> for Message {
         })
     }
 }
+
+/// A message along with its sender's signature over the message CID.
+#[derive(PartialEq, Clone, Debug, Hash, Eq, Serialize_tuple, Deserialize_tuple)]
+pub struct SignedMessage {
+    pub message: Message,
+    pub signature: Signature,
+}
+
+impl Cbor for SignedMessage {}
+
+impl SignedMessage {
+    /// Creates a signed message from a message and an existing signature, verifying that the
+    /// signature is valid for the message's sender.
+    #[cfg(feature = "crypto")]
+    pub fn new_from_parts(message: Message, signature: Signature) -> Result<Self, String> {
+        let msg = Self { message, signature };
+        msg.verify()?;
+        Ok(msg)
+    }
+
+    /// Signs a message with the sender's secp256k1 key.
+    #[cfg(feature = "crypto")]
+    pub fn new_secp256k1(
+        message: Message,
+        key: &libsecp256k1::SecretKey,
+    ) -> Result<Self, crate::crypto::signature::Error> {
+        let cid = message.chain_cid()?;
+        let sig = crate::crypto::signature::ops::sign_secp256k1(key, &cid.to_bytes());
+        Ok(Self {
+            message,
+            signature: Signature::new_secp256k1(sig.to_vec()),
+        })
+    }
+
+    /// Signs a message with the sender's BLS key.
+    #[cfg(feature = "crypto")]
+    pub fn new_bls(
+        message: Message,
+        key: &bls_signatures::PrivateKey,
+    ) -> Result<Self, crate::crypto::signature::Error> {
+        let cid = message.chain_cid()?;
+        let sig = crate::crypto::signature::ops::sign_bls(key, &cid.to_bytes());
+        Ok(Self {
+            message,
+            signature: Signature::new_bls(sig.to_vec()),
+        })
+    }
+
+    /// Checks that the signature is valid for the message's sender, which must be a key address.
+    #[cfg(feature = "crypto")]
+    pub fn verify(&self) -> Result<(), String> {
+        let cid = self.message.chain_cid().map_err(|e| e.to_string())?;
+        self.signature.verify(&cid.to_bytes(), &self.message.from)
+    }
+
+    /// Returns true if the message is signed with a BLS signature.
+    pub fn is_bls(&self) -> bool {
+        self.signature.signature_type() == SignatureType::BLS
+    }
+
+    /// Returns the CID identifying the signed message on chain.
+    ///
+    /// BLS signatures are aggregated in blocks, so BLS signed messages are identified by the CID
+    /// of the unsigned message. All other messages are identified by the CID of the signed
+    /// message.
+    pub fn chain_cid(&self) -> Result<Cid, EncodingError> {
+        if self.is_bls() {
+            self.message.chain_cid()
+        } else {
+            cbor_cid(self)
+        }
+    }
+}
+
+fn cbor_cid<T: Serialize>(obj: &T) -> Result<Cid, EncodingError> {
+    let bytes = to_vec(obj)?;
+    Ok(Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(&bytes)))
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "crypto")]
+    use rand::SeedableRng;
+    #[cfg(feature = "crypto")]
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+
+    fn message(from: Address) -> Message {
+        Message {
+            version: 0,
+            from,
+            to: Address::new_id(1234),
+            sequence: 1,
+            value: TokenAmount::from_atto(100),
+            method_num: 0,
+            params: RawBytes::default(),
+            gas_limit: 1_000_000,
+            gas_fee_cap: TokenAmount::from_atto(100),
+            gas_premium: TokenAmount::from_atto(10),
+        }
+    }
+
+    #[test]
+    fn validate() {
+        let limits = MessageLimits::default();
+        let valid = message(Address::new_id(100));
+        valid.validate(&limits).unwrap();
+
+        let check = |f: fn(&mut Message)| {
+            let mut msg = valid.clone();
+            f(&mut msg);
+            msg.validate(&limits).unwrap_err()
+        };
+        assert_eq!(
+            check(|m| m.version = 1),
+            MessageError::UnsupportedVersion(1)
+        );
+        assert_eq!(
+            check(|m| m.to = *crate::ZERO_ADDRESS),
+            MessageError::InvalidTo
+        );
+        assert_eq!(
+            check(|m| m.from = Address::new_actor(b"actor")),
+            MessageError::InvalidFromProtocol(Protocol::Actor)
+        );
+        assert_eq!(
+            check(|m| m.value = TokenAmount::from_atto(-1)),
+            MessageError::NegativeValue
+        );
+        assert_eq!(
+            check(|m| m.value = &*TOTAL_FILECOIN + TokenAmount::from_atto(1)),
+            MessageError::ValueExceedsSupply
+        );
+        assert_eq!(
+            check(|m| m.gas_fee_cap = TokenAmount::from_atto(-1)),
+            MessageError::NegativeGasFeeCap
+        );
+        assert_eq!(
+            check(|m| m.gas_premium = TokenAmount::from_atto(-1)),
+            MessageError::NegativeGasPremium
+        );
+        assert_eq!(
+            check(|m| m.gas_premium = TokenAmount::from_atto(101)),
+            MessageError::GasFeeCapBelowPremium
+        );
+        assert_eq!(
+            check(|m| m.gas_limit = 0),
+            MessageError::GasLimitNotPositive(0)
+        );
+        assert_eq!(
+            check(|m| m.gas_limit = BLOCK_GAS_LIMIT + 1),
+            MessageError::GasLimitExceedsBlock {
+                gas_limit: BLOCK_GAS_LIMIT + 1,
+                block_gas_limit: BLOCK_GAS_LIMIT,
+            }
+        );
+        assert!(matches!(
+            check(|m| m.params = RawBytes::new(vec![0; MAX_MESSAGE_SIZE])),
+            MessageError::TooLarge {
+                max_size: MAX_MESSAGE_SIZE,
+                ..
+            }
+        ));
+
+        // The premium may be equal to the fee cap, and the value to the total supply.
+        let mut msg = valid.clone();
+        msg.gas_premium = msg.gas_fee_cap.clone();
+        msg.value = TOTAL_FILECOIN.clone();
+        msg.validate(&limits).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "crypto")]
+    fn secp256k1_signed_message() {
+        let rng = &mut ChaCha8Rng::seed_from_u64(8);
+        let key = libsecp256k1::SecretKey::random(rng);
+        let pub_key = libsecp256k1::PublicKey::from_secret_key(&key);
+        let from = Address::new_secp256k1(&pub_key.serialize()).unwrap();
+
+        let msg = SignedMessage::new_secp256k1(message(from), &key).unwrap();
+        msg.verify().unwrap();
+        assert!(!msg.is_bls());
+        assert_ne!(msg.chain_cid().unwrap(), msg.message.chain_cid().unwrap());
+
+        let bytes = msg.marshal_cbor().unwrap();
+        assert_eq!(SignedMessage::unmarshal_cbor(&bytes).unwrap(), msg);
+
+        // Any change to the message invalidates the signature.
+        let mut tampered = msg.clone();
+        tampered.message.sequence += 1;
+        tampered.verify().unwrap_err();
+        SignedMessage::new_from_parts(tampered.message, msg.signature).unwrap_err();
+    }
+
+    #[test]
+    #[cfg(feature = "crypto")]
+    fn bls_signed_message() {
+        let rng = &mut ChaCha8Rng::seed_from_u64(11);
+        let key = bls_signatures::PrivateKey::generate(rng);
+        let from =
+            Address::new_bls(&bls_signatures::Serialize::as_bytes(&key.public_key())).unwrap();
+
+        let msg = SignedMessage::new_bls(message(from), &key).unwrap();
+        msg.verify().unwrap();
+        assert!(msg.is_bls());
+        assert_eq!(msg.chain_cid().unwrap(), msg.message.chain_cid().unwrap());
+
+        let other = Address::new_id(1);
+        let mut wrong_sender = msg.clone();
+        wrong_sender.message.from = other;
+        wrong_sender.verify().unwrap_err();
+    }
+}
@@ -1,6 +1,7 @@
 //! This module contains types exchanged at the syscall layer between actors
 //! (usually through the SDK) and the FVM.
 
+use bitflags::bitflags;
 use num_bigint::TryFromBigIntError;
 
 pub mod out;
@@ -46,6 +47,40 @@ impl<'a> TryFrom<&'a crate::econ::TokenAmount> for TokenAmount {
     }
 }
 
+bitflags! {
+    /// Flags passed to the send syscall.
+    #[derive(Default)]
+    #[repr(transparent)]
+    pub struct SendFlags: u64 {
+        /// Send in "read-only" mode: the callee, and anything it calls, may not mutate state.
+        const READ_ONLY = 0b00000001;
+    }
+}
+
+impl SendFlags {
+    /// Returns true if the send is read-only.
+    pub fn read_only(self) -> bool {
+        self.intersects(Self::READ_ONLY)
+    }
+}
+
+bitflags! {
+    /// Flags describing the context of the current call.
+    #[derive(Default)]
+    #[repr(transparent)]
+    pub struct ContextFlags: u64 {
+        /// The current call, or one of its callers, was sent in read-only mode.
+        const READ_ONLY = 0b00000001;
+    }
+}
+
+impl ContextFlags {
+    /// Returns true if the current call is read-only.
+    pub fn read_only(self) -> bool {
+        self.intersects(Self::READ_ONLY)
+    }
+}
+
 /// An unsafe trait to mark "syscall safe" types. These types must be safe to memcpy to and from
 /// WASM. This means:
 ///
@@ -72,6 +107,8 @@ assert_syscall_safe! {
     i8, i16, i32, i64,
 
     TokenAmount,
+    SendFlags,
+    ContextFlags,
     out::ipld::IpldOpen,
     out::ipld::IpldStat,
     out::send::Send,
@@ -0,0 +1,52 @@
+use fvm_shared::event;
+use fvm_shared::event::{ActorEvent, Event, EventError, Flags, StampedEvent};
+
+#[derive(Event, Debug, PartialEq)]
+struct Transfer {
+    #[event(indexed)]
+    from: u64,
+    #[event(indexed_value, rename = "recipient")]
+    to: u64,
+    amount: String,
+}
+
+#[test]
+fn derive_event() {
+    let transfer = Transfer {
+        from: 100,
+        to: 101,
+        amount: "1234".to_owned(),
+    };
+
+    let evt = transfer.to_actor_event().unwrap();
+    assert_eq!(
+        evt,
+        event! {
+            "from" | indexed => 100u64,
+            "recipient" | indexed_value => 101u64,
+            "amount" => "1234",
+        }
+        .unwrap()
+    );
+    assert_eq!(Transfer::from_actor_event(&evt).unwrap(), transfer);
+
+    // Events are decoded from the receipts by key, ignoring extra entries.
+    let mut entries = evt.entries.clone();
+    entries.reverse();
+    entries.push(event::Entry::new(Flags::empty(), "memo", "hi").unwrap());
+    let stamped = StampedEvent::new(1000, ActorEvent::from(entries));
+    assert_eq!(stamped.emitter(), 1000);
+    assert_eq!(stamped.decode::<Transfer>().unwrap(), transfer);
+
+    let missing = event! { "from" => 100u64, "amount" => "1234" }.unwrap();
+    assert!(matches!(
+        Transfer::from_actor_event(&missing),
+        Err(EventError::MissingEntry(key)) if key == "recipient"
+    ));
+
+    let mistyped = event! { "from" => "100", "recipient" => 101u64, "amount" => "1234" }.unwrap();
+    assert!(matches!(
+        Transfer::from_actor_event(&mistyped),
+        Err(EventError::Encoding(key, _)) if key == "from"
+    ));
+}
@@ -5,7 +5,9 @@ use std::sync::{Arc, Mutex};
 use anyhow::anyhow;
 use cid::Cid;
 use futures::executor::block_on;
-use fvm::call_manager::{CallManager, DefaultCallManager, FinishRet, InvocationResult};
+use fvm::call_manager::{
+    CallManager, DefaultCallManager, FinishRet, InvocationResult, ResourceLimitExceeded,
+};
 use fvm::gas::{Gas, GasTracker, PriceList};
 use fvm::kernel::*;
 use fvm::machine::limiter::ExecMemory;
@@ -13,6 +15,7 @@ use fvm::machine::{
     DefaultMachine, Engine, Machine, MachineContext, Manifest, MultiEngine, NetworkConfig,
 };
 use fvm::state_tree::{ActorState, StateTree};
+use fvm::syscalls::InvocationData;
 use fvm::DefaultKernel;
 use fvm_ipld_blockstore::MemoryBlockstore;
 use fvm_ipld_car::load_car_unchecked;
@@ -30,10 +33,11 @@ use fvm_shared::sector::{
     AggregateSealVerifyProofAndInfos, RegisteredSealProof, ReplicaUpdateInfo, SealVerifyInfo,
     WindowPoStVerifyInfo,
 };
+use fvm_shared::sys::SendFlags;
 use fvm_shared::version::NetworkVersion;
 use fvm_shared::{ActorID, MethodNum, TOTAL_FILECOIN};
 use multihash::MultihashGeneric;
-use wasmtime::ResourceLimiter;
+use wasmtime::{Linker, ResourceLimiter};
 
 use crate::externs::TestExterns;
 use crate::vector::{MessageVector, Variant};
@@ -123,7 +127,7 @@ impl TestMachine<Box<DefaultMachine<MemoryBlockstore, TestExterns>>> {
             )
             .unwrap();
 
-        let price_list = machine.context().price_list.clone();
+        let price_list = machine.context().price_list.clone().into_owned();
 
         let machine = TestMachine::<Box<DefaultMachine<_, _>>> {
             machine: Box::new(machine),
@@ -252,11 +256,13 @@ where
         method: MethodNum,
         params: Option<Block>,
         value: &TokenAmount,
+        gas_limit: Option<Gas>,
+        read_only: bool,
     ) -> Result<InvocationResult> {
         // K is the kernel specified by the non intercepted kernel.
         // We wrap that here.
         self.0
-            .send::<TestKernel<K>>(from, to, method, params, value)
+            .send::<TestKernel<K>>(from, to, method, params, value, gas_limit, read_only)
     }
 
     fn with_transaction(
@@ -374,6 +380,7 @@ where
         actor_id: ActorID,
         method: MethodNum,
         value_received: TokenAmount,
+        read_only: bool,
     ) -> Self
     where
         Self: Sized,
@@ -389,6 +396,7 @@ where
                 actor_id,
                 method,
                 value_received,
+                read_only,
             ),
             data,
         )
@@ -397,6 +405,13 @@ where
     fn machine(&self) -> &<Self::CallManager as CallManager>::Machine {
         self.0.machine()
     }
+
+    fn bind_custom_syscalls<O: Kernel>(linker: &mut Linker<InvocationData<O>>) -> anyhow::Result<()>
+    where
+        Self: Sized,
+    {
+        K::bind_custom_syscalls(linker)
+    }
 }
 
 impl<M, C, K> ActorOps for TestKernel<K>
@@ -649,6 +664,10 @@ where
     fn msg_gas_limit(&self) -> u64 {
         self.0.msg_gas_limit()
     }
+
+    fn read_only(&self) -> bool {
+        self.0.read_only()
+    }
 }
 
 impl<M, C, K> NetworkOps for TestKernel<K>
@@ -740,8 +759,11 @@ where
         method: u64,
         params: BlockId,
         value: &TokenAmount,
+        gas_limit: Option<Gas>,
+        flags: SendFlags,
     ) -> Result<SendResult> {
-        self.0.send(recipient, method, params, value)
+        self.0
+            .send(recipient, method, params, value, gas_limit, flags)
     }
 }
 
@@ -835,6 +857,22 @@ where
         self.inner.curr_exec_memory_bytes()
     }
 
+    fn peak_exec_memory_bytes(&self) -> usize {
+        self.inner.peak_exec_memory_bytes()
+    }
+
+    fn memory_limit_exceeded(&self) -> Option<ResourceLimitExceeded> {
+        self.inner.memory_limit_exceeded()
+    }
+
+    fn set_syscall_limit_exceeded(&mut self, limit: ResourceLimitExceeded) {
+        self.inner.set_syscall_limit_exceeded(limit)
+    }
+
+    fn take_syscall_limit_exceeded(&mut self) -> Option<ResourceLimitExceeded> {
+        self.inner.take_syscall_limit_exceeded()
+    }
+
     fn with_stack_frame<T, G, F, R>(t: &mut T, g: G, f: F) -> R
     where
         G: Fn(&mut T) -> &mut Self,
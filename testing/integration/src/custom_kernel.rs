@@ -0,0 +1,383 @@
+use std::marker::PhantomData;
+
+use cid::Cid;
+use fvm::call_manager::CallManager;
+use fvm::gas::{Gas, PriceList};
+use fvm::kernel::*;
+use fvm::syscalls::InvocationData;
+use fvm_shared::address::Address;
+use fvm_shared::clock::ChainEpoch;
+use fvm_shared::consensus::ConsensusFault;
+use fvm_shared::crypto::signature::{
+    SignatureType, SECP_PUB_LEN, SECP_SIG_LEN, SECP_SIG_MESSAGE_HASH_SIZE,
+};
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::event::ActorEvent;
+use fvm_shared::piece::PieceInfo;
+use fvm_shared::randomness::RANDOMNESS_LENGTH;
+use fvm_shared::sector::{
+    AggregateSealVerifyProofAndInfos, RegisteredSealProof, ReplicaUpdateInfo, SealVerifyInfo,
+    WindowPoStVerifyInfo,
+};
+use fvm_shared::sys::SendFlags;
+use fvm_shared::version::NetworkVersion;
+use fvm_shared::{ActorID, MethodNum};
+use multihash::MultihashGeneric;
+use wasmtime::Linker;
+
+/// Custom syscalls bound by a [`CustomKernel`].
+pub trait CustomSyscalls: 'static {
+    /// Binds the syscalls to the linker, see [`Kernel::bind_custom_syscalls`].
+    fn bind<K: Kernel>(linker: &mut Linker<InvocationData<K>>) -> anyhow::Result<()>;
+}
+
+/// No custom syscalls.
+impl CustomSyscalls for () {
+    fn bind<K: Kernel>(_linker: &mut Linker<InvocationData<K>>) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// A kernel that forwards everything to the kernel it wraps, and binds the custom syscalls `S` on
+/// top of the wrapped kernel's own custom syscalls.
+pub struct CustomKernel<K, S = ()>(pub K, PhantomData<fn() -> S>);
+
+impl<K: Kernel, S: CustomSyscalls> Kernel for CustomKernel<K, S> {
+    type CallManager = K::CallManager;
+
+    fn into_inner(self) -> (Self::CallManager, BlockRegistry)
+    where
+        Self: Sized,
+    {
+        self.0.into_inner()
+    }
+
+    fn new(
+        mgr: Self::CallManager,
+        blocks: BlockRegistry,
+        caller: ActorID,
+        actor_id: ActorID,
+        method: MethodNum,
+        value_received: TokenAmount,
+        read_only: bool,
+    ) -> Self
+    where
+        Self: Sized,
+    {
+        CustomKernel(
+            K::new(
+                mgr,
+                blocks,
+                caller,
+                actor_id,
+                method,
+                value_received,
+                read_only,
+            ),
+            PhantomData,
+        )
+    }
+
+    fn machine(&self) -> &<Self::CallManager as CallManager>::Machine {
+        self.0.machine()
+    }
+
+    fn bind_custom_syscalls<O: Kernel>(linker: &mut Linker<InvocationData<O>>) -> anyhow::Result<()>
+    where
+        Self: Sized,
+    {
+        K::bind_custom_syscalls(linker)?;
+        S::bind(linker)
+    }
+}
+
+impl<K: Kernel, S: CustomSyscalls> ActorOps for CustomKernel<K, S> {
+    fn resolve_address(&self, address: &Address) -> Result<ActorID> {
+        self.0.resolve_address(address)
+    }
+
+    fn get_actor_code_cid(&self, id: ActorID) -> Result<Cid> {
+        self.0.get_actor_code_cid(id)
+    }
+
+    fn new_actor_address(&mut self) -> Result<Address> {
+        self.0.new_actor_address()
+    }
+
+    fn create_actor(
+        &mut self,
+        code_id: Cid,
+        actor_id: ActorID,
+        predictable_address: Option<Address>,
+    ) -> Result<()> {
+        self.0.create_actor(code_id, actor_id, predictable_address)
+    }
+
+    #[cfg(feature = "m2-native")]
+    fn install_actor(&mut self, code_id: Cid) -> Result<()> {
+        self.0.install_actor(code_id)
+    }
+
+    fn get_builtin_actor_type(&self, code_cid: &Cid) -> u32 {
+        self.0.get_builtin_actor_type(code_cid)
+    }
+
+    fn get_code_cid_for_type(&self, typ: u32) -> Result<Cid> {
+        self.0.get_code_cid_for_type(typ)
+    }
+
+    fn balance_of(&self, actor_id: ActorID) -> Result<TokenAmount> {
+        self.0.balance_of(actor_id)
+    }
+
+    fn lookup_address(&self, actor_id: ActorID) -> Result<Option<Address>> {
+        self.0.lookup_address(actor_id)
+    }
+}
+
+impl<K: Kernel, S: CustomSyscalls> IpldBlockOps for CustomKernel<K, S> {
+    fn block_open(&mut self, cid: &Cid) -> Result<(BlockId, BlockStat)> {
+        self.0.block_open(cid)
+    }
+
+    fn block_create(&mut self, codec: u64, data: &[u8]) -> Result<BlockId> {
+        self.0.block_create(codec, data)
+    }
+
+    fn block_link(&mut self, id: BlockId, hash_fun: u64, hash_len: u32) -> Result<Cid> {
+        self.0.block_link(id, hash_fun, hash_len)
+    }
+
+    fn block_read(&mut self, id: BlockId, offset: u32, buf: &mut [u8]) -> Result<i32> {
+        self.0.block_read(id, offset, buf)
+    }
+
+    fn block_stat(&mut self, id: BlockId) -> Result<BlockStat> {
+        self.0.block_stat(id)
+    }
+}
+
+impl<K: Kernel, S: CustomSyscalls> CircSupplyOps for CustomKernel<K, S> {
+    fn total_fil_circ_supply(&self) -> Result<TokenAmount> {
+        self.0.total_fil_circ_supply()
+    }
+}
+
+impl<K: Kernel, S: CustomSyscalls> CryptoOps for CustomKernel<K, S> {
+    fn hash(&mut self, code: u64, data: &[u8]) -> Result<MultihashGeneric<64>> {
+        self.0.hash(code, data)
+    }
+
+    fn compute_unsealed_sector_cid(
+        &mut self,
+        proof_type: RegisteredSealProof,
+        pieces: &[PieceInfo],
+    ) -> Result<Cid> {
+        self.0.compute_unsealed_sector_cid(proof_type, pieces)
+    }
+
+    fn verify_signature(
+        &mut self,
+        sig_type: SignatureType,
+        signature: &[u8],
+        signer: &Address,
+        plaintext: &[u8],
+    ) -> Result<bool> {
+        self.0
+            .verify_signature(sig_type, signature, signer, plaintext)
+    }
+
+    fn recover_secp_public_key(
+        &mut self,
+        hash: &[u8; SECP_SIG_MESSAGE_HASH_SIZE],
+        signature: &[u8; SECP_SIG_LEN],
+    ) -> Result<[u8; SECP_PUB_LEN]> {
+        self.0.recover_secp_public_key(hash, signature)
+    }
+
+    fn batch_verify_seals(&mut self, vis: &[SealVerifyInfo]) -> Result<Vec<bool>> {
+        self.0.batch_verify_seals(vis)
+    }
+
+    fn verify_seal(&mut self, vi: &SealVerifyInfo) -> Result<bool> {
+        self.0.verify_seal(vi)
+    }
+
+    fn verify_post(&mut self, vi: &WindowPoStVerifyInfo) -> Result<bool> {
+        self.0.verify_post(vi)
+    }
+
+    fn verify_consensus_fault(
+        &mut self,
+        h1: &[u8],
+        h2: &[u8],
+        extra: &[u8],
+    ) -> Result<Option<ConsensusFault>> {
+        self.0.verify_consensus_fault(h1, h2, extra)
+    }
+
+    fn verify_aggregate_seals(&mut self, agg: &AggregateSealVerifyProofAndInfos) -> Result<bool> {
+        self.0.verify_aggregate_seals(agg)
+    }
+
+    fn verify_replica_update(&mut self, rep: &ReplicaUpdateInfo) -> Result<bool> {
+        self.0.verify_replica_update(rep)
+    }
+}
+
+impl<K: Kernel, S: CustomSyscalls> DebugOps for CustomKernel<K, S> {
+    fn log(&self, msg: String) {
+        self.0.log(msg)
+    }
+
+    fn debug_enabled(&self) -> bool {
+        self.0.debug_enabled()
+    }
+
+    fn store_artifact(&self, name: &str, data: &[u8]) -> Result<()> {
+        self.0.store_artifact(name, data)
+    }
+}
+
+impl<K: Kernel, S: CustomSyscalls> GasOps for CustomKernel<K, S> {
+    fn gas_used(&self) -> Gas {
+        self.0.gas_used()
+    }
+
+    fn charge_gas(&mut self, name: &str, compute: Gas) -> Result<()> {
+        self.0.charge_gas(name, compute)
+    }
+
+    fn price_list(&self) -> &PriceList {
+        self.0.price_list()
+    }
+
+    fn gas_available(&self) -> Gas {
+        self.0.gas_available()
+    }
+}
+
+impl<K: Kernel, S: CustomSyscalls> MessageOps for CustomKernel<K, S> {
+    fn msg_caller(&self) -> ActorID {
+        self.0.msg_caller()
+    }
+
+    fn msg_origin(&self) -> ActorID {
+        self.0.msg_origin()
+    }
+
+    fn msg_receiver(&self) -> ActorID {
+        self.0.msg_receiver()
+    }
+
+    fn msg_method_number(&self) -> MethodNum {
+        self.0.msg_method_number()
+    }
+
+    fn msg_value_received(&self) -> TokenAmount {
+        self.0.msg_value_received()
+    }
+
+    fn msg_gas_premium(&self) -> TokenAmount {
+        self.0.msg_gas_premium()
+    }
+
+    fn msg_gas_limit(&self) -> u64 {
+        self.0.msg_gas_limit()
+    }
+
+    fn read_only(&self) -> bool {
+        self.0.read_only()
+    }
+}
+
+impl<K: Kernel, S: CustomSyscalls> NetworkOps for CustomKernel<K, S> {
+    fn network_epoch(&self) -> ChainEpoch {
+        self.0.network_epoch()
+    }
+
+    fn network_version(&self) -> NetworkVersion {
+        self.0.network_version()
+    }
+
+    fn network_base_fee(&self) -> &TokenAmount {
+        self.0.network_base_fee()
+    }
+
+    fn tipset_timestamp(&self) -> u64 {
+        self.0.tipset_timestamp()
+    }
+
+    fn tipset_cid(&self, epoch: ChainEpoch) -> Result<Cid> {
+        self.0.tipset_cid(epoch)
+    }
+}
+
+impl<K: Kernel, S: CustomSyscalls> RandomnessOps for CustomKernel<K, S> {
+    fn get_randomness_from_tickets(
+        &mut self,
+        personalization: i64,
+        rand_epoch: ChainEpoch,
+        entropy: &[u8],
+    ) -> Result<[u8; RANDOMNESS_LENGTH]> {
+        self.0
+            .get_randomness_from_tickets(personalization, rand_epoch, entropy)
+    }
+
+    fn get_randomness_from_beacon(
+        &mut self,
+        personalization: i64,
+        rand_epoch: ChainEpoch,
+        entropy: &[u8],
+    ) -> Result<[u8; RANDOMNESS_LENGTH]> {
+        self.0
+            .get_randomness_from_beacon(personalization, rand_epoch, entropy)
+    }
+}
+
+impl<K: Kernel, S: CustomSyscalls> SelfOps for CustomKernel<K, S> {
+    fn root(&self) -> Result<Cid> {
+        self.0.root()
+    }
+
+    fn set_root(&mut self, root: Cid) -> Result<()> {
+        self.0.set_root(root)
+    }
+
+    fn current_balance(&self) -> Result<TokenAmount> {
+        self.0.current_balance()
+    }
+
+    fn self_destruct(&mut self, beneficiary: &Address) -> Result<()> {
+        self.0.self_destruct(beneficiary)
+    }
+}
+
+impl<K: Kernel, S: CustomSyscalls> SendOps for CustomKernel<K, S> {
+    fn send(
+        &mut self,
+        recipient: &Address,
+        method: u64,
+        params: BlockId,
+        value: &TokenAmount,
+        gas_limit: Option<Gas>,
+        flags: SendFlags,
+    ) -> Result<SendResult> {
+        self.0
+            .send(recipient, method, params, value, gas_limit, flags)
+    }
+}
+
+impl<K: Kernel, S: CustomSyscalls> LimiterOps for CustomKernel<K, S> {
+    type Limiter = K::Limiter;
+
+    fn limiter_mut(&mut self) -> &mut Self::Limiter {
+        self.0.limiter_mut()
+    }
+}
+
+impl<K: Kernel, S: CustomSyscalls> EventOps for CustomKernel<K, S> {
+    fn emit_event(&mut self, evt: ActorEvent) -> Result<()> {
+        self.0.emit_event(evt)
+    }
+}
@@ -1,5 +1,6 @@
 mod builtin;
 pub mod bundle;
+pub mod custom_kernel;
 pub mod dummy;
 pub mod error;
 pub mod tester;
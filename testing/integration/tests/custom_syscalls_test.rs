@@ -0,0 +1,181 @@
+mod bundles;
+use bundles::*;
+use fvm::call_manager::backtrace::Cause;
+use fvm::call_manager::DefaultCallManager;
+use fvm::executor::{ApplyFailure, ApplyKind, ApplyRet, DefaultExecutor, Executor};
+use fvm::gas::Gas;
+use fvm::kernel::Kernel;
+use fvm::machine::DefaultMachine;
+use fvm::syscalls::{BindSyscall, Context, InvocationData};
+use fvm::trace::ExecutionEvent;
+use fvm::{syscall_error, DefaultKernel};
+use fvm_integration_tests::custom_kernel::{CustomKernel, CustomSyscalls};
+use fvm_integration_tests::dummy::DummyExterns;
+use fvm_ipld_blockstore::MemoryBlockstore;
+use fvm_shared::address::Address;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::error::{ErrorNumber, ExitCode};
+use fvm_shared::message::Message;
+use fvm_shared::state::StateTreeVersion;
+use fvm_shared::version::NetworkVersion;
+use num_traits::Zero;
+use wabt::wat2wasm;
+use wasmtime::Linker;
+
+/// The gas charged by the `custom::double` syscall.
+const DOUBLE_GAS: i64 = 1234;
+
+/// Doubles `value`, failing with `IllegalArgument` if it overflows.
+fn double(context: Context<'_, impl Kernel>, value: u32) -> fvm::kernel::Result<u32> {
+    context
+        .kernel
+        .charge_gas("OnDouble", Gas::new(DOUBLE_GAS))?;
+    value
+        .checked_mul(2)
+        .ok_or_else(|| syscall_error!(IllegalArgument; "{} is too large to double", value).into())
+}
+
+/// Binds the custom `custom::double` syscall.
+struct Double;
+
+impl CustomSyscalls for Double {
+    fn bind<K: Kernel>(linker: &mut Linker<InvocationData<K>>) -> anyhow::Result<()> {
+        linker.bind("custom", "double", double)?;
+        Ok(())
+    }
+}
+
+/// Tries to replace the built-in `vm::abort` syscall.
+struct Shadow;
+
+impl CustomSyscalls for Shadow {
+    fn bind<K: Kernel>(linker: &mut Linker<InvocationData<K>>) -> anyhow::Result<()> {
+        linker.bind("vm", "abort", double)?;
+        Ok(())
+    }
+}
+
+type BaseKernel = DefaultKernel<DefaultCallManager<DefaultMachine<MemoryBlockstore, DummyExterns>>>;
+
+/// Doubles 21 and checks the result, then tries to double 2^31 and traps.
+const WAT: &str = r#"(module
+  (import "custom" "double" (func $double (param i32 i32) (result i32)))
+  (import "vm" "abort" (func $abort (param i32 i32 i32) (result i32)))
+  (memory (export "memory") 1)
+  (func (export "invoke") (param $x i32) (result i32)
+    (if (call $double (i32.const 0) (i32.const 21))
+      (then (drop (call $abort (i32.const 1000) (i32.const 0) (i32.const 0)))))
+    (if (i32.ne (i32.load (i32.const 0)) (i32.const 42))
+      (then (drop (call $abort (i32.const 1001) (i32.const 0) (i32.const 0)))))
+    (drop (call $double (i32.const 0) (i32.const 0x80000000)))
+    unreachable))"#;
+
+/// Executes a message to an actor running [`WAT`] on the given kernel.
+fn exec_custom_syscalls<K>() -> ApplyRet
+where
+    K: Kernel<CallManager = DefaultCallManager<DefaultMachine<MemoryBlockstore, DummyExterns>>>,
+{
+    // Instantiate tester
+    let mut tester = new_tester(
+        NetworkVersion::V18,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let [(_sender_id, sender_address)] = tester.create_accounts().unwrap();
+
+    // Set actor
+    let state_cid = tester.set_state(&[(); 0]).unwrap();
+    let actor_address = Address::new_id(10000);
+    tester
+        .set_actor_from_bin(
+            &wat2wasm(WAT).unwrap(),
+            state_cid,
+            actor_address,
+            TokenAmount::zero(),
+        )
+        .unwrap();
+
+    // Instantiate machine, and run it with our kernel.
+    tester.instantiate_machine(DummyExterns).unwrap();
+    let machine = tester.executor.unwrap().into_machine().unwrap();
+    let mut executor = DefaultExecutor::<K>::new(machine);
+
+    let message = Message {
+        from: sender_address,
+        to: actor_address,
+        gas_limit: 1000000000,
+        method_num: 1,
+        ..Message::default()
+    };
+
+    executor
+        .execute_message(message, ApplyKind::Explicit, 100)
+        .unwrap()
+}
+
+#[test]
+fn custom_syscalls() {
+    check_custom_syscalls(exec_custom_syscalls::<CustomKernel<BaseKernel, Double>>());
+}
+
+#[test]
+fn custom_syscalls_of_wrapped_kernel() {
+    // Kernels wrapping a kernel keep the wrapped kernel's custom syscalls.
+    check_custom_syscalls(exec_custom_syscalls::<
+        CustomKernel<CustomKernel<BaseKernel, Double>>,
+    >());
+}
+
+/// Checks the outcome of running [`WAT`] with the `custom::double` syscall bound.
+fn check_custom_syscalls(res: ApplyRet) {
+    // The actor trapped after the second call failed.
+    assert_eq!(res.msg_receipt.exit_code, ExitCode::SYS_ILLEGAL_INSTRUCTION);
+
+    // Both calls were charged for.
+    let charges = res
+        .exec_trace
+        .iter()
+        .filter(|evt| {
+            matches!(evt, ExecutionEvent::GasCharge(charge)
+                if charge.name == "OnDouble" && charge.compute_gas == Gas::new(DOUBLE_GAS))
+        })
+        .count();
+    assert_eq!(charges, 2);
+
+    // The failure was recorded as the cause of the trap.
+    let cause = match &res.failure_info {
+        Some(ApplyFailure::MessageBacktrace(bt)) => bt.cause.as_ref(),
+        _ => None,
+    };
+    assert!(
+        matches!(
+            cause,
+            Some(Cause::Syscall {
+                module: "custom",
+                function: "double",
+                error: ErrorNumber::IllegalArgument,
+                ..
+            })
+        ),
+        "unexpected cause: {:?}",
+        cause
+    );
+}
+
+#[test]
+fn custom_syscalls_cannot_shadow_builtins() {
+    // Creating the linker fails, so the actor can't be instantiated.
+    let res = exec_custom_syscalls::<CustomKernel<CustomKernel<BaseKernel, Double>, Shadow>>();
+    assert_eq!(res.msg_receipt.exit_code, ExitCode::SYS_ASSERTION_FAILED);
+    let cause = match &res.failure_info {
+        Some(ApplyFailure::MessageBacktrace(bt)) => bt.cause.as_ref(),
+        _ => None,
+    };
+    assert!(
+        matches!(cause, Some(Cause::Fatal { error_msg, .. }) if error_msg.contains("abort")),
+        "unexpected cause: {:?}",
+        cause
+    );
+}
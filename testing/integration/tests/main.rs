@@ -8,11 +8,15 @@ use fil_hello_world_actor::WASM_BINARY as HELLO_BINARY;
 use fil_ipld_actor::WASM_BINARY as IPLD_BINARY;
 use fil_stack_overflow_actor::WASM_BINARY as OVERFLOW_BINARY;
 use fil_syscall_actor::WASM_BINARY as SYSCALL_BINARY;
-use fvm::executor::{ApplyKind, Executor, ThreadedExecutor};
+use fvm::call_manager::backtrace::Cause;
+use fvm::executor::{ApplyFailure, ApplyKind, ApplyRet, Executor, ThreadedExecutor};
+use fvm::machine::capabilities::{Capabilities, SyscallGroups};
+use fvm::machine::NetworkConfig;
 use fvm_integration_tests::dummy::DummyExterns;
 use fvm_integration_tests::tester::{Account, IntegrationExecutor};
 use fvm_ipld_blockstore::{Blockstore, MemoryBlockstore};
 use fvm_ipld_encoding::tuple::*;
+use fvm_ipld_encoding::RawBytes;
 use fvm_shared::address::Address;
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::error::{ErrorNumber, ExitCode};
@@ -77,6 +81,68 @@ fn hello_world() {
     assert_eq!(res.msg_receipt.exit_code.value(), 16)
 }
 
+#[test]
+fn invalid_messages() {
+    // Instantiate tester
+    let mut tester = new_tester(
+        NetworkVersion::V18,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let sender: [Account; 1] = tester.create_accounts().unwrap();
+
+    // Instantiate machine
+    tester.instantiate_machine(DummyExterns).unwrap();
+
+    let mut executor = ThreadedExecutor(tester.executor.unwrap());
+
+    // Messages that can't be included in a block fail with a receipt rather than an error.
+    let cases = [
+        (
+            Message {
+                from: Address::new_actor(b"actor"),
+                to: sender[0].1,
+                gas_limit: 10_000_000,
+                ..Message::default()
+            },
+            ExitCode::SYS_SENDER_INVALID,
+        ),
+        (
+            Message {
+                from: sender[0].1,
+                to: sender[0].1,
+                gas_limit: 10_000_000,
+                gas_premium: TokenAmount::from_atto(2),
+                gas_fee_cap: TokenAmount::from_atto(1),
+                ..Message::default()
+            },
+            ExitCode::SYS_SENDER_STATE_INVALID,
+        ),
+    ];
+    for (message, code) in cases {
+        let res = executor
+            .execute_message(message, ApplyKind::Explicit, 100)
+            .unwrap();
+        assert_eq!(res.msg_receipt.exit_code, code);
+        assert!(matches!(
+            res.failure_info,
+            Some(ApplyFailure::PreValidation(_))
+        ));
+    }
+
+    // A message without a gas limit is still rejected outright.
+    let message = Message {
+        from: sender[0].1,
+        to: sender[0].1,
+        ..Message::default()
+    };
+    executor
+        .execute_message(message, ApplyKind::Explicit, 100)
+        .unwrap_err();
+}
+
 #[test]
 fn ipld() {
     // Instantiate tester
@@ -131,6 +197,79 @@ fn ipld() {
 
 #[test]
 fn syscalls() {
+    let res = exec_syscall_actor(|_, _| ());
+
+    if !res.msg_receipt.exit_code.is_success() {
+        if let Some(info) = res.failure_info {
+            panic!("{}", info)
+        } else {
+            panic!("non-zero exit code {}", res.msg_receipt.exit_code)
+        }
+    }
+}
+
+#[test]
+fn syscall_capabilities() {
+    // The syscall actor creates actors, which requires the CREATE_ACTOR capability once syscalls
+    // are restricted.
+    let res = exec_syscall_actor(|nc, _| {
+        nc.override_capabilities(Capabilities::restricted());
+    });
+    assert_eq!(res.msg_receipt.exit_code, ExitCode::USR_ASSERTION_FAILED);
+    assert_forbidden(&res, "actor", "create_actor");
+
+    let res = exec_syscall_actor(|nc, code_cid| {
+        nc.override_capabilities(Capabilities::restricted())
+            .capabilities
+            .set_code(
+                code_cid,
+                SyscallGroups::BASIC | SyscallGroups::PROOFS | SyscallGroups::CREATE_ACTOR,
+            );
+    });
+    assert_eq!(res.msg_receipt.exit_code, ExitCode::OK);
+
+    // Once restricted, only the init actor may install actors, and only the storage power actor
+    // may batch verify seals.
+    #[allow(unused_mut)]
+    let mut privileged = vec![("crypto", "batch_verify_seals", "i32 i32 i32")];
+    #[cfg(feature = "m2-native")]
+    privileged.push(("actor", "install_actor", "i32"));
+    for (module, name, params) in privileged {
+        let wat = format!(
+            r#"(module
+             (import "{module}" "{name}" (func $syscall (param {params}) (result i32)))
+             (memory (export "memory") 1)
+             (func (export "invoke") (param $x i32) (result i32)
+               (drop (call $syscall {args}))
+               unreachable))"#,
+            args = "(i32.const 0) ".repeat(params.split(' ').count()),
+        );
+        let res = exec_wat(&wat, |nc| {
+            nc.override_capabilities(Capabilities::restricted());
+        });
+        assert_eq!(res.msg_receipt.exit_code, ExitCode::SYS_ILLEGAL_INSTRUCTION);
+        assert_forbidden(&res, module, name);
+    }
+}
+
+/// Asserts that the message failed because the given syscall was forbidden.
+fn assert_forbidden(res: &ApplyRet, module: &str, function: &str) {
+    let cause = match &res.failure_info {
+        Some(ApplyFailure::MessageBacktrace(bt)) => bt.cause.as_ref(),
+        _ => None,
+    };
+    assert!(
+        matches!(
+            cause,
+            Some(Cause::Syscall { module: m, function: f, error: ErrorNumber::Forbidden, .. })
+                if *m == module && *f == function
+        ),
+        "unexpected cause: {:?}",
+        cause
+    );
+}
+
+fn exec_syscall_actor(configure: impl FnOnce(&mut NetworkConfig, Cid)) -> ApplyRet {
     // Instantiate tester
     let mut tester = new_tester(
         NetworkVersion::V18,
@@ -150,12 +289,14 @@ fn syscalls() {
     // Set actor
     let actor_address = Address::new_id(10000);
 
-    tester
+    let code_cid = tester
         .set_actor_from_bin(wasm_bin, state_cid, actor_address, TokenAmount::zero())
         .unwrap();
 
     // Instantiate machine
-    tester.instantiate_machine(DummyExterns).unwrap();
+    tester
+        .instantiate_machine_with_config(DummyExterns, |nc| configure(nc, code_cid))
+        .unwrap();
 
     // Send message
     let message = Message {
@@ -166,19 +307,11 @@ fn syscalls() {
         ..Message::default()
     };
 
-    let res = tester
+    tester
         .executor
         .unwrap()
         .execute_message(message, ApplyKind::Explicit, 100)
-        .unwrap();
-
-    if !res.msg_receipt.exit_code.is_success() {
-        if let Some(info) = res.failure_info {
-            panic!("{}", info)
-        } else {
-            panic!("non-zero exit code {}", res.msg_receipt.exit_code)
-        }
-    }
+        .unwrap()
 }
 
 #[test]
@@ -256,6 +389,20 @@ fn native_stack_overflow() {
 }
 
 fn test_exitcode(wat: &str, code: ExitCode) {
+    test_exitcode_with_config(wat, code, |_| ())
+}
+
+fn test_exitcode_with_config(
+    wat: &str,
+    code: ExitCode,
+    configure: impl FnOnce(&mut NetworkConfig),
+) {
+    let res = exec_wat(wat, configure);
+    assert_eq!(res.msg_receipt.exit_code, code)
+}
+
+/// Executes a message to an actor with the given code.
+fn exec_wat(wat: &str, configure: impl FnOnce(&mut NetworkConfig)) -> ApplyRet {
     // Instantiate tester
     let mut tester = new_tester(
         NetworkVersion::V18,
@@ -281,7 +428,9 @@ fn test_exitcode(wat: &str, code: ExitCode) {
         .unwrap();
 
     // Instantiate machine
-    tester.instantiate_machine(DummyExterns).unwrap();
+    tester
+        .instantiate_machine_with_config(DummyExterns, configure)
+        .unwrap();
 
     // Send message
     let message = Message {
@@ -293,11 +442,9 @@ fn test_exitcode(wat: &str, code: ExitCode) {
     };
 
     let mut executor = ThreadedExecutor(tester.executor.unwrap());
-    let res = executor
+    executor
         .execute_message(message, ApplyKind::Explicit, 100)
-        .unwrap();
-
-    assert_eq!(res.msg_receipt.exit_code, code)
+        .unwrap()
 }
 
 #[test]
@@ -338,8 +485,7 @@ fn div_by_zero() {
 
 #[test]
 fn out_of_stack() {
-    test_exitcode(
-        r#"(module
+    const WAT: &str = r#"(module
              (memory (export "memory") 1)
              (func (export "invoke") (param $x i32) (result i32)
                (i64.const 123)
@@ -348,8 +494,46 @@ fn out_of_stack() {
                (i32.const 0))
              (func (param $x i64) (result i64)
                (local.get 0)
-               (call 1)))"#,
+               (call 1)))"#;
+
+    // Exceeding the stack limit has its own exit code when limit exit codes are enabled, but it
+    // can only be detected with stack height reporting.
+    test_exitcode(WAT, ExitCode::SYS_ILLEGAL_INSTRUCTION);
+    test_exitcode_with_config(WAT, ExitCode::SYS_ILLEGAL_INSTRUCTION, |nc| {
+        nc.enable_limit_exit_codes();
+    });
+    test_exitcode_with_config(WAT, ExitCode::SYS_STACK_LIMIT_EXCEEDED, |nc| {
+        nc.enable_limit_exit_codes().enable_wasm_stack_reporting();
+    });
+}
+
+#[test]
+fn out_of_memory() {
+    // A trap after failing to grow the memory isn't necessarily caused by the denied growth, so
+    // it's not attributed to it.
+    test_exitcode_with_config(
+        r#"(module
+             (memory (export "memory") 1)
+             (func (export "invoke") (param $x i32) (result i32)
+               (drop (memory.grow (i32.const 10000)))
+               unreachable))"#,
         ExitCode::SYS_ILLEGAL_INSTRUCTION,
+        |nc| {
+            nc.enable_limit_exit_codes();
+        },
+    );
+
+    // An actor whose initial memory exceeds the per-instance limit can't be instantiated, which is
+    // reported as exceeding the limit when limit exit codes are enabled.
+    test_exitcode_with_config(
+        r#"(module
+             (memory (export "memory") 10000)
+             (func (export "invoke") (param $x i32) (result i32)
+               (i32.const 0)))"#,
+        ExitCode::SYS_MEMORY_LIMIT_EXCEEDED,
+        |nc| {
+            nc.enable_limit_exit_codes();
+        },
     );
 }
 
@@ -488,6 +672,405 @@ fn backtraces() {
     println!("panic backtrace: {}", res.failure_info.unwrap());
 }
 
+#[test]
+fn call_actor() {
+    // Instantiate tester
+    let mut tester = new_tester(
+        NetworkVersion::V18,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let sender: [Account; 1] = tester.create_accounts().unwrap();
+
+    // Set actors
+    let state_cid = tester.set_state(&State::default()).unwrap();
+
+    let ok_address = Address::new_id(10000);
+    let ok_wat = r#"(module
+             (memory (export "memory") 1)
+             (func (export "invoke") (param $x i32) (result i32)
+               (i32.const 0)))"#;
+    tester
+        .set_actor_from_bin(
+            &wat2wasm(ok_wat).unwrap(),
+            state_cid,
+            ok_address,
+            TokenAmount::zero(),
+        )
+        .unwrap();
+
+    let loop_address = Address::new_id(10001);
+    let loop_wat = r#"(module
+             (memory (export "memory") 1)
+             (func (export "invoke") (param $x i32) (result i32)
+               (loop (br 0))
+               (i32.const 1)))"#;
+    tester
+        .set_actor_from_bin(
+            &wat2wasm(loop_wat).unwrap(),
+            state_cid,
+            loop_address,
+            TokenAmount::zero(),
+        )
+        .unwrap();
+
+    // Instantiate machine
+    tester.instantiate_machine(DummyExterns).unwrap();
+
+    let executor = tester.executor.as_mut().unwrap();
+    let sender_before = executor.state_tree().get_actor(&sender[0].1).unwrap();
+
+    let res = executor
+        .call_actor(&sender[0].1, ok_address, 1, RawBytes::default(), 10_000_000)
+        .unwrap();
+    assert_eq!(res.exit_code, ExitCode::OK);
+    assert!(res.return_data.is_none());
+    assert!(res.gas_used > 0);
+
+    let res = executor
+        .call_actor(
+            &sender[0].1,
+            loop_address,
+            1,
+            RawBytes::default(),
+            10_000_000,
+        )
+        .unwrap();
+    assert_eq!(res.exit_code, ExitCode::SYS_OUT_OF_GAS);
+    assert_eq!(res.gas_used, 10_000_000);
+
+    let res = executor
+        .call_actor(
+            &sender[0].1,
+            Address::new_id(20000),
+            1,
+            RawBytes::default(),
+            10_000_000,
+        )
+        .unwrap();
+    assert_eq!(res.exit_code, ExitCode::SYS_INVALID_RECEIVER);
+
+    // The sender's nonce and balance are untouched.
+    let sender_after = executor.state_tree().get_actor(&sender[0].1).unwrap();
+    assert_eq!(sender_before, sender_after);
+}
+
+#[test]
+fn send_gas_limit() {
+    // Instantiate tester
+    let mut tester = new_tester(
+        NetworkVersion::V18,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let sender: [Account; 1] = tester.create_accounts().unwrap();
+
+    // Set actors
+    let state_cid = tester.set_state(&State::default()).unwrap();
+
+    // Sends to the looping actor (f010001) with a gas limit of 1M, and aborts unless the send
+    // fails with SYS_OUT_OF_GAS (7).
+    let caller_address = Address::new_id(10000);
+    let caller_wat = r#"(module
+             ;; send::send_v2
+             (type (;0;) (func (param i32 i32 i32 i64 i32 i64 i64 i64 i64) (result i32)))
+             (import "send" "send_v2" (func $send_v2 (type 0)))
+             ;; vm::abort
+             (type (;1;) (func (param i32 i32 i32) (result i32)))
+             (import "vm" "abort" (func $abort (type 1)))
+             (memory (export "memory") 1)
+             (data (i32.const 100) "\00\91\4e")
+             (func (export "invoke") (param $x i32) (result i32)
+               (local $err i32)
+               (local.set $err
+                 (call $send_v2
+                   (i32.const 0)
+                   (i32.const 100) (i32.const 3)
+                   (i64.const 1)
+                   (i32.const 0)
+                   (i64.const 0) (i64.const 0)
+                   (i64.const 1000000)
+                   (i64.const 0)))
+               (if (local.get $err)
+                 (then (drop (call $abort (i32.const 1000) (i32.const 0) (i32.const 0)))))
+               (if (i32.ne (i32.load (i32.const 0)) (i32.const 7))
+                 (then (drop (call $abort (i32.const 1001) (i32.const 0) (i32.const 0)))))
+               (i32.const 0)))"#;
+    tester
+        .set_actor_from_bin(
+            &wat2wasm(caller_wat).unwrap(),
+            state_cid,
+            caller_address,
+            TokenAmount::zero(),
+        )
+        .unwrap();
+
+    let loop_address = Address::new_id(10001);
+    let loop_wat = r#"(module
+             (memory (export "memory") 1)
+             (func (export "invoke") (param $x i32) (result i32)
+               (loop (br 0))
+               (i32.const 1)))"#;
+    tester
+        .set_actor_from_bin(
+            &wat2wasm(loop_wat).unwrap(),
+            state_cid,
+            loop_address,
+            TokenAmount::zero(),
+        )
+        .unwrap();
+
+    // Instantiate machine
+    tester.instantiate_machine(DummyExterns).unwrap();
+
+    let message = Message {
+        from: sender[0].1,
+        to: caller_address,
+        gas_limit: 10_000_000,
+        method_num: 1,
+        ..Message::default()
+    };
+
+    let mut executor = ThreadedExecutor(tester.executor.unwrap());
+    let res = executor
+        .execute_message(message, ApplyKind::Explicit, 100)
+        .unwrap();
+
+    // The callee ran out of its own gas, but the caller carried on and succeeded without
+    // exhausting the message's gas.
+    assert_eq!(
+        res.msg_receipt.exit_code,
+        ExitCode::OK,
+        "{:?}",
+        res.failure_info
+    );
+    assert!(res.msg_receipt.gas_used >= 1_000_000);
+    assert!(res.msg_receipt.gas_used < 10_000_000);
+}
+
+#[test]
+fn read_only_send() {
+    // Instantiate tester
+    let mut tester = new_tester(
+        NetworkVersion::V18,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let sender: [Account; 1] = tester.create_accounts().unwrap();
+
+    // Set actors
+    let state_cid = tester.set_state(&State::default()).unwrap();
+
+    // Sends to f010001 with the READ_ONLY flag, aborting with the callee's exit code if it fails.
+    let caller_address = Address::new_id(10000);
+    let caller_wat = r#"(module
+             (import "send" "send_v2"
+               (func $send_v2 (param i32 i32 i32 i64 i32 i64 i64 i64 i64) (result i32)))
+             (import "vm" "abort" (func $abort (param i32 i32 i32) (result i32)))
+             (memory (export "memory") 1)
+             (data (i32.const 100) "\00\91\4e")
+             (func (export "invoke") (param $x i32) (result i32)
+               (if (call $send_v2
+                     (i32.const 0)
+                     (i32.const 100) (i32.const 3)
+                     (i64.const 1)
+                     (i32.const 0)
+                     (i64.const 0) (i64.const 0)
+                     (i64.const -1)
+                     (i64.const 1))
+                 (then (drop (call $abort (i32.const 1000) (i32.const 0) (i32.const 0)))))
+               (if (i32.load (i32.const 0))
+                 (then (drop (call $abort (i32.load (i32.const 0)) (i32.const 0) (i32.const 0)))))
+               (i32.const 0)))"#;
+
+    // Tries to set its state root and to transfer value to f010000, aborting unless both fail with
+    // ReadOnly (13). Then, optionally, makes a plain nested send to f010002, which must succeed.
+    let mutator_wat = |nested: bool| {
+        let nested = if nested {
+            r#"(if (call $send
+                     (i32.const 0)
+                     (i32.const 210) (i32.const 3)
+                     (i64.const 1)
+                     (i32.const 0)
+                     (i64.const 0) (i64.const 0))
+                 (then (drop (call $abort (i32.const 1003) (i32.const 0) (i32.const 0)))))
+               (if (i32.load (i32.const 0))
+                 (then (drop (call $abort (i32.load (i32.const 0)) (i32.const 0) (i32.const 0)))))"#
+        } else {
+            ""
+        };
+        format!(
+            r#"(module
+             (import "self" "root" (func $root (param i32 i32 i32) (result i32)))
+             (import "self" "set_root" (func $set_root (param i32) (result i32)))
+             (import "send" "send"
+               (func $send (param i32 i32 i32 i64 i32 i64 i64) (result i32)))
+             (import "vm" "abort" (func $abort (param i32 i32 i32) (result i32)))
+             (memory (export "memory") 1)
+             (data (i32.const 200) "\00\90\4e")
+             (data (i32.const 210) "\00\92\4e")
+             (func (export "invoke") (param $x i32) (result i32)
+               (if (call $root (i32.const 32) (i32.const 64) (i32.const 100))
+                 (then (drop (call $abort (i32.const 1000) (i32.const 0) (i32.const 0)))))
+               (if (i32.ne (call $set_root (i32.const 64)) (i32.const 13))
+                 (then (drop (call $abort (i32.const 1001) (i32.const 0) (i32.const 0)))))
+               (if (i32.ne
+                     (call $send
+                       (i32.const 0)
+                       (i32.const 200) (i32.const 3)
+                       (i64.const 0)
+                       (i32.const 0)
+                       (i64.const 0) (i64.const 1))
+                     (i32.const 13))
+                 (then (drop (call $abort (i32.const 1002) (i32.const 0) (i32.const 0)))))
+               {}
+               (i32.const 0)))"#,
+            nested
+        )
+    };
+
+    let callee_address = Address::new_id(10001);
+    let nested_address = Address::new_id(10002);
+    for (address, wat) in [
+        (caller_address, caller_wat.to_owned()),
+        (callee_address, mutator_wat(true)),
+        (nested_address, mutator_wat(false)),
+    ] {
+        tester
+            .set_actor_from_bin(
+                &wat2wasm(wat).unwrap(),
+                state_cid,
+                address,
+                TokenAmount::from_atto(100),
+            )
+            .unwrap();
+    }
+
+    // Instantiate machine
+    tester.instantiate_machine(DummyExterns).unwrap();
+
+    let message = Message {
+        from: sender[0].1,
+        to: caller_address,
+        gas_limit: 10_000_000,
+        method_num: 1,
+        ..Message::default()
+    };
+
+    let mut executor = ThreadedExecutor(tester.executor.unwrap());
+    let res = executor
+        .execute_message(message, ApplyKind::Explicit, 100)
+        .unwrap();
+
+    // Both the callee and its nested callee saw their mutations fail with ReadOnly.
+    assert_eq!(
+        res.msg_receipt.exit_code,
+        ExitCode::OK,
+        "{:?}",
+        res.failure_info
+    );
+
+    // And nothing was changed.
+    for address in [caller_address, callee_address, nested_address] {
+        let actor = executor
+            .0
+            .state_tree()
+            .get_actor(&address)
+            .unwrap()
+            .unwrap();
+        assert_eq!(actor.state, state_cid);
+        assert_eq!(actor.balance, TokenAmount::from_atto(100));
+    }
+}
+
+#[test]
+fn resource_usage() {
+    // Instantiate tester
+    let mut tester = new_tester(
+        NetworkVersion::V18,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let sender: [Account; 2] = tester.create_accounts().unwrap();
+
+    // Set actors, recursing to the given depth.
+    let state_cid = tester.set_state(&State::default()).unwrap();
+    let recurse_wat = |depth: u32| {
+        format!(
+            r#"(module
+             (memory (export "memory") 2)
+             (func (export "invoke") (param $x i32) (result i32)
+               (call $recurse (i32.const {}))
+               (i32.const 0))
+             (func $recurse (param $n i32)
+               (if (local.get $n)
+                 (then (call $recurse (i32.sub (local.get $n) (i32.const 1)))))))"#,
+            depth
+        )
+    };
+
+    let shallow_address = Address::new_id(10000);
+    let deep_address = Address::new_id(10001);
+    for (address, depth) in [(shallow_address, 1), (deep_address, 100)] {
+        tester
+            .set_actor_from_bin(
+                &wat2wasm(&recurse_wat(depth)).unwrap(),
+                state_cid,
+                address,
+                TokenAmount::zero(),
+            )
+            .unwrap();
+    }
+
+    // Instantiate machine
+    tester
+        .instantiate_machine_with_config(DummyExterns, |nc| {
+            nc.enable_wasm_stack_reporting();
+        })
+        .unwrap();
+
+    let mut executor = ThreadedExecutor(tester.executor.unwrap());
+    let mut exec = |from: Address, to: Address| {
+        let message = Message {
+            from,
+            to,
+            gas_limit: 10_000_000,
+            method_num: 1,
+            ..Message::default()
+        };
+        let res = executor
+            .execute_message(message, ApplyKind::Explicit, 100)
+            .unwrap();
+        assert_eq!(res.msg_receipt.exit_code, ExitCode::OK);
+        res.resource_usage
+    };
+
+    let shallow = exec(sender[0].1, shallow_address);
+    let deep = exec(sender[1].1, deep_address);
+
+    // Each message makes a single invocation, which reaches the message-wide peaks.
+    for (usage, actor_id) in [(&shallow, 10000), (&deep, 10001)] {
+        assert_eq!(usage.invocations.len(), 1);
+        let invocation = &usage.invocations[0];
+        assert_eq!(invocation.actor_id, actor_id);
+        assert_eq!(invocation.method, 1);
+        assert_eq!(invocation.peak, usage.peak);
+        assert_eq!(usage.peak.instance_memory_bytes, 2 << 16);
+        assert_eq!(usage.peak.exec_memory_bytes, 2 << 16);
+    }
+
+    assert!(shallow.peak.wasm_stack > 0);
+    assert!(deep.peak.wasm_stack > shallow.peak.wasm_stack);
+}
+
 #[derive(Default)]
 pub struct FailingBlockstore {
     fail_for: RefCell<HashSet<Cid>>,